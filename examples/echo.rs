@@ -2,12 +2,18 @@
 #![no_main]
 
 use core::fmt::Write;
-use heapless::String;
 use microbit::hal::prelude::*;
 use microbit::hal::uarte::{self, Baudrate, Parity};
 use microbit::Board;
 
-use microbit_v2_examples::{self as _, serial_setup::UartePort};
+use microbit_v2_examples::{
+    self as _,
+    console::{Event, LineEditor},
+    serial_setup::{DmaBuffers, UartePort},
+};
+
+/// Words offered when tab is pressed.
+const WORDS: &[&str] = &["echo", "hello", "help", "micro:bit"];
 
 #[cortex_m_rt::entry]
 fn main() -> ! {
@@ -18,25 +24,23 @@ fn main() -> ! {
         Parity::EXCLUDED,
         Baudrate::BAUD115200,
     );
-    let mut serial = UartePort::new(serial);
-    let mut buffer = String::<32>::new();
+    let dma = cortex_m::singleton!(: DmaBuffers = DmaBuffers::new()).unwrap();
+    let mut serial = UartePort::new(serial, dma);
+    let mut editor = LineEditor::<64, 8>::new();
 
+    editor.prompt(&mut serial).unwrap();
+    nb::block!(serial.flush()).unwrap();
     loop {
-        loop {
-            let byte = nb::block!(serial.read()).unwrap();
-
-            if buffer.push(byte as char).is_err() {
-                write!(serial, "error: buffer full\r\n").unwrap();
-                break;
-            }
-
-            if byte == 13 {
-                write!(serial, "{}\r\n", buffer).unwrap();
-                break;
+        let byte = nb::block!(serial.read()).unwrap();
+        match editor.feed(byte, &mut serial).unwrap() {
+            Some(Event::Line) => {
+                write!(serial, "{}\r\n", editor.line()).unwrap();
+                editor.prompt(&mut serial).unwrap();
             }
+            Some(Event::Cancel) => editor.prompt(&mut serial).unwrap(),
+            Some(Event::Tab) => editor.complete_from(WORDS, &mut serial).unwrap(),
+            None => {}
         }
         nb::block!(serial.flush()).unwrap();
-
-        buffer.clear();
     }
 }
@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+
+use cortex_m::peripheral::NVIC;
+use embassy_executor::Spawner;
+use embedded_io_async::{Read, Write};
+use microbit::{
+    gpio::DisplayPins,
+    hal::uarte::{self, Baudrate, Parity},
+    pac::{self, interrupt, Interrupt},
+    Board,
+};
+
+use microbit_v2_examples::{
+    self as _,
+    display::{AsyncDisplay, Image, MAX_BRIGHTNESS},
+    monotonic::{self, Delay, ExtU64, MonoTimer},
+    serial_setup::{self, DmaBuffers, UartePort},
+};
+
+static DISPLAY: AsyncDisplay = AsyncDisplay::new();
+
+const HEART: Image = [
+    [0, 1, 0, 1, 0],
+    [1, 1, 1, 1, 1],
+    [1, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0],
+    [0, 0, 1, 0, 0],
+];
+
+#[interrupt]
+fn TIMER2() {
+    monotonic::wake::<pac::TIMER2>();
+}
+
+#[interrupt]
+fn UARTE0_UART0() {
+    serial_setup::wake::<pac::UARTE0>();
+}
+
+#[embassy_executor::task]
+async fn refresh(pins: DisplayPins, mut delay: Delay<pac::TIMER2>) {
+    DISPLAY.refresh(pins, &mut delay).await
+}
+
+/// Fade the heart in and out.
+#[embassy_executor::task]
+async fn heartbeat(mut delay: Delay<pac::TIMER2>) {
+    loop {
+        for level in (0..=MAX_BRIGHTNESS).chain((0..MAX_BRIGHTNESS).rev()) {
+            DISPLAY.show(HEART.map(|row| row.map(|led| led * level)));
+            delay.after(60.millis()).await;
+        }
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let board = Board::take().unwrap();
+    let (refresh_delay, heartbeat_delay) = MonoTimer::new(board.TIMER2).into_delays();
+    let serial = uarte::Uarte::new(
+        board.UARTE0,
+        board.uart.into(),
+        Parity::EXCLUDED,
+        Baudrate::BAUD115200,
+    );
+    let dma = cortex_m::singleton!(: DmaBuffers = DmaBuffers::new()).unwrap();
+    let mut serial = UartePort::new(serial, dma);
+    unsafe {
+        NVIC::unmask(Interrupt::TIMER2);
+        NVIC::unmask(Interrupt::UARTE0_UART0);
+    }
+
+    spawner
+        .spawn(refresh(board.display_pins, refresh_delay))
+        .unwrap();
+    spawner.spawn(heartbeat(heartbeat_delay)).unwrap();
+
+    // Echo what is typed while the heart beats
+    let mut buf = [0; 32];
+    loop {
+        let len = serial.read(&mut buf).await.unwrap();
+        serial.write_all(&buf[..len]).await.unwrap();
+        serial.flush().await.unwrap();
+    }
+}
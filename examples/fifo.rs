@@ -0,0 +1,99 @@
+#![no_main]
+#![no_std]
+
+use heapless::spsc::{Consumer, Producer, Queue};
+use lsm303agr::AccelOutputDataRate;
+use microbit::{
+    hal::{
+        gpio::{Floating, Input, Pin},
+        gpiote::Gpiote,
+        twim::Twim,
+    },
+    pac::{self, twim0::frequency::FREQUENCY_A},
+    Board,
+};
+
+use microbit_v2_examples::{
+    self as _,
+    accel_fifo::{AccelFifo, Sample},
+    monotonic::MonoTimer,
+};
+
+const QUEUE_SIZE: usize = 64;
+
+#[rtic::app(device = microbit::pac, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[monotonic(binds = TIMER0, default = true)]
+    type Tnoic = MonoTimer<pac::TIMER0>;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
+        gpiote: Gpiote,
+        fifo: AccelFifo<Twim<pac::TWIM0>>,
+        producer: Producer<'static, Sample, QUEUE_SIZE>,
+        consumer: Consumer<'static, Sample, QUEUE_SIZE>,
+        _int_pin: Pin<Input<Floating>>,
+    }
+
+    #[init(local = [queue: Queue<Sample, QUEUE_SIZE> = Queue::new()])]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+        let mono = MonoTimer::new(board.TIMER0);
+
+        let i2c = Twim::new(board.TWIM0, board.i2c_internal.into(), FREQUENCY_A::K100);
+        let mut fifo = AccelFifo::new(i2c);
+        // 50Hz with an interrupt every 10 samples, the CPU wakes up 5 times a second
+        fifo.configure(AccelOutputDataRate::Hz50, 10).unwrap();
+
+        let int_pin = board.pins.p0_25.into_floating_input().degrade();
+        let gpiote = Gpiote::new(board.GPIOTE);
+        gpiote
+            .channel0()
+            .input_pin(&int_pin)
+            .hi_to_lo()
+            .enable_interrupt();
+
+        let (producer, consumer) = cx.local.queue.split();
+
+        (
+            Shared {},
+            Local {
+                gpiote,
+                fifo,
+                producer,
+                consumer,
+                _int_pin: int_pin,
+            },
+            init::Monotonics(mono),
+        )
+    }
+
+    #[task(binds = GPIOTE, priority = 2, local = [gpiote, fifo, producer])]
+    fn gpiote(cx: gpiote::Context) {
+        let local = cx.local;
+        local.gpiote.reset_events();
+        let count = local.fifo.drain(monotonics::now(), local.producer).unwrap();
+        defmt::debug!("drained {} samples", count);
+    }
+
+    #[idle(local = [consumer])]
+    fn idle(cx: idle::Context) -> ! {
+        loop {
+            match cx.local.consumer.dequeue() {
+                Some(sample) => defmt::info!(
+                    "{}us: x {} y {} z {}",
+                    sample.timestamp.ticks(),
+                    sample.accel.x,
+                    sample.accel.y,
+                    sample.accel.z
+                ),
+                None => cortex_m::asm::wfi(),
+            }
+        }
+    }
+}
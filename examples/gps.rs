@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+use heapless::Vec;
+use microbit::hal::gpio::Level;
+use microbit::hal::uarte::{self, Baudrate, Parity, Uarte};
+use microbit::Board;
+
+use microbit_v2_examples::{
+    self as _,
+    serial_setup::{DmaBuffers, UartePort},
+};
+
+/// Forward the NMEA sentences of a GPS module wired to the edge connector
+/// (module TX on P0, module RX on P1) to the host console.
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    let board = Board::take().unwrap();
+
+    // host console on UARTE0
+    let mut console = {
+        let serial = Uarte::new(
+            board.UARTE0,
+            board.uart.into(),
+            Parity::EXCLUDED,
+            Baudrate::BAUD115200,
+        );
+        let dma = cortex_m::singleton!(: DmaBuffers = DmaBuffers::new()).unwrap();
+        // room for a few whole sentences
+        UartePort::<_, 64, 256>::with_capacity(serial, dma)
+    };
+
+    // gps module on UARTE1
+    let mut gps = {
+        let pins = uarte::Pins {
+            rxd: board.pins.p0_02.into_floating_input().degrade(),
+            txd: board
+                .pins
+                .p0_03
+                .into_push_pull_output(Level::High)
+                .degrade(),
+            cts: None,
+            rts: None,
+        };
+        let serial = Uarte::new(board.UARTE1, pins, Parity::EXCLUDED, Baudrate::BAUD9600);
+        let dma = cortex_m::singleton!(: DmaBuffers = DmaBuffers::new()).unwrap();
+        UartePort::<_, 128, 16>::with_capacity(serial, dma)
+    };
+
+    let mut sentence = Vec::<u8, 96>::new();
+    let mut buf = [0; 16];
+    loop {
+        gps.on_interrupt();
+        console.on_interrupt();
+
+        let len = gps.read_available(&mut buf);
+        for &byte in &buf[..len] {
+            if byte == b'\n' {
+                // All of it or nothing, a partial sentence would corrupt the next
+                if console.tx_free() < sentence.len() + 1 {
+                    defmt::warn!("console too slow, sentence dropped");
+                } else {
+                    console.try_write_all(&sentence).unwrap();
+                    console.try_write_all(b"\n").unwrap();
+                }
+                sentence.clear();
+            } else if sentence.push(byte).is_err() {
+                // Not NMEA, wait for the next line
+                sentence.clear();
+            }
+        }
+
+        // typed commands are passed to the module, e.g. to change its update rate
+        let len = console.read_available(&mut buf);
+        if len > 0 && gps.try_write_all(&buf[..len]).is_err() {
+            write!(console, "error: gps busy\r\n").unwrap();
+        }
+    }
+}
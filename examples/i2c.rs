@@ -2,16 +2,143 @@
 #![no_main]
 
 use core::fmt::Write;
-use core::str;
-use heapless::Vec;
-use lsm303agr::{AccelOutputDataRate, Lsm303agr, MagOutputDataRate};
-use microbit::hal::pac::twim0::frequency::FREQUENCY_A;
+use lsm303agr::interface::I2cInterface;
+use lsm303agr::mode::MagContinuous;
+use lsm303agr::{AccelOutputDataRate, AccelScale, Lsm303agr, MagOutputDataRate};
+use microbit::hal::pac::{twim0::frequency::FREQUENCY_A, TWIM0};
 use microbit::hal::prelude::*;
 use microbit::hal::twim::Twim;
 use microbit::hal::uarte::{Baudrate, Parity, Uarte};
 use microbit::Board;
 
-use microbit_v2_examples::{self as _, serial_setup::UartePort};
+use microbit_v2_examples::{
+    self as _,
+    console::{Event, LineEditor},
+    serial_setup::{DmaBuffers, UartePort},
+    shell::{Args, Command, Error, Shell},
+};
+
+type Sensor = Lsm303agr<I2cInterface<Twim<TWIM0>>, MagContinuous>;
+
+const I2C_ERROR: Error = Error::Failed("i2c error");
+
+const COMMANDS: &[Command<Sensor>] = &[
+    Command {
+        name: "accelerometer",
+        usage: "",
+        help: "read the acceleration in mg",
+        run: accelerometer,
+    },
+    Command {
+        name: "accelerometer rate",
+        usage: "<1|10|25|50|100|200|400>",
+        help: "set the accelerometer data rate in Hz",
+        run: accelerometer_rate,
+    },
+    Command {
+        name: "accelerometer scale",
+        usage: "<2|4|8|16>",
+        help: "set the accelerometer full scale in g",
+        run: accelerometer_scale,
+    },
+    Command {
+        name: "magnetometer",
+        usage: "",
+        help: "read the magnetic field in nT",
+        run: magnetometer,
+    },
+    Command {
+        name: "magnetometer rate",
+        usage: "<10|20|50|100>",
+        help: "set the magnetometer data rate in Hz",
+        run: magnetometer_rate,
+    },
+];
+
+fn accelerometer(sensor: &mut Sensor, args: &mut Args, out: &mut dyn Write) -> Result<(), Error> {
+    args.finish()?;
+    while !sensor.accel_status().map_err(|_| I2C_ERROR)?.xyz_new_data {}
+    let data = sensor.accel_data().map_err(|_| I2C_ERROR)?;
+    // RTT instead of normal print
+    // rprintln!("Acceleration: x {} y {} z {}\r\n", data.x, data.y, data.z);
+    write!(
+        out,
+        "Acceleration: x {} y {} z {}\r\n",
+        data.x, data.y, data.z
+    )?;
+    Ok(())
+}
+
+fn accelerometer_rate(
+    sensor: &mut Sensor,
+    args: &mut Args,
+    _out: &mut dyn Write,
+) -> Result<(), Error> {
+    let odr = args.choice(
+        "hz",
+        &[
+            ("1", AccelOutputDataRate::Hz1),
+            ("10", AccelOutputDataRate::Hz10),
+            ("25", AccelOutputDataRate::Hz25),
+            ("50", AccelOutputDataRate::Hz50),
+            ("100", AccelOutputDataRate::Hz100),
+            ("200", AccelOutputDataRate::Hz200),
+            ("400", AccelOutputDataRate::Hz400),
+        ],
+    )?;
+    args.finish()?;
+    sensor.set_accel_odr(odr).map_err(|_| I2C_ERROR)
+}
+
+fn accelerometer_scale(
+    sensor: &mut Sensor,
+    args: &mut Args,
+    _out: &mut dyn Write,
+) -> Result<(), Error> {
+    let scale = args.choice(
+        "g",
+        &[
+            ("2", AccelScale::G2),
+            ("4", AccelScale::G4),
+            ("8", AccelScale::G8),
+            ("16", AccelScale::G16),
+        ],
+    )?;
+    args.finish()?;
+    sensor.set_accel_scale(scale).map_err(|_| I2C_ERROR)
+}
+
+fn magnetometer(sensor: &mut Sensor, args: &mut Args, out: &mut dyn Write) -> Result<(), Error> {
+    args.finish()?;
+    while !sensor.mag_status().map_err(|_| I2C_ERROR)?.xyz_new_data {}
+    let data = sensor.mag_data().map_err(|_| I2C_ERROR)?;
+    // RTT instead of normal print
+    // rprintln!("Magnetometer: x {} y {} z {}\r\n", data.x, data.y, data.z);
+    write!(
+        out,
+        "Magnetometer: x {} y {} z {}\r\n",
+        data.x, data.y, data.z
+    )?;
+    Ok(())
+}
+
+fn magnetometer_rate(
+    sensor: &mut Sensor,
+    args: &mut Args,
+    _out: &mut dyn Write,
+) -> Result<(), Error> {
+    let odr = args.choice(
+        "hz",
+        &[
+            ("10", MagOutputDataRate::Hz10),
+            ("20", MagOutputDataRate::Hz20),
+            ("50", MagOutputDataRate::Hz50),
+            ("100", MagOutputDataRate::Hz100),
+        ],
+    )?;
+    args.finish()?;
+    sensor.set_mag_odr(odr).map_err(|_| I2C_ERROR)
+}
 
 #[cortex_m_rt::entry]
 fn main() -> ! {
@@ -25,7 +152,8 @@ fn main() -> ! {
             Parity::EXCLUDED,
             Baudrate::BAUD115200,
         );
-        UartePort::new(serial)
+        let dma = cortex_m::singleton!(: DmaBuffers = DmaBuffers::new()).unwrap();
+        UartePort::new(serial, dma)
     };
 
     // init i2c sensor
@@ -39,57 +167,23 @@ fn main() -> ! {
 
     let mut sensor = sensor.into_mag_continuous().ok().unwrap();
 
-    let mut buffer = Vec::<u8, 32>::new();
+    let shell = Shell::new(COMMANDS);
+    let mut editor = LineEditor::<32, 4>::new();
+    editor.set_prompt("input: ");
+    editor.prompt(&mut serial).unwrap();
+    nb::block!(serial.flush()).unwrap();
     loop {
-        write!(serial, "input: ").unwrap();
-        nb::block!(serial.flush()).unwrap();
-        loop {
-            let byte = nb::block!(serial.read()).unwrap();
-            write!(serial, "{}", byte as char).unwrap();
-            nb::block!(serial.flush()).unwrap();
-            if byte == 13 {
-                break;
-            }
-            if buffer.push(byte).is_err() {
-                write!(serial, "error: buffer full\r\n").unwrap();
-                break;
+        let byte = nb::block!(serial.read()).unwrap();
+        match editor.feed(byte, &mut serial).unwrap() {
+            Some(Event::Line) => {
+                shell
+                    .execute(&mut sensor, editor.line(), &mut serial)
+                    .unwrap();
+                editor.prompt(&mut serial).unwrap();
             }
+            Some(Event::Cancel) => editor.prompt(&mut serial).unwrap(),
+            Some(Event::Tab) | None => {}
         }
-
-        match str::from_utf8(&buffer) {
-            Ok("accelerometer") => {
-                while !sensor.accel_status().unwrap().xyz_new_data {}
-                let data = sensor.accel_data().unwrap();
-                // RTT instead of normal print
-                // rprintln!("Acceleration: x {} y {} z {}\r\n", data.x, data.y, data.z);
-                write!(
-                    serial,
-                    "Acceleration: x {} y {} z {}\r\n",
-                    data.x, data.y, data.z
-                )
-                .unwrap();
-            }
-            Ok("magnetometer") => {
-                while !sensor.mag_status().unwrap().xyz_new_data {}
-                let data = sensor.mag_data().unwrap();
-                // RTT instead of normal print
-                // rprintln!("Magnetometer: x {} y {} z {}\r\n", data.x, data.y, data.z);
-                write!(
-                    serial,
-                    "Magnetometer: x {} y {} z {}\r\n",
-                    data.x, data.y, data.z
-                )
-                .unwrap();
-            }
-            Ok(command) => {
-                write!(serial, "error: '{}' command not detected\r\n", command).unwrap();
-            }
-            Err(_) => {
-                write!(serial, "error: can't convert to utf8 str").unwrap();
-            }
-        };
-
         nb::block!(serial.flush()).unwrap();
-        buffer.clear();
     }
 }
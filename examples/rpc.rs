@@ -0,0 +1,214 @@
+#![no_main]
+#![no_std]
+
+use heapless::Vec;
+use lsm303agr::{
+    interface::I2cInterface, mode::MagContinuous, AccelOutputDataRate, Lsm303agr, MagOutputDataRate,
+};
+use microbit::{
+    board::Board,
+    display::nonblocking::{Display, GreyscaleImage},
+    hal::{
+        gpio::Level,
+        twim::Twim,
+        uarte::{Baudrate, Parity, Uarte},
+    },
+    pac::{self, twim0::frequency::FREQUENCY_A},
+};
+
+use microbit_v2_examples::{
+    self as _,
+    framing::FrameBuffer,
+    music::{Music, MAX_MELODY_LEN},
+    rpc::{self, ErrorCode, Request, Response, MAX_BRIGHTNESS, MAX_FRAME_SIZE},
+    serial_setup::{DmaBuffers, UartePort},
+};
+
+type Sensor = Lsm303agr<I2cInterface<Twim<pac::TWIM0>>, MagContinuous>;
+
+/// Tunes for [`Request::PlayMusic`], by index.
+const TUNES: &[&[u8]] = &[
+    b"c4 c4 g4 g4 a4 a4 g4 - f4 f4 e4 e4 d4 d4 c4 -",
+    b"e4 d4 c4 d4 e4 e4 e4 - d4 d4 d4 - e4 g4 g4 -",
+];
+
+#[rtic::app(device = microbit::pac, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        display: Display<pac::TIMER1>,
+        music: Music<pac::PWM0, pac::TIMER2>,
+    }
+
+    #[local]
+    struct Local {
+        serial: UartePort<pac::UARTE0, 64, { 2 * MAX_FRAME_SIZE }>,
+        frames: FrameBuffer<MAX_FRAME_SIZE>,
+        sensor: Sensor,
+        melody: Vec<u8, MAX_MELODY_LEN>,
+    }
+
+    #[init(local = [dma: DmaBuffers = DmaBuffers::new()])]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+
+        let serial = Uarte::new(
+            board.UARTE0,
+            board.uart.into(),
+            Parity::EXCLUDED,
+            Baudrate::BAUD115200,
+        );
+        let mut serial = UartePort::with_capacity(serial, cx.local.dma);
+        serial.listen();
+
+        let i2c = Twim::new(board.TWIM0, board.i2c_internal.into(), FREQUENCY_A::K100);
+        let mut sensor = Lsm303agr::new_with_i2c(i2c);
+        sensor.init().unwrap();
+        sensor.set_accel_odr(AccelOutputDataRate::Hz50).unwrap();
+        sensor.set_mag_odr(MagOutputDataRate::Hz50).unwrap();
+        let sensor = sensor.into_mag_continuous().ok().unwrap();
+
+        let display = Display::new(board.TIMER1, board.display_pins);
+
+        let speaker_pin = board
+            .speaker_pin
+            .into_push_pull_output(Level::High)
+            .degrade();
+        let music = Music::new(speaker_pin, board.PWM0, board.TIMER2);
+
+        (
+            Shared { display, music },
+            Local {
+                serial,
+                frames: FrameBuffer::new(),
+                sensor,
+                melody: Vec::new(),
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = TIMER1, priority = 2, shared = [display])]
+    fn timer1(mut cx: timer1::Context) {
+        cx.shared
+            .display
+            .lock(|display| display.handle_display_event());
+    }
+
+    #[task(binds = TIMER2, priority = 2, shared = [music])]
+    fn timer2(mut cx: timer2::Context) {
+        cx.shared.music.lock(|music| music.next_tick());
+    }
+
+    #[task(binds = UARTE0_UART0, priority = 1, shared = [display, music],
+           local = [serial, frames, sensor, melody])]
+    fn uarte0(cx: uarte0::Context) {
+        let mut shared = cx.shared;
+        let local = cx.local;
+        local.serial.on_interrupt();
+
+        let mut buf = [0; 16];
+        loop {
+            let len = local.serial.read_available(&mut buf);
+            if len == 0 {
+                break;
+            }
+            for &byte in &buf[..len] {
+                let frame = match local.frames.push(byte) {
+                    Some(frame) => frame,
+                    None => continue,
+                };
+                // Without a readable sequence number there is nobody to answer
+                let (seq, request) = match rpc::decode_request(frame) {
+                    Ok(request) => request,
+                    Err(error) => {
+                        defmt::warn!("bad request: {}", error);
+                        continue;
+                    }
+                };
+
+                let response = match request {
+                    Request::Ping => Response::Pong,
+                    Request::ReadAccel => match local.sensor.accel_data() {
+                        Ok(accel) => Response::Accel(accel),
+                        Err(_) => Response::Error(ErrorCode::Sensor),
+                    },
+                    Request::ReadMag => match local.sensor.mag_data() {
+                        Ok(mag) => Response::Mag(mag),
+                        Err(_) => Response::Error(ErrorCode::Sensor),
+                    },
+                    Request::ReadSensors => {
+                        match (local.sensor.accel_data(), local.sensor.mag_data()) {
+                            (Ok(accel), Ok(mag)) => Response::Sensors { accel, mag },
+                            _ => Response::Error(ErrorCode::Sensor),
+                        }
+                    }
+                    Request::Display(image)
+                        if image.iter().flatten().all(|&led| led <= MAX_BRIGHTNESS) =>
+                    {
+                        let image = GreyscaleImage::new(&image);
+                        shared.display.lock(|display| display.show(&image));
+                        Response::Done
+                    }
+                    Request::ClearDisplay => {
+                        shared.display.lock(|display| display.clear());
+                        Response::Done
+                    }
+                    Request::PlayMusic { tune, bpm }
+                        if bpm > 0 && (tune as usize) < TUNES.len() =>
+                    {
+                        let notes = TUNES[tune as usize];
+                        match shared.music.lock(|music| music.play(notes, bpm.into())) {
+                            Ok(()) => Response::Done,
+                            Err(_) => Response::Error(ErrorCode::InvalidArgument),
+                        }
+                    }
+                    Request::StopMusic => {
+                        shared.music.lock(|music| music.stop());
+                        Response::Done
+                    }
+                    Request::SetVolume(volume) if volume <= 100 => {
+                        shared.music.lock(|music| {
+                            music.set_volume(volume.into());
+                        });
+                        Response::Done
+                    }
+                    Request::LoadMelody { offset, notes } => {
+                        if offset == 0 {
+                            local.melody.clear();
+                        }
+                        let offset = offset as usize;
+                        // A retry repeats the last chunk, any other gap means one was lost
+                        if local.melody.get(offset..) == Some(&notes[..])
+                            || offset == local.melody.len()
+                                && local.melody.extend_from_slice(&notes).is_ok()
+                        {
+                            Response::Done
+                        } else {
+                            Response::Error(ErrorCode::InvalidArgument)
+                        }
+                    }
+                    Request::PlayMelody { bpm } if bpm > 0 => {
+                        let melody = &local.melody;
+                        match shared.music.lock(|music| music.play(melody, bpm.into())) {
+                            Ok(()) => Response::Done,
+                            Err(_) => Response::Error(ErrorCode::InvalidArgument),
+                        }
+                    }
+                    Request::Display(_)
+                    | Request::PlayMusic { .. }
+                    | Request::SetVolume(_)
+                    | Request::PlayMelody { .. } => Response::Error(ErrorCode::InvalidArgument),
+                };
+
+                let mut reply = [0; MAX_FRAME_SIZE];
+                let len = rpc::encode_response(seq, &response, &mut reply).unwrap();
+                if local.serial.try_write_all(&reply[..len]).is_err() {
+                    defmt::warn!("host too slow, response {} dropped", seq);
+                }
+            }
+        }
+    }
+}
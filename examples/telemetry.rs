@@ -0,0 +1,124 @@
+#![no_std]
+#![no_main]
+
+use core::fmt;
+use core::str;
+use embedded_io::WriteFmtError;
+use heapless::Vec;
+use lsm303agr::{AccelOutputDataRate, Lsm303agr, MagOutputDataRate};
+use microbit::hal::pac::twim0::frequency::FREQUENCY_A;
+use microbit::hal::prelude::*;
+use microbit::hal::twim::Twim;
+use microbit::hal::uarte::{Baudrate, Parity, Uarte};
+use microbit::Board;
+use rtic_monotonic::Monotonic;
+
+use microbit_v2_examples::{
+    self as _,
+    monotonic::MonoTimer,
+    serial_setup::{DmaBuffers, UartePort},
+    telemetry::{Command, Format, Record, Telemetry, CSV_HEADER, FRAME_SIZE},
+    writeln_flush,
+};
+
+/// Write one record in `format` and wait for it to be sent.
+fn send_record<W: fmt::Write + embedded_io::Write>(
+    w: &mut W,
+    record: &Record,
+    format: Format,
+) -> Result<(), WriteFmtError<W::Error>> {
+    match format {
+        Format::Csv => record.write_csv(w).map_err(|_| WriteFmtError::FmtError)?,
+        Format::Binary => {
+            let mut frame = [0; FRAME_SIZE];
+            let len = record.encode_frame(&mut frame).unwrap();
+            w.write_all(&frame[..len])?;
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    let board = Board::take().unwrap();
+
+    // init uarte port
+    let mut serial = {
+        let serial = Uarte::new(
+            board.UARTE0,
+            board.uart.into(),
+            Parity::EXCLUDED,
+            Baudrate::BAUD115200,
+        );
+        let dma = cortex_m::singleton!(: DmaBuffers = DmaBuffers::new()).unwrap();
+        UartePort::new(serial, dma)
+    };
+
+    // init i2c sensor
+    let mut sensor = {
+        let i2c = Twim::new(board.TWIM0, board.i2c_internal.into(), FREQUENCY_A::K100);
+        Lsm303agr::new_with_i2c(i2c)
+    };
+    sensor.init().unwrap();
+    sensor.set_accel_odr(AccelOutputDataRate::Hz100).unwrap();
+    sensor.set_mag_odr(MagOutputDataRate::Hz100).unwrap();
+
+    let mut sensor = sensor.into_mag_continuous().ok().unwrap();
+
+    let mut mono = MonoTimer::new(board.TIMER0);
+    unsafe { mono.reset() };
+
+    let mut telemetry = Telemetry::default();
+    let mut next_record = mono.now();
+    let mut buffer = Vec::<u8, 32>::new();
+    loop {
+        match serial.read() {
+            Ok(b'\r' | b'\n') if buffer.is_empty() => {}
+            Ok(b'\r' | b'\n') => {
+                let reply = match str::from_utf8(&buffer).ok().and_then(Command::parse) {
+                    Some(command) => {
+                        telemetry.handle(command);
+                        next_record = mono.now();
+                        if command == Command::Start && telemetry.format() == Format::Csv {
+                            writeln_flush!(serial, "{}", CSV_HEADER)
+                        } else {
+                            Ok(())
+                        }
+                    }
+                    None => writeln_flush!(serial, "error: unknown command"),
+                };
+                if let Err(error) = reply {
+                    defmt::warn!("serial: {}", error);
+                }
+                buffer.clear();
+            }
+            Ok(byte) => {
+                if buffer.push(byte).is_err() {
+                    if let Err(error) = writeln_flush!(serial, "error: buffer full") {
+                        defmt::warn!("serial: {}", error);
+                    }
+                    buffer.clear();
+                }
+            }
+            Err(nb::Error::WouldBlock) => {}
+            Err(nb::Error::Other(error)) => panic!("serial error: {:?}", error),
+        }
+
+        let now = mono.now();
+        if !telemetry.is_running() || now < next_record {
+            continue;
+        }
+        next_record += telemetry.period();
+
+        let record = Record {
+            // The record keeps the low 32 bits, wrapping every ~71 minutes
+            timestamp_us: now.ticks() as u32,
+            accel: sensor.accel_data().unwrap(),
+            mag: sensor.mag_data().unwrap(),
+        };
+        if let Err(error) = send_record(&mut serial, &record, telemetry.format()) {
+            defmt::warn!("serial: {}", error);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+use lsm303agr::interface::I2cInterface;
+use lsm303agr::mode::MagOneShot;
+use lsm303agr::{AccelOutputDataRate, Lsm303agr};
+use microbit::hal::pac::{self, twim0::frequency::FREQUENCY_A};
+use microbit::hal::twim::Twim;
+use microbit::Board;
+
+use microbit_v2_examples::{
+    self as _,
+    monotonic::{ExtU64, MonoTimer},
+    timers::{TimerId, TimerService},
+};
+
+type Sensor = Lsm303agr<I2cInterface<Twim<pac::TWIM0>>, MagOneShot>;
+
+/// Read the accelerometer.
+const POLL: TimerId = TimerId(0);
+/// Report the uptime.
+const UPTIME: TimerId = TimerId(1);
+/// Stop reading the accelerometer.
+const STOP: TimerId = TimerId(2);
+
+#[rtic::app(device = microbit::pac, peripherals = true, dispatchers = [SWI0_EGU0])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        timers: TimerService<pac::TIMER2, 3>,
+    }
+
+    #[local]
+    struct Local {
+        sensor: Sensor,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+
+        let i2c = Twim::new(board.TWIM0, board.i2c_internal.into(), FREQUENCY_A::K100);
+        let mut sensor = Lsm303agr::new_with_i2c(i2c);
+        sensor.init().unwrap();
+        sensor.set_accel_odr(AccelOutputDataRate::Hz10).unwrap();
+
+        // One TIMER for all three timers
+        let (delay, _) = MonoTimer::new(board.TIMER2).into_delays();
+        let mut timers = TimerService::new(delay);
+        timers.start_periodic(POLL, 200.millis(), Some(poll_due));
+        timers.start_periodic(UPTIME, 1.secs(), None);
+        timers.start_once(STOP, 5.secs(), None);
+
+        (Shared { timers }, Local { sensor }, init::Monotonics())
+    }
+
+    fn poll_due() {
+        poll::spawn().ok();
+    }
+
+    #[task(binds = TIMER2, shared = [timers])]
+    fn timer2(mut cx: timer2::Context) {
+        cx.shared.timers.lock(|timers| timers.on_interrupt());
+    }
+
+    #[task(local = [sensor])]
+    fn poll(cx: poll::Context) {
+        let accel = cx.local.sensor.accel_data().unwrap();
+        defmt::info!("accel mg: x {} y {} z {}", accel.x, accel.y, accel.z);
+    }
+
+    #[idle(shared = [timers])]
+    fn idle(mut cx: idle::Context) -> ! {
+        loop {
+            cx.shared.timers.lock(|timers| {
+                if timers.take_fired(UPTIME) {
+                    let uptime = timers.now().duration_since_epoch();
+                    defmt::info!("up for {} s", uptime.to_secs());
+                }
+                if timers.take_fired(STOP) {
+                    timers.cancel(POLL);
+                    defmt::info!("done polling");
+                }
+            });
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Calibration printed by `led-compass` built with the `calibration` feature.
+//!
+//! The board writes its `Debug` output once the user has finished rolling it:
+//!
+//! ```text
+//! Calibration: Calibration { center: Measurement { x: 43190, y: 6388, z: -22662 }, scale: Measurement { x: 1060, y: 1047, z: 1057 }, radius: 45006 }
+//! ```
+
+use lsm303agr::Measurement;
+
+pub const PREFIX: &str = "Calibration: ";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub center: Measurement,
+    pub scale: Measurement,
+    pub radius: u32,
+}
+
+impl Calibration {
+    /// Parse a line starting with [`PREFIX`], None for any other line.
+    pub fn parse(line: &str) -> Option<Calibration> {
+        let fields = line.trim().strip_prefix(PREFIX)?;
+        // Values come in declaration order, the field names are only checked
+        // for the right count
+        let mut values = fields
+            .split(|c: char| !(c == '-' || c.is_ascii_digit()))
+            .filter(|word| !word.is_empty())
+            .map(|word| word.parse::<i64>().ok());
+        let mut next = || values.next().flatten();
+        let calibration = Calibration {
+            center: Measurement {
+                x: next()?.try_into().ok()?,
+                y: next()?.try_into().ok()?,
+                z: next()?.try_into().ok()?,
+            },
+            scale: Measurement {
+                x: next()?.try_into().ok()?,
+                y: next()?.try_into().ok()?,
+                z: next()?.try_into().ok()?,
+            },
+            radius: next()?.try_into().ok()?,
+        };
+        match next() {
+            None => Some(calibration),
+            Some(_) => None,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Host side of the protocols spoken by the examples.
+//!
+//! The protocol modules are compiled from the firmware sources so both ends
+//! always agree on the wire format.
+
+#[path = "../../src/framing.rs"]
+pub mod framing;
+#[path = "../../src/rpc.rs"]
+pub mod rpc;
+#[path = "../../src/telemetry.rs"]
+pub mod telemetry;
+
+pub mod calibration;
+pub mod link;
+pub mod plot;
+
+/// Stand-in for the firmware timer module, only its types are used by
+/// [`telemetry`].
+mod monotonic {
+    pub use fugit::ExtU64;
+
+    pub type Duration = fugit::TimerDurationU64<1_000_000>;
+}
@@ -0,0 +1,438 @@
+//! Serial link to the board.
+//!
+//! [`Link`] works over anything that reads and writes, a serial port in the CLI
+//! or one end of a pseudo terminal pair when trying it without a board.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use crate::framing::{self, FrameBuffer};
+use crate::rpc::{self, Client, ErrorCode, Request, Response, MAX_FRAME_SIZE};
+use crate::telemetry::{self, Record, RECORD_SIZE};
+
+/// How long to wait for the board before retrying or giving up.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+/// Attempts made by [`Link::call`].
+const ATTEMPTS: usize = 3;
+/// Longest frame accepted, enough for requests and telemetry records.
+const MAX_FRAME: usize = if MAX_FRAME_SIZE > telemetry::FRAME_SIZE {
+    MAX_FRAME_SIZE
+} else {
+    telemetry::FRAME_SIZE
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Nothing usable arrived in time.
+    Timeout,
+    Protocol(rpc::Error),
+    /// The board refused the request.
+    Device(ErrorCode),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "serial port: {}", error),
+            Error::Timeout => f.write_str("no answer from the board"),
+            Error::Protocol(error) => write!(f, "protocol error: {:?}", error),
+            Error::Device(code) => write!(f, "board error: {:?}", code),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<rpc::Error> for Error {
+    fn from(error: rpc::Error) -> Error {
+        Error::Protocol(error)
+    }
+}
+
+pub struct Link<P> {
+    port: P,
+    /// Bytes read from the port and not consumed yet.
+    received: VecDeque<u8>,
+    frames: FrameBuffer<MAX_FRAME>,
+    client: Client,
+    timeout: Duration,
+}
+
+impl<P: Read + Write> Link<P> {
+    /// The port should have a short read timeout so deadlines are kept.
+    pub fn new(port: P) -> Self {
+        Link {
+            port,
+            received: VecDeque::new(),
+            frames: FrameBuffer::new(),
+            client: Client::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn into_inner(self) -> P {
+        self.port
+    }
+
+    /// Send a request to the rpc example and wait for its response, retrying
+    /// after a timeout.
+    pub fn call(&mut self, request: &Request) -> Result<Response, Error> {
+        let mut frame = [0; MAX_FRAME_SIZE];
+        let mut len = self.client.request(request, &mut frame)?;
+        for _ in 0..ATTEMPTS {
+            self.port.write_all(&frame[..len])?;
+            self.port.flush()?;
+
+            let deadline = Instant::now() + self.timeout;
+            while let Some(received) = self.read_frame(deadline)? {
+                match self.client.response(&received) {
+                    Ok(Response::Error(code)) => return Err(Error::Device(code)),
+                    Ok(response) => return Ok(response),
+                    // Late answers to an earlier request and line noise
+                    Err(rpc::Error::UnexpectedSequence(_) | rpc::Error::Frame(_)) => {}
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            len = self.client.retry(request, &mut frame)?;
+        }
+        self.client.cancel();
+        Err(Error::Timeout)
+    }
+
+    /// Send a line to a text console, e.g. the shell of the i2c example.
+    pub fn send_line(&mut self, line: &str) -> Result<(), Error> {
+        self.port.write_all(line.as_bytes())?;
+        self.port.write_all(b"\r")?;
+        self.port.flush()?;
+        Ok(())
+    }
+
+    /// Run a shell command and collect its output, up to the next `prompt`.
+    ///
+    /// The echo of the command is left out.
+    pub fn command(&mut self, line: &str, prompt: &str) -> Result<String, Error> {
+        self.send_line(line)?;
+        let mut output = Vec::new();
+        loop {
+            match self.read_byte(Instant::now() + self.timeout)? {
+                Some(byte) => output.push(byte),
+                None if output.is_empty() => return Err(Error::Timeout),
+                // Quiet without a prompt, return what there is
+                None => break,
+            }
+            if output.ends_with(prompt.as_bytes()) {
+                output.truncate(output.len() - prompt.len());
+                break;
+            }
+        }
+        let output = String::from_utf8_lossy(&output);
+        let output = match output.split_once('\n') {
+            Some((echo, rest)) if echo.trim_end() == line => rest,
+            _ => &output,
+        };
+        Ok(output.to_string())
+    }
+
+    /// The next text line, without its line ending.
+    pub fn read_line(&mut self, deadline: Instant) -> Result<Option<String>, Error> {
+        let mut line = Vec::new();
+        while let Some(byte) = self.read_byte(deadline)? {
+            if byte == b'\n' {
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                return Ok(Some(String::from_utf8_lossy(&line).into_owned()));
+            }
+            line.push(byte);
+        }
+        // Keep a partial line for the next call
+        for byte in line.into_iter().rev() {
+            self.received.push_front(byte);
+        }
+        Ok(None)
+    }
+
+    /// The next frame, without its delimiter.
+    pub fn read_frame(&mut self, deadline: Instant) -> Result<Option<Vec<u8>>, Error> {
+        while let Some(byte) = self.read_byte(deadline)? {
+            if let Some(frame) = self.frames.push(byte) {
+                return Ok(Some(frame.to_vec()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The next binary telemetry record, damaged frames are skipped.
+    pub fn read_record(&mut self, deadline: Instant) -> Result<Option<Record>, Error> {
+        while let Some(frame) = self.read_frame(deadline)? {
+            let mut bytes = [0; RECORD_SIZE + 2];
+            if let Ok(RECORD_SIZE) = framing::decode_frame(&frame, &mut bytes) {
+                let bytes = bytes[..RECORD_SIZE].try_into().unwrap();
+                return Ok(Some(Record::from_bytes(bytes)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_byte(&mut self, deadline: Instant) -> Result<Option<u8>, Error> {
+        loop {
+            if let Some(byte) = self.received.pop_front() {
+                return Ok(Some(byte));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            let mut buf = [0; 64];
+            match self.port.read(&mut buf) {
+                Ok(len) => self.received.extend(&buf[..len]),
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::TimedOut
+                            | io::ErrorKind::WouldBlock
+                            | io::ErrorKind::Interrupted
+                    ) => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rpc::{decode_request, encode_response};
+
+    /// Board end of an in-memory serial link, answering whatever is flushed.
+    struct Loopback<F> {
+        to_host: VecDeque<u8>,
+        pending: Vec<u8>,
+        /// What the host sent, one entry per flush.
+        sent: Vec<Vec<u8>>,
+        answer: F,
+    }
+
+    impl<F: FnMut(&[u8]) -> Vec<u8>> Loopback<F> {
+        fn new(answer: F) -> Self {
+            Loopback {
+                to_host: VecDeque::new(),
+                pending: Vec::new(),
+                sent: Vec::new(),
+                answer,
+            }
+        }
+    }
+
+    impl<F> Read for Loopback<F> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.to_host.is_empty() {
+                // Like a serial port with a short read timeout
+                std::thread::sleep(Duration::from_millis(1));
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let len = buf.len().min(self.to_host.len());
+            for (dst, src) in buf.iter_mut().zip(self.to_host.drain(..len)) {
+                *dst = src;
+            }
+            Ok(len)
+        }
+    }
+
+    impl<F: FnMut(&[u8]) -> Vec<u8>> Write for Loopback<F> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let sent = std::mem::take(&mut self.pending);
+            self.to_host.extend((self.answer)(&sent));
+            self.sent.push(sent);
+            Ok(())
+        }
+    }
+
+    fn loopback<F: FnMut(&[u8]) -> Vec<u8>>(answer: F) -> Link<Loopback<F>> {
+        let mut link = Link::new(Loopback::new(answer));
+        link.set_timeout(Duration::from_millis(20));
+        link
+    }
+
+    fn response(seq: u16, response: &Response) -> Vec<u8> {
+        let mut frame = [0; MAX_FRAME_SIZE];
+        let len = encode_response(seq, response, &mut frame).unwrap();
+        frame[..len].to_vec()
+    }
+
+    fn seq_of(frame: &[u8]) -> u16 {
+        decode_request(frame).unwrap().0
+    }
+
+    #[test]
+    fn call() {
+        let mut link = loopback(|frame| response(seq_of(frame), &Response::Pong));
+        assert!(matches!(link.call(&Request::Ping), Ok(Response::Pong)));
+        assert!(matches!(link.call(&Request::Ping), Ok(Response::Pong)));
+        let sent = link.into_inner().sent;
+        assert_eq!(sent.len(), 2);
+        assert_eq!(seq_of(&sent[1]), seq_of(&sent[0]).wrapping_add(1));
+    }
+
+    #[test]
+    fn device_error() {
+        let mut link = loopback(|frame| response(seq_of(frame), &Response::Error(ErrorCode::Busy)));
+        assert!(matches!(
+            link.call(&Request::StopMusic),
+            Err(Error::Device(ErrorCode::Busy))
+        ));
+    }
+
+    #[test]
+    fn timeout_after_retries_with_the_same_sequence_number() {
+        let mut link = loopback(|_| Vec::new());
+        let started = Instant::now();
+        assert!(matches!(link.call(&Request::Ping), Err(Error::Timeout)));
+        assert!(started.elapsed() >= Duration::from_millis(60));
+
+        let sent = link.into_inner().sent;
+        assert_eq!(sent.len(), ATTEMPTS);
+        assert!(sent.iter().all(|frame| *frame == sent[0]));
+    }
+
+    #[test]
+    fn answer_to_a_retry() {
+        let mut attempts = 0;
+        let mut link = loopback(|frame| {
+            attempts += 1;
+            match attempts {
+                1 => Vec::new(),
+                _ => response(seq_of(frame), &Response::Done),
+            }
+        });
+        assert!(matches!(
+            link.call(&Request::ClearDisplay),
+            Ok(Response::Done)
+        ));
+        let sent = link.into_inner().sent;
+        assert_eq!(sent.len(), 2);
+        assert_eq!(seq_of(&sent[0]), seq_of(&sent[1]));
+    }
+
+    #[test]
+    fn stale_answers_and_noise_are_skipped() {
+        let mut link = loopback(|frame| {
+            let seq = seq_of(frame);
+            let mut answer = response(seq.wrapping_sub(1), &Response::Done);
+            answer.extend(b"noise\0");
+            answer.extend(response(seq, &Response::Pong));
+            answer
+        });
+        assert!(matches!(link.call(&Request::Ping), Ok(Response::Pong)));
+        assert_eq!(link.into_inner().sent.len(), 1);
+    }
+
+    #[test]
+    fn late_answer_to_a_timed_out_call_is_dropped() {
+        let mut late = None;
+        let mut link = loopback(|frame| {
+            let seq = seq_of(frame);
+            match decode_request(frame).unwrap().1 {
+                // Answered only after the host gave up
+                Request::ReadMag => {
+                    late = Some(seq);
+                    Vec::new()
+                }
+                _ => {
+                    let mut answer = response(late.unwrap(), &Response::Done);
+                    answer.extend(response(seq, &Response::Pong));
+                    answer
+                }
+            }
+        });
+        assert!(matches!(link.call(&Request::ReadMag), Err(Error::Timeout)));
+        assert!(matches!(link.call(&Request::Ping), Ok(Response::Pong)));
+    }
+
+    #[test]
+    fn command() {
+        let mut link = loopback(|line| {
+            assert_eq!(line, b"mag show\r");
+            b"mag show\r\nx 1 y 2\r\ninput: ".to_vec()
+        });
+        assert_eq!(link.command("mag show", "input: ").unwrap(), "x 1 y 2\r\n");
+    }
+
+    #[test]
+    fn command_without_prompt() {
+        let mut link = loopback(|_| b"rebooting\r\n".to_vec());
+        assert_eq!(link.command("reset", "input: ").unwrap(), "rebooting\r\n");
+
+        let mut link = loopback(|_| Vec::new());
+        assert!(matches!(
+            link.command("reset", "input: "),
+            Err(Error::Timeout)
+        ));
+    }
+
+    #[test]
+    fn read_line() {
+        let mut link = loopback(|_| Vec::new());
+        link.port.to_host.extend(b"1,2,3\r\n4,5");
+        let deadline = || Instant::now() + Duration::from_millis(20);
+        assert_eq!(
+            link.read_line(deadline()).unwrap().as_deref(),
+            Some("1,2,3")
+        );
+        // The rest of a line is kept until it ends
+        assert_eq!(link.read_line(deadline()).unwrap(), None);
+        link.port.to_host.extend(b",6\n");
+        assert_eq!(
+            link.read_line(deadline()).unwrap().as_deref(),
+            Some("4,5,6")
+        );
+    }
+
+    #[test]
+    fn read_record() {
+        let record = Record {
+            timestamp_us: 123_456,
+            accel: lsm303agr::Measurement {
+                x: -1,
+                y: 2,
+                z: -1000,
+            },
+            mag: lsm303agr::Measurement {
+                x: 300,
+                y: -400,
+                z: 500,
+            },
+        };
+        let mut frame = [0; telemetry::FRAME_SIZE];
+        let len = record.encode_frame(&mut frame).unwrap();
+        let mut damaged = frame[..len].to_vec();
+        damaged[3] ^= 0x40;
+
+        let mut link = loopback(|_| Vec::new());
+        link.port.to_host.extend(damaged);
+        link.port.to_host.extend(&frame[..len]);
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let read = link.read_record(deadline).unwrap().unwrap();
+        assert_eq!(read.to_bytes(), record.to_bytes());
+        assert!(link.read_record(deadline).unwrap().is_none());
+    }
+}
@@ -0,0 +1,294 @@
+use std::error::Error;
+use std::io::{self, BufRead, Write as _};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use clap::{Parser, Subcommand};
+
+use microbit_cli::calibration::{self, Calibration};
+use microbit_cli::link::{self, Link};
+use microbit_cli::plot;
+use microbit_cli::rpc::{Request, Response, MAX_BRIGHTNESS, MELODY_CHUNK_SIZE};
+use microbit_cli::telemetry::{Record, CSV_HEADER};
+
+/// Talk to the micro:bit examples over the serial port.
+#[derive(Parser)]
+struct Cli {
+    /// Serial device, e.g. /dev/ttyACM0
+    #[arg(short, long, env = "MICROBIT_PORT")]
+    port: String,
+    #[arg(short, long, default_value_t = 115_200)]
+    baud: u32,
+    /// Milliseconds to wait for an answer
+    #[arg(long, default_value_t = 500)]
+    timeout: u64,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a command on the shell of the i2c example, read them from stdin
+    /// without one
+    Shell {
+        line: Vec<String>,
+        #[arg(long, default_value = "input: ")]
+        prompt: String,
+    },
+    /// Check the rpc example answers
+    Ping,
+    /// Read both sensors through the rpc example
+    Sensors,
+    /// Show an image on the rpc example, rows of brightness separated by
+    /// colons, e.g. 09090:90909:90009:09090:00900
+    Display { image: String },
+    /// Clear the display of the rpc example
+    Clear,
+    /// Control the speaker of the rpc example
+    Music {
+        #[command(subcommand)]
+        command: Music,
+    },
+    /// Stream records from the telemetry example
+    Telemetry {
+        /// Records per second
+        #[arg(short, long, default_value_t = 10)]
+        rate: u32,
+        /// Use binary frames instead of CSV on the wire
+        #[arg(long)]
+        binary: bool,
+        /// Stop after this many records
+        #[arg(short = 'n', long)]
+        count: Option<usize>,
+        /// Draw gauges instead of printing CSV
+        #[arg(long)]
+        plot: bool,
+    },
+    /// Wait for the calibration printed by led-compass built with the
+    /// calibration feature
+    Calibration {
+        /// Seconds to wait for the calibration to finish
+        #[arg(long, default_value_t = 120)]
+        wait: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum Music {
+    /// Play a built in tune, or the uploaded melody without one
+    Play {
+        tune: Option<u8>,
+        #[arg(long, default_value_t = 120)]
+        bpm: u16,
+    },
+    /// Upload a melody, notes separated by whitespace
+    Upload {
+        file: PathBuf,
+        /// Play it once uploaded
+        #[arg(long)]
+        play: bool,
+        #[arg(long, default_value_t = 120)]
+        bpm: u16,
+    },
+    Stop,
+    /// Set the volume, 0 to 100
+    Volume {
+        volume: u8,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    // A short read timeout lets the link keep its own deadlines
+    let port = serialport::new(&cli.port, cli.baud)
+        .timeout(Duration::from_millis(10))
+        .open()?;
+    let mut link = Link::new(port);
+    link.set_timeout(Duration::from_millis(cli.timeout));
+    let timeout = Duration::from_millis(cli.timeout);
+
+    match cli.command {
+        Command::Shell { line, prompt } if line.is_empty() => {
+            let stdin = io::stdin();
+            print!("{}", prompt);
+            io::stdout().flush()?;
+            for line in stdin.lock().lines() {
+                print!("{}{}", link.command(&line?, &prompt)?, prompt);
+                io::stdout().flush()?;
+            }
+        }
+        Command::Shell { line, prompt } => print!("{}", link.command(&line.join(" "), &prompt)?),
+        Command::Ping => {
+            let start = Instant::now();
+            link.call(&Request::Ping)?;
+            println!("pong in {:?}", start.elapsed());
+        }
+        Command::Sensors => match link.call(&Request::ReadSensors)? {
+            Response::Sensors { accel, mag } => {
+                println!("accel mg: x {} y {} z {}", accel.x, accel.y, accel.z);
+                println!("mag nT: x {} y {} z {}", mag.x, mag.y, mag.z);
+            }
+            response => return Err(unexpected(response)),
+        },
+        Command::Display { image } => {
+            let image =
+                parse_image(&image).ok_or("the image needs 5 rows of 5 digits from 0 to 9")?;
+            expect_done(link.call(&Request::Display(image))?)?;
+        }
+        Command::Clear => expect_done(link.call(&Request::ClearDisplay)?)?,
+        Command::Music { command } => music(&mut link, command)?,
+        Command::Telemetry {
+            rate,
+            binary,
+            count,
+            plot,
+        } => telemetry(&mut link, rate, binary, count, plot, timeout)?,
+        Command::Calibration { wait } => {
+            let deadline = Instant::now() + Duration::from_secs(wait);
+            println!("roll the board to light up every LED");
+            loop {
+                let line = link.read_line(deadline)?.ok_or(link::Error::Timeout)?;
+                if line.starts_with(calibration::PREFIX) {
+                    let calibration = Calibration::parse(&line).ok_or("unreadable calibration")?;
+                    let Calibration {
+                        center,
+                        scale,
+                        radius,
+                    } = calibration;
+                    println!("center: x {} y {} z {}", center.x, center.y, center.z);
+                    println!("scale: x {} y {} z {}", scale.x, scale.y, scale.z);
+                    println!("radius: {}", radius);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn music<P: io::Read + io::Write>(
+    link: &mut Link<P>,
+    command: Music,
+) -> Result<(), Box<dyn Error>> {
+    let request = match command {
+        Music::Play {
+            tune: Some(tune),
+            bpm,
+        } => Request::PlayMusic { tune, bpm },
+        Music::Play { tune: None, bpm } => Request::PlayMelody { bpm },
+        Music::Upload { file, play, bpm } => {
+            let melody = std::fs::read(file)?;
+            let melody = melody.trim_ascii();
+            if melody.is_empty() {
+                return Err("the melody has no notes".into());
+            }
+            for (i, chunk) in melody.chunks(MELODY_CHUNK_SIZE).enumerate() {
+                let request = Request::LoadMelody {
+                    offset: (i * MELODY_CHUNK_SIZE).try_into()?,
+                    notes: chunk.try_into().unwrap(),
+                };
+                match link.call(&request) {
+                    Err(link::Error::Device(_)) => return Err("the melody is too long".into()),
+                    response => expect_done(response?)?,
+                }
+            }
+            println!("uploaded {} bytes", melody.len());
+            if !play {
+                return Ok(());
+            }
+            Request::PlayMelody { bpm }
+        }
+        Music::Stop => Request::StopMusic,
+        Music::Volume { volume } => Request::SetVolume(volume),
+    };
+    expect_done(link.call(&request)?)
+}
+
+fn telemetry<P: io::Read + io::Write>(
+    link: &mut Link<P>,
+    rate: u32,
+    binary: bool,
+    count: Option<usize>,
+    plot: bool,
+    timeout: Duration,
+) -> Result<(), Box<dyn Error>> {
+    link.send_line("stop")?;
+    link.send_line(if binary { "format bin" } else { "format csv" })?;
+    link.send_line(&format!("rate {}", rate))?;
+    link.send_line("start")?;
+
+    if !plot {
+        println!("{}", CSV_HEADER);
+    }
+    let period = Duration::from_secs(1) / rate.max(1);
+    let mut received = 0;
+    while count.is_none_or(|count| received < count) {
+        let deadline = Instant::now() + period + timeout;
+        let record = if binary {
+            link.read_record(deadline)?
+        } else {
+            // The header and command errors are passed over
+            loop {
+                let line = link.read_line(deadline)?.ok_or(link::Error::Timeout)?;
+                if let Some(record) = Record::parse_csv(&line) {
+                    break Some(record);
+                }
+                if line.starts_with("error") {
+                    eprintln!("{}", line);
+                }
+            }
+        };
+        let record = record.ok_or(link::Error::Timeout)?;
+        received += 1;
+
+        if plot {
+            println!("{}", plot::line(&record));
+        } else {
+            let mut line = String::new();
+            record.write_csv(&mut line)?;
+            println!("{}", line.trim_end());
+        }
+    }
+    link.send_line("stop")?;
+    Ok(())
+}
+
+/// Rows of digits separated by colons, as in `09090:90909:90009:09090:00900`.
+fn parse_image(image: &str) -> Option<[[u8; 5]; 5]> {
+    let mut rows = image.split(':');
+    let mut parsed = [[0; 5]; 5];
+    for row in parsed.iter_mut() {
+        let digits = rows.next()?.as_bytes();
+        if digits.len() != 5 {
+            return None;
+        }
+        for (led, &digit) in row.iter_mut().zip(digits) {
+            *led = digit.checked_sub(b'0').filter(|&b| b <= MAX_BRIGHTNESS)?;
+        }
+    }
+    match rows.next() {
+        None => Some(parsed),
+        Some(_) => None,
+    }
+}
+
+fn expect_done(response: Response) -> Result<(), Box<dyn Error>> {
+    match response {
+        Response::Done => Ok(()),
+        response => Err(unexpected(response)),
+    }
+}
+
+fn unexpected(response: Response) -> Box<dyn Error> {
+    format!("unexpected response {:?}", response).into()
+}
@@ -0,0 +1,47 @@
+//! Text plots of telemetry records, one line per record.
+
+use lsm303agr::Measurement;
+
+use crate::telemetry::Record;
+
+/// Characters on each side of a gauge's zero mark.
+const HALF_WIDTH: i32 = 10;
+
+/// Accelerometer full scale in mg, the default ±2 g.
+pub const ACCEL_RANGE: i32 = 2000;
+/// Magnetometer range in nT, well above the Earth's field.
+pub const MAG_RANGE: i32 = 100_000;
+
+/// Gauges for each axis of both sensors.
+pub fn line(record: &Record) -> String {
+    format!(
+        "{:>10}  accel {}  mag {}",
+        record.timestamp_us,
+        axes(record.accel, ACCEL_RANGE),
+        axes(record.mag, MAG_RANGE)
+    )
+}
+
+fn axes(m: Measurement, range: i32) -> String {
+    format!(
+        "x{} y{} z{}",
+        gauge(m.x, range),
+        gauge(m.y, range),
+        gauge(m.z, range)
+    )
+}
+
+/// `[    |  *   ]` with the mark at `value` scaled to `-range..=range`.
+fn gauge(value: i32, range: i32) -> String {
+    let pos = (value.clamp(-range, range) as i64 * HALF_WIDTH as i64 / range as i64) as i32;
+    let mut gauge = String::from("[");
+    for i in -HALF_WIDTH..=HALF_WIDTH {
+        gauge.push(match i {
+            i if i == pos => '*',
+            0 => '|',
+            _ => ' ',
+        });
+    }
+    gauge.push(']');
+    gauge
+}
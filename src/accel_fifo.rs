@@ -0,0 +1,213 @@
+//! Interrupt driven accelerometer reads using the LSM303AGR FIFO.
+//!
+//! The accelerometer buffers samples in its 32 slot FIFO and pulls the shared
+//! `I2C_INT` line (P0.25) low through INT1 once the watermark is reached, so the
+//! CPU can sleep until a GPIOTE event asks for the FIFO to be drained.
+//!
+//! ```ignore
+//! let mut fifo = AccelFifo::new(i2c);
+//! fifo.configure(AccelOutputDataRate::Hz50, 10).unwrap();
+//! gpiote.channel0().input_pin(&int_pin).hi_to_lo().enable_interrupt();
+//!
+//! // in the GPIOTE task
+//! fifo.drain(monotonics::now(), &mut producer).unwrap();
+//! ```
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use heapless::spsc::Producer;
+use lsm303agr::{AccelOutputDataRate, Measurement};
+
+use crate::monotonic::{Duration, ExtU64, Instant};
+
+const ACCEL_ADDRESS: u8 = 0x19;
+/// Set on the register address to auto-increment it during multi-byte reads.
+const AUTO_INCREMENT: u8 = 0x80;
+
+const CTRL_REG1_A: u8 = 0x20;
+const CTRL_REG3_A: u8 = 0x22;
+const CTRL_REG4_A: u8 = 0x23;
+const CTRL_REG5_A: u8 = 0x24;
+const CTRL_REG6_A: u8 = 0x25;
+const OUT_X_L_A: u8 = 0x28;
+const FIFO_CTRL_REG_A: u8 = 0x2E;
+const FIFO_SRC_REG_A: u8 = 0x2F;
+
+const XYZ_EN: u8 = 0x07;
+const BDU: u8 = 0x80;
+const I1_DRDY1: u8 = 0x10;
+const I1_WTM: u8 = 0x04;
+const FIFO_EN: u8 = 0x40;
+const H_LACTIVE: u8 = 0x02;
+const FIFO_MODE_STREAM: u8 = 0x80;
+const FIFO_SRC_WTM: u8 = 0x80;
+const FIFO_SRC_OVRN: u8 = 0x40;
+const FIFO_SRC_FSS: u8 = 0x1F;
+
+pub const FIFO_SIZE: u8 = 32;
+/// Normal mode at ±2g gives 10 bit samples of 4mg.
+const MG_PER_DIGIT: i32 = 4;
+
+/// An accelerometer sample in milli-g, stamped with the `MonoTimer` time it was taken at.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub timestamp: Instant,
+    pub accel: Measurement,
+}
+
+pub struct AccelFifo<I> {
+    i2c: I,
+    period: Duration,
+    watermark: u8,
+    dropped: u32,
+    overruns: u32,
+}
+
+impl<I, E> AccelFifo<I>
+where
+    I: Write<Error = E> + WriteRead<Error = E>,
+{
+    pub fn new(i2c: I) -> Self {
+        AccelFifo {
+            i2c,
+            period: 100.millis(),
+            watermark: 0,
+            dropped: 0,
+            overruns: 0,
+        }
+    }
+
+    /// Start sampling and raise INT1 every `watermark` samples.
+    ///
+    /// A watermark of 0 bypasses the FIFO and raises INT1 on every new sample.
+    pub fn configure(&mut self, odr: AccelOutputDataRate, watermark: u8) -> Result<(), E> {
+        let (odr_bits, period) = odr_config(odr);
+        self.period = period;
+        self.watermark = watermark.min(FIFO_SIZE - 1);
+
+        self.write_register(CTRL_REG1_A, odr_bits << 4 | XYZ_EN)?;
+        self.write_register(CTRL_REG4_A, BDU)?;
+        // The line is shared with the magnetometer and interface MCU, so it idles high
+        self.write_register(CTRL_REG6_A, H_LACTIVE)?;
+        if self.watermark == 0 {
+            self.write_register(CTRL_REG5_A, 0)?;
+            self.write_register(FIFO_CTRL_REG_A, 0)?;
+            self.write_register(CTRL_REG3_A, I1_DRDY1)
+        } else {
+            self.write_register(CTRL_REG5_A, FIFO_EN)?;
+            self.write_register(FIFO_CTRL_REG_A, FIFO_MODE_STREAM | self.watermark)?;
+            self.write_register(CTRL_REG3_A, I1_WTM)
+        }
+    }
+
+    /// Stop raising interrupts, samples keep being taken.
+    pub fn disable_interrupt(&mut self) -> Result<(), E> {
+        self.write_register(CTRL_REG3_A, 0)
+    }
+
+    /// Time between two samples at the configured data rate.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Samples lost because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Times the FIFO filled up before it was drained.
+    pub fn overruns(&self) -> u32 {
+        self.overruns
+    }
+
+    /// Move every buffered sample into `queue`, call it from the GPIOTE task.
+    ///
+    /// The newest sample is stamped with `now` and older ones a sample period apart.
+    /// Returns the number of samples read from the sensor.
+    pub fn drain<const N: usize>(
+        &mut self,
+        now: Instant,
+        queue: &mut Producer<'_, Sample, N>,
+    ) -> Result<usize, E> {
+        let mut total = 0;
+        let mut buf = [0u8; 6 * FIFO_SIZE as usize];
+
+        // Keep going until the FIFO is below the watermark so INT1 is released
+        loop {
+            let count = if self.watermark == 0 {
+                1
+            } else {
+                let src = self.read_register(FIFO_SRC_REG_A)?;
+                if src & FIFO_SRC_OVRN != 0 {
+                    self.overruns += 1;
+                }
+                if src & FIFO_SRC_WTM == 0 && total > 0 {
+                    break;
+                }
+                (src & FIFO_SRC_FSS) as usize
+            };
+            if count == 0 {
+                break;
+            }
+
+            let data = &mut buf[..6 * count];
+            self.i2c
+                .write_read(ACCEL_ADDRESS, &[OUT_X_L_A | AUTO_INCREMENT], data)?;
+            for (i, raw) in data.chunks_exact(6).enumerate() {
+                let age = (count - 1 - i) as u32;
+                let sample = Sample {
+                    timestamp: now - self.period * age,
+                    accel: Measurement {
+                        x: to_mg(raw[0], raw[1]),
+                        y: to_mg(raw[2], raw[3]),
+                        z: to_mg(raw[4], raw[5]),
+                    },
+                };
+                if queue.enqueue(sample).is_err() {
+                    self.dropped += 1;
+                }
+            }
+            total += count;
+
+            if self.watermark == 0 {
+                break;
+            }
+        }
+        Ok(total)
+    }
+
+    pub fn release(self) -> I {
+        self.i2c
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), E> {
+        self.i2c.write(ACCEL_ADDRESS, &[register, value])
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8, E> {
+        let mut value = [0];
+        self.i2c
+            .write_read(ACCEL_ADDRESS, &[register], &mut value)?;
+        Ok(value[0])
+    }
+}
+
+fn odr_config(odr: AccelOutputDataRate) -> (u8, Duration) {
+    match odr {
+        AccelOutputDataRate::Hz1 => (1, 1000.millis()),
+        AccelOutputDataRate::Hz10 => (2, 100.millis()),
+        AccelOutputDataRate::Hz25 => (3, 40.millis()),
+        AccelOutputDataRate::Hz50 => (4, 20.millis()),
+        AccelOutputDataRate::Hz100 => (5, 10.millis()),
+        AccelOutputDataRate::Hz200 => (6, 5.millis()),
+        AccelOutputDataRate::Hz400 => (7, 2500.micros()),
+        // The sensor is kept in normal mode, so low-power only rates fall back to 1.344kHz
+        AccelOutputDataRate::Khz1_344
+        | AccelOutputDataRate::Khz1_620LowPower
+        | AccelOutputDataRate::Khz5_376LowPower => (9, 744.micros()),
+    }
+}
+
+fn to_mg(low: u8, high: u8) -> i32 {
+    // Left aligned 10 bit value
+    (i16::from_le_bytes([low, high]) >> 6) as i32 * MG_PER_DIGIT
+}
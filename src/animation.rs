@@ -0,0 +1,417 @@
+//! Frame by frame animations for the LED matrix.
+//!
+//! An [`Animation`] plays a list of [`Step`]s, each showing a [`Frame`] for a
+//! number of ticks, optionally wiping or fading in from the step before. It is
+//! advanced with [`Animate::tick`] from a periodic interrupt, like the scrolling
+//! text of `microbit_text`, and rendered with `MicrobitFrame::set`.
+//!
+//! ```ignore
+//! static BEAT: [Step; 2] = [
+//!     Step::new(images::HEART, 10).transition(Transition::Fade { ticks: 4 }),
+//!     Step::new(images::HEART_SMALL, 10).transition(Transition::Fade { ticks: 4 }),
+//! ];
+//! let mut animation = Animation::new(&BEAT, Repeat::Loop);
+//!
+//! // in the RTC handler
+//! animation.tick();
+//! frame.set(&animation);
+//! display.show_frame(&frame);
+//! ```
+
+use microbit_text::scrolling::Animate;
+use tiny_led_matrix::Render;
+
+use crate::frame::{Frame, HEIGHT, WIDTH};
+
+/// Side a wipe starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// How a step replaces the frame before it, during its first `ticks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Transition {
+    /// Show the new frame at once.
+    Cut,
+    /// Uncover the new frame a column or row at a time from `from`.
+    Wipe { from: Edge, ticks: u16 },
+    /// Blend the brightness from the old frame to the new one.
+    Fade { ticks: u16 },
+}
+
+/// What happens after the last step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Repeat {
+    /// Stop on the last frame.
+    Once,
+    /// Start again from the first step.
+    Loop,
+    /// Play the steps backwards, then forwards again, and so on.
+    PingPong,
+}
+
+/// A frame and how long it stays on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Step {
+    pub frame: Frame,
+    /// Ticks including the transition, at least 1.
+    pub ticks: u16,
+    pub transition: Transition,
+}
+
+impl Step {
+    pub const fn new(frame: Frame, ticks: u16) -> Step {
+        Step {
+            frame,
+            ticks,
+            transition: Transition::Cut,
+        }
+    }
+
+    /// Enter the step with `transition`, in either direction when ping-ponging.
+    pub const fn transition(mut self, transition: Transition) -> Step {
+        self.transition = transition;
+        self
+    }
+}
+
+/// Plays `steps`, one [`Animate::tick`] at a time.
+///
+/// The first step transitions from a dark frame.
+pub struct Animation<'a> {
+    steps: &'a [Step],
+    repeat: Repeat,
+    index: usize,
+    elapsed: u16,
+    forward: bool,
+    finished: bool,
+    previous: Frame,
+    frame: Frame,
+}
+
+impl<'a> Animation<'a> {
+    pub fn new(steps: &'a [Step], repeat: Repeat) -> Animation<'a> {
+        let mut animation = Animation {
+            steps,
+            repeat,
+            index: 0,
+            elapsed: 0,
+            forward: true,
+            finished: false,
+            previous: Frame::new(),
+            frame: Frame::new(),
+        };
+        animation.reset();
+        animation
+    }
+
+    /// What to show now.
+    pub fn frame(&self) -> Frame {
+        self.frame
+    }
+
+    /// Index of the step playing.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The step after the current one, `None` at the end of a `Once` animation.
+    fn next_index(&mut self) -> Option<usize> {
+        let last = self.steps.len() - 1;
+        match self.repeat {
+            Repeat::Once => (self.index < last).then_some(self.index + 1),
+            Repeat::Loop => Some(if self.index < last { self.index + 1 } else { 0 }),
+            Repeat::PingPong => {
+                if self.forward && self.index == last || !self.forward && self.index == 0 {
+                    self.forward = !self.forward;
+                }
+                Some(match (self.forward, last) {
+                    (_, 0) => 0,
+                    (true, _) => self.index + 1,
+                    (false, _) => self.index - 1,
+                })
+            }
+        }
+    }
+
+    /// The current step `elapsed` ticks in.
+    fn render(&self) -> Frame {
+        let step = &self.steps[self.index];
+        let ticks = match step.transition {
+            Transition::Cut => return step.frame,
+            Transition::Wipe { ticks, .. } | Transition::Fade { ticks } => ticks,
+        };
+        if self.elapsed >= ticks {
+            return step.frame;
+        }
+        // Share of the way through in steps of 1/(ticks + 1), so neither the
+        // old nor the new frame is shown as part of the transition
+        let (done, total) = (i32::from(self.elapsed) + 1, i32::from(ticks) + 1);
+        let mut frame = Frame::new();
+        for y in 0..HEIGHT as i32 {
+            for x in 0..WIDTH as i32 {
+                let (old, new) = (self.previous.pixel(x, y), step.frame.pixel(x, y));
+                let brightness = match step.transition {
+                    Transition::Wipe { from, .. } => {
+                        let (position, size) = match from {
+                            Edge::Left => (x, WIDTH as i32),
+                            Edge::Right => (WIDTH as i32 - 1 - x, WIDTH as i32),
+                            Edge::Top => (y, HEIGHT as i32),
+                            Edge::Bottom => (HEIGHT as i32 - 1 - y, HEIGHT as i32),
+                        };
+                        if position < size * done / total {
+                            new
+                        } else {
+                            old
+                        }
+                    }
+                    _ => {
+                        let (old, new) = (i32::from(old), i32::from(new));
+                        (old + (new - old) * done / total) as u8
+                    }
+                };
+                frame.set_pixel(x, y, brightness);
+            }
+        }
+        frame
+    }
+}
+
+impl Animate for Animation<'_> {
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn reset(&mut self) {
+        self.index = 0;
+        self.elapsed = 0;
+        self.forward = true;
+        self.finished = self.steps.is_empty();
+        self.previous = Frame::new();
+        self.frame = if self.finished {
+            Frame::new()
+        } else {
+            self.render()
+        };
+    }
+
+    fn tick(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.elapsed += 1;
+        if self.elapsed >= self.steps[self.index].ticks.max(1) {
+            let Some(next) = self.next_index() else {
+                self.finished = true;
+                self.frame = self.steps[self.index].frame;
+                return;
+            };
+            self.previous = self.steps[self.index].frame;
+            self.index = next;
+            self.elapsed = 0;
+        }
+        self.frame = self.render();
+    }
+}
+
+impl Render for Animation<'_> {
+    fn brightness_at(&self, x: usize, y: usize) -> u8 {
+        self.frame.brightness_at(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    const LIT: Frame = Frame::literal("99999:99999:99999:99999:99999");
+    const DOT: Frame = Frame::literal("00000:00000:00900:00000:00000");
+    const CROSS: Frame = Frame::literal("90009:09090:00900:09090:90009");
+
+    /// Index of the step after each of `ticks` ticks.
+    fn indices(animation: &mut Animation, ticks: usize) -> Vec<usize> {
+        (0..ticks)
+            .map(|_| {
+                animation.tick();
+                animation.index()
+            })
+            .collect()
+    }
+
+    /// Frame after each of `ticks` ticks, starting with the one before the first.
+    fn frames(animation: &mut Animation, ticks: usize) -> Vec<Frame> {
+        let mut frames = Vec::from([animation.frame()]);
+        for _ in 0..ticks {
+            animation.tick();
+            frames.push(animation.frame());
+        }
+        frames
+    }
+
+    #[test]
+    fn loops() {
+        let steps = [Step::new(DOT, 2), Step::new(CROSS, 1), Step::new(LIT, 1)];
+        let mut animation = Animation::new(&steps, Repeat::Loop);
+        assert_eq!(indices(&mut animation, 7), [0, 1, 2, 0, 0, 1, 2]);
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn ping_pong_turns_around() {
+        let steps = [Step::new(DOT, 1), Step::new(CROSS, 1), Step::new(LIT, 1)];
+        let mut animation = Animation::new(&steps, Repeat::PingPong);
+        assert_eq!(indices(&mut animation, 8), [1, 2, 1, 0, 1, 2, 1, 0]);
+        assert!(!animation.is_finished());
+
+        let steps = [Step::new(DOT, 1), Step::new(CROSS, 1)];
+        let mut animation = Animation::new(&steps, Repeat::PingPong);
+        assert_eq!(indices(&mut animation, 4), [1, 0, 1, 0]);
+
+        // Reset goes forwards again
+        animation.tick();
+        animation.reset();
+        assert_eq!(indices(&mut animation, 3), [1, 0, 1]);
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let steps = [Step::new(DOT, 2), Step::new(CROSS, 2)];
+        let mut animation = Animation::new(&steps, Repeat::Once);
+        assert_eq!(
+            frames(&mut animation, 6),
+            [DOT, DOT, CROSS, CROSS, CROSS, CROSS, CROSS]
+        );
+        assert!(animation.is_finished());
+        assert_eq!(animation.index(), 1);
+
+        animation.reset();
+        assert!(!animation.is_finished());
+        assert_eq!((animation.index(), animation.frame()), (0, DOT));
+    }
+
+    #[test]
+    fn once_finishes_after_the_last_step() {
+        let steps = [Step::new(DOT, 1), Step::new(CROSS, 3)];
+        let mut animation = Animation::new(&steps, Repeat::Once);
+        let finished: Vec<bool> = (0..5)
+            .map(|_| {
+                animation.tick();
+                animation.is_finished()
+            })
+            .collect();
+        assert_eq!(finished, [false, false, false, true, true]);
+    }
+
+    #[test]
+    fn single_step() {
+        let steps = [Step::new(CROSS, 2)];
+        for repeat in [Repeat::Loop, Repeat::PingPong] {
+            let mut animation = Animation::new(&steps, repeat);
+            assert_eq!(indices(&mut animation, 5), [0; 5]);
+            assert_eq!(animation.frame(), CROSS);
+            assert!(!animation.is_finished());
+        }
+
+        let mut animation = Animation::new(&steps, Repeat::Once);
+        animation.tick();
+        assert!(!animation.is_finished());
+        animation.tick();
+        assert!(animation.is_finished());
+        assert_eq!(animation.frame(), CROSS);
+    }
+
+    #[test]
+    fn zero_ticks_last_one_tick() {
+        let steps = [Step::new(DOT, 0), Step::new(CROSS, 0)];
+        let mut animation = Animation::new(&steps, Repeat::Loop);
+        assert_eq!(indices(&mut animation, 3), [1, 0, 1]);
+    }
+
+    #[test]
+    fn no_steps() {
+        let mut animation = Animation::new(&[], Repeat::Loop);
+        assert!(animation.is_finished());
+        animation.tick();
+        assert_eq!(animation.frame(), Frame::new());
+    }
+
+    #[test]
+    fn wipe() {
+        let wipe = |from| [Step::new(LIT, 5).transition(Transition::Wipe { from, ticks: 4 })];
+
+        // A column more each tick, never all dark nor all lit until the end
+        let steps = wipe(Edge::Left);
+        assert_eq!(
+            frames(&mut Animation::new(&steps, Repeat::Once), 4),
+            [
+                Frame::literal("90000:90000:90000:90000:90000"),
+                Frame::literal("99000:99000:99000:99000:99000"),
+                Frame::literal("99900:99900:99900:99900:99900"),
+                Frame::literal("99990:99990:99990:99990:99990"),
+                LIT,
+            ]
+        );
+        let steps = wipe(Edge::Right);
+        assert_eq!(
+            Animation::new(&steps, Repeat::Once).frame(),
+            Frame::literal("00009:00009:00009:00009:00009")
+        );
+        let steps = wipe(Edge::Top);
+        let mut animation = Animation::new(&steps, Repeat::Once);
+        animation.tick();
+        assert_eq!(
+            animation.frame(),
+            Frame::literal("99999:99999:00000:00000:00000")
+        );
+        let steps = wipe(Edge::Bottom);
+        assert_eq!(
+            Animation::new(&steps, Repeat::Once).frame(),
+            Frame::literal("00000:00000:00000:00000:99999")
+        );
+    }
+
+    #[test]
+    fn wipe_uncovers_the_previous_frame() {
+        let steps = [
+            Step::new(CROSS, 1),
+            Step::new(DOT, 3).transition(Transition::Wipe {
+                from: Edge::Bottom,
+                ticks: 2,
+            }),
+        ];
+        let mut animation = Animation::new(&steps, Repeat::Once);
+        assert_eq!(
+            frames(&mut animation, 3),
+            [
+                CROSS,
+                Frame::literal("90009:09090:00900:09090:00000"),
+                Frame::literal("90009:09090:00900:00000:00000"),
+                DOT,
+            ]
+        );
+    }
+
+    #[test]
+    fn fade() {
+        let steps = [
+            Step::new(LIT, 3).transition(Transition::Fade { ticks: 2 }),
+            Step::new(DOT, 3).transition(Transition::Fade { ticks: 2 }),
+        ];
+        let mut animation = Animation::new(&steps, Repeat::Once);
+        let corner_and_centre: Vec<(u8, u8)> = frames(&mut animation, 6)
+            .iter()
+            .map(|frame| (frame.pixel(0, 0), frame.pixel(2, 2)))
+            .collect();
+        // In from dark, then the corners fade out while the centre stays lit
+        assert_eq!(
+            corner_and_centre,
+            [(3, 3), (6, 6), (9, 9), (6, 9), (3, 9), (0, 9), (0, 9)]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+#![no_main]
+#![no_std]
+
+use microbit::{
+    board::Board,
+    display::nonblocking::{Display, Frame as _, MicrobitFrame},
+    hal::{
+        clocks::Clocks,
+        gpiote::Gpiote,
+        rtc::{Rtc, RtcInterrupt},
+    },
+    pac,
+};
+use microbit_text::scrolling::Animate;
+
+use microbit_v2_examples::{
+    self as _,
+    animation::{Animation, Edge, Repeat, Step, Transition},
+    frame::Frame,
+    images,
+};
+
+/// Steps of the animations, in ticks of 50ms.
+static HEARTBEAT: [Step; 2] = [
+    Step::new(images::HEART, 8).transition(Transition::Fade { ticks: 3 }),
+    Step::new(images::HEART_SMALL, 8).transition(Transition::Fade { ticks: 3 }),
+];
+static CLOCK: [Step; 12] = steps(images::ALL_CLOCKS, 2);
+static ARROWS: [Step; 8] = wipes(steps(images::ALL_ARROWS, 8));
+
+static ANIMATIONS: [(&str, &[Step], Repeat); 3] = [
+    ("heartbeat", &HEARTBEAT, Repeat::Loop),
+    ("clock", &CLOCK, Repeat::Loop),
+    ("arrows", &ARROWS, Repeat::PingPong),
+];
+
+/// A step per frame, each on for `ticks`.
+const fn steps<const N: usize>(frames: [Frame; N], ticks: u16) -> [Step; N] {
+    let mut steps = [Step::new(Frame::new(), ticks); N];
+    let mut i = 0;
+    while i < N {
+        steps[i] = Step::new(frames[i], ticks);
+        i += 1;
+    }
+    steps
+}
+
+/// Wipe each step in from the left.
+const fn wipes<const N: usize>(mut steps: [Step; N]) -> [Step; N] {
+    let mut i = 0;
+    while i < N {
+        steps[i] = steps[i].transition(Transition::Wipe {
+            from: Edge::Left,
+            ticks: 4,
+        });
+        i += 1;
+    }
+    steps
+}
+
+#[rtic::app(device = microbit::pac, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        display: Display<pac::TIMER1>,
+        animation: Animation<'static>,
+    }
+
+    #[local]
+    struct Local {
+        rtc: Rtc<pac::RTC0>,
+        gpiote: Gpiote,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+
+        // Starting the low-frequency clock (needed for RTC to work)
+        Clocks::new(board.CLOCK).start_lfclk();
+
+        // RTC at 20Hz (32_768 / (1637 + 1))
+        let mut rtc = Rtc::new(board.RTC0, 1637).unwrap();
+        rtc.enable_event(RtcInterrupt::Tick);
+        rtc.enable_interrupt(RtcInterrupt::Tick, None);
+        rtc.enable_counter();
+
+        let gpiote = Gpiote::new(board.GPIOTE);
+        gpiote
+            .channel0()
+            .input_pin(&board.buttons.button_a.degrade())
+            .hi_to_lo()
+            .enable_interrupt();
+
+        let display = Display::new(board.TIMER1, board.display_pins);
+        let (_, steps, repeat) = ANIMATIONS[0];
+
+        (
+            Shared {
+                display,
+                animation: Animation::new(steps, repeat),
+            },
+            Local { rtc, gpiote },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = TIMER1, priority = 2, shared = [display])]
+    fn timer1(mut cx: timer1::Context) {
+        cx.shared
+            .display
+            .lock(|display| display.handle_display_event());
+    }
+
+    #[task(binds = RTC0, shared = [display, animation],
+           local = [rtc, frame: MicrobitFrame = MicrobitFrame::default()])]
+    fn rtc0(cx: rtc0::Context) {
+        let rtc0::SharedResources {
+            mut display,
+            mut animation,
+        } = cx.shared;
+        let frame = cx.local.frame;
+        cx.local.rtc.reset_event(RtcInterrupt::Tick);
+
+        animation.lock(|animation| {
+            animation.tick();
+            frame.set(animation);
+        });
+        display.lock(|display| display.show_frame(frame));
+    }
+
+    /// A switches to the next animation.
+    #[task(binds = GPIOTE, shared = [animation], local = [gpiote, index: usize = 0])]
+    fn gpiote(mut cx: gpiote::Context) {
+        let index = cx.local.index;
+        cx.local.gpiote.reset_events();
+        *index = (*index + 1) % ANIMATIONS.len();
+
+        let (name, steps, repeat) = ANIMATIONS[*index];
+        defmt::info!("{}", name);
+        cx.shared
+            .animation
+            .lock(|animation| *animation = Animation::new(steps, repeat));
+    }
+}
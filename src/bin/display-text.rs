@@ -1,35 +1,48 @@
 #![no_main]
 #![no_std]
 
+use core::fmt::Write;
+
+use heapless::String;
 use microbit::{
     board::Board,
     display::nonblocking::{Display, Frame, MicrobitFrame},
-    hal::{
-        clocks::Clocks,
-        rtc::{Rtc, RtcInterrupt},
-    },
+    hal::clocks::Clocks,
     pac,
 };
-use microbit_text::{scrolling::Animate, scrolling_text::ScrollingStaticText};
+use microbit_text::scrolling::Animate;
+
+use microbit_v2_examples::{
+    self as _, images,
+    rtc_monotonic::{Duration, RtcMonotonic},
+    scroller::TextScroller,
+};
 
-use microbit_v2_examples as _;
+/// Messages of up to 32 characters, 4 waiting.
+type Scroller = TextScroller<32, 4>;
 
-#[rtic::app(device = microbit::pac, peripherals = true)]
+#[rtic::app(device = microbit::pac, peripherals = true, dispatchers = [SWI0_EGU0])]
 mod app {
     use super::*;
 
-    const MESSAGE: &[u8] = b"Hello, world!";
+    const MESSAGE: &str = "Hello, world!";
+    /// Glyphs from the Latin-1 extension, the arrows and one of our own.
+    const EXTENDED: &str = "21°C ↑ Grüße ☺";
+    /// 16Hz
+    const FRAME_PERIOD: Duration = Duration::micros(62_500);
+    const UPTIME_PERIOD: Duration = Duration::secs(15);
+
+    #[monotonic(binds = RTC0, default = true)]
+    type Mono = RtcMonotonic<pac::RTC0>;
 
     #[shared]
     struct Shared {
         display: Display<pac::TIMER1>,
+        scroller: Scroller,
     }
 
     #[local]
-    struct Local {
-        anim_timer: Rtc<pac::RTC0>,
-        scroller: ScrollingStaticText,
-    }
+    struct Local {}
 
     #[init]
     fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
@@ -38,25 +51,23 @@ mod app {
         // Starting the low-frequency clock (needed for RTC to work)
         Clocks::new(board.CLOCK).start_lfclk();
 
-        // RTC at 16Hz (32_768 / (2047 + 1))
-        // 16Hz; 62.5ms period
-        let mut rtc0 = Rtc::new(board.RTC0, 2047).unwrap();
-        rtc0.enable_event(RtcInterrupt::Tick);
-        rtc0.enable_interrupt(RtcInterrupt::Tick, None);
-        rtc0.enable_counter();
+        let mono = RtcMonotonic::new(board.RTC0);
 
         let display = Display::new(board.TIMER1, board.display_pins);
 
-        let mut scroller = ScrollingStaticText::default();
-        scroller.set_message(MESSAGE);
+        let mut scroller = Scroller::new();
+        scroller.set_repeat(2);
+        scroller.font_mut().register('☺', images::HAPPY).unwrap();
+        scroller.show_text(MESSAGE).unwrap();
+        scroller.push(EXTENDED).unwrap();
+
+        scroll::spawn().unwrap();
+        uptime::spawn_after(UPTIME_PERIOD).unwrap();
 
         (
-            Shared { display },
-            Local {
-                anim_timer: rtc0,
-                scroller,
-            },
-            init::Monotonics(),
+            Shared { display, scroller },
+            Local {},
+            init::Monotonics(mono),
         )
     }
 
@@ -67,21 +78,35 @@ mod app {
             .lock(|display| display.handle_display_event());
     }
 
-    #[task(binds = RTC0, priority = 1, shared = [display],
-           local = [anim_timer, scroller,
-                    frame: MicrobitFrame = MicrobitFrame::default()])]
-    fn rtc0(cx: rtc0::Context) {
+    #[task(priority = 1, shared = [display, scroller],
+           local = [frame: MicrobitFrame = MicrobitFrame::default()])]
+    fn scroll(cx: scroll::Context) {
         let mut shared = cx.shared;
-        let local = cx.local;
-        local.anim_timer.reset_event(RtcInterrupt::Tick);
-        if !local.scroller.is_finished() {
-            local.scroller.tick();
-            local.frame.set(local.scroller);
-            shared.display.lock(|display| {
-                display.show_frame(local.frame);
-            });
-        } else {
-            local.scroller.reset();
+        let frame = cx.local.frame;
+        scroll::spawn_after(FRAME_PERIOD).unwrap();
+        shared.scroller.lock(|scroller| {
+            scroller.tick();
+            frame.set(scroller);
+        });
+        shared.display.lock(|display| {
+            display.show_frame(frame);
+        });
+    }
+
+    /// Queue the time since boot behind whatever is scrolling.
+    #[task(priority = 1, shared = [scroller])]
+    fn uptime(mut cx: uptime::Context) {
+        uptime::spawn_after(UPTIME_PERIOD).unwrap();
+        let secs = monotonics::now().duration_since_epoch().to_secs();
+        let mut message = String::<32>::new();
+        write!(message, "Up {} s", secs).unwrap();
+        show_text(&mut cx.shared.scroller, &message);
+    }
+
+    /// Copy `text` into the scroller from any task, dropped if the queue is full.
+    fn show_text(scroller: &mut impl rtic::Mutex<T = Scroller>, text: &str) {
+        if let Err(error) = scroller.lock(|scroller| scroller.push(text)) {
+            defmt::warn!("{}: {}", text, error);
         }
     }
 }
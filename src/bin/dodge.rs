@@ -0,0 +1,123 @@
+#![no_main]
+#![no_std]
+
+use embedded_hal::digital::v2::InputPin;
+use lsm303agr::{interface::I2cInterface, mode::MagOneShot, AccelOutputDataRate, Lsm303agr};
+use microbit::{
+    board::{Board, Buttons},
+    display::nonblocking::Display,
+    hal::{twim::Twim, Rng},
+    pac::{self, twim0::frequency::FREQUENCY_A},
+};
+
+use microbit_v2_examples::{
+    self as _,
+    dodge::Dodge,
+    game::{Game, Input, Runner, Tilt},
+    monotonic::{Duration, Instant, MonoTimer},
+    touch::{Pad, TouchConfig, TouchSensor},
+};
+
+type Sensor = Lsm303agr<I2cInterface<Twim<pac::TWIM0>>, MagOneShot>;
+
+/// Everything a player can do to the board.
+struct Controls {
+    buttons: Buttons,
+    /// Only the logo, keep your hands off it while the game starts.
+    touch: TouchSensor<pac::TIMER2, 1>,
+    sensor: Sensor,
+}
+
+impl Controls {
+    fn read(&mut self, input: &mut Input) {
+        let accel = self.sensor.accel_data().unwrap();
+        self.touch.scan();
+        input.update(
+            self.buttons.button_a.is_low().unwrap(),
+            self.buttons.button_b.is_low().unwrap(),
+            self.touch.is_touched(Pad::Logo),
+            Tilt::new(accel.x, accel.y),
+        );
+    }
+}
+
+#[rtic::app(device = microbit::pac, peripherals = true, dispatchers = [SWI0_EGU0])]
+mod app {
+    use super::*;
+
+    /// 20 steps a second
+    const STEP: Duration = Duration::millis(50);
+
+    #[monotonic(binds = TIMER0, default = true)]
+    type Mono = MonoTimer<pac::TIMER0>;
+
+    #[shared]
+    struct Shared {
+        display: Display<pac::TIMER1>,
+    }
+
+    #[local]
+    struct Local {
+        controls: Controls,
+        runner: Runner<Dodge>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+        let mono = MonoTimer::new(board.TIMER0);
+        let display = Display::new(board.TIMER1, board.display_pins);
+
+        let i2c = Twim::new(board.TWIM0, board.i2c_internal.into(), FREQUENCY_A::K100);
+        let mut sensor = Lsm303agr::new_with_i2c(i2c);
+        sensor.init().unwrap();
+        sensor.set_accel_odr(AccelOutputDataRate::Hz50).unwrap();
+
+        let controls = Controls {
+            buttons: board.buttons,
+            touch: TouchSensor::new(
+                board.TIMER2,
+                [(Pad::Logo, board.pins.p1_04.degrade())],
+                TouchConfig::default(),
+            ),
+            sensor,
+        };
+        let seed = Rng::new(board.RNG).random_u32();
+
+        step::spawn().unwrap();
+
+        (
+            Shared { display },
+            Local {
+                controls,
+                runner: Runner::new(Dodge::new(seed)),
+            },
+            init::Monotonics(mono),
+        )
+    }
+
+    #[task(binds = TIMER1, priority = 2, shared = [display])]
+    fn timer1(mut cx: timer1::Context) {
+        cx.shared
+            .display
+            .lock(|display| display.handle_display_event());
+    }
+
+    /// One step of the game, re-spawned a fixed time after the last one was due
+    /// so the steps don't drift.
+    #[task(shared = [display],
+           local = [controls, runner, input: Input = Input::new(), due: Option<Instant> = None])]
+    fn step(mut cx: step::Context) {
+        let due = cx.local.due.get_or_insert_with(monotonics::now);
+        *due += STEP;
+        step::spawn_at(*due).unwrap();
+
+        let playing = cx.local.runner.is_playing();
+        cx.local.controls.read(cx.local.input);
+        let frame = cx.local.runner.step(cx.local.input);
+        if playing && !cx.local.runner.is_playing() {
+            defmt::info!("game over, score {}", cx.local.runner.game().score());
+        }
+        cx.shared.display.lock(|display| display.show(&frame));
+    }
+}
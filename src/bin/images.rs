@@ -0,0 +1,76 @@
+#![no_main]
+#![no_std]
+
+use microbit::{
+    board::Board,
+    display::nonblocking::{Display, MicrobitFrame},
+    hal::gpiote::Gpiote,
+    pac,
+};
+
+use microbit_v2_examples::{self as _, images};
+
+#[rtic::app(device = microbit::pac, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        display: Display<pac::TIMER1>,
+    }
+
+    #[local]
+    struct Local {
+        gpiote: Gpiote,
+        index: usize,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+        let mut display = Display::new(board.TIMER1, board.display_pins);
+        show(&mut display, 0);
+
+        let gpiote = Gpiote::new(board.GPIOTE);
+        gpiote
+            .channel0()
+            .input_pin(&board.buttons.button_a.degrade())
+            .hi_to_lo()
+            .enable_interrupt();
+        gpiote
+            .channel1()
+            .input_pin(&board.buttons.button_b.degrade())
+            .hi_to_lo()
+            .enable_interrupt();
+
+        (Shared { display }, Local { gpiote, index: 0 }, init::Monotonics())
+    }
+
+    #[task(binds = TIMER1, priority = 2, shared = [display])]
+    fn timer1(mut cx: timer1::Context) {
+        cx.shared
+            .display
+            .lock(|display| display.handle_display_event());
+    }
+
+    /// A shows the previous image, B the next one.
+    #[task(binds = GPIOTE, local = [gpiote, index], shared = [display])]
+    fn gpiote(mut cx: gpiote::Context) {
+        let gpiote = cx.local.gpiote;
+        let index = cx.local.index;
+        let count = images::ALL.len();
+        if gpiote.channel0().is_event_triggered() {
+            *index = (*index + count - 1) % count;
+        } else if gpiote.channel1().is_event_triggered() {
+            *index = (*index + 1) % count;
+        }
+        gpiote.reset_events();
+        cx.shared.display.lock(|display| show(display, *index));
+    }
+
+    fn show(display: &mut Display<pac::TIMER1>, index: usize) {
+        let (name, image) = &images::ALL[index];
+        defmt::info!("{}", name);
+        display.show_frame(&MicrobitFrame::from(image));
+    }
+}
@@ -14,6 +14,7 @@ use microbit::pac::twim0::frequency::FREQUENCY_A;
 use microbit_v2_examples::{
     self as _,
     calibration::calibrated_measurement,
+    declination::true_heading,
     led::{direction_to_led, Direction},
 };
 
@@ -27,9 +28,16 @@ use {
         hal::uarte::{Baudrate, Parity},
         hal::Uarte,
     },
-    microbit_v2_examples::{calibration::calc_calibration, serial_setup::UartePort},
+    microbit_v2_examples::{
+        calibration::calc_calibration,
+        serial_setup::{DmaBuffers, UartePort},
+    },
 };
 
+/// Where the board is used, needed to correct magnetic north to true north.
+const LATITUDE: f32 = 51.5;
+const LONGITUDE: f32 = -0.13;
+
 #[cortex_m_rt::entry]
 fn main() -> ! {
     let board = Board::take().unwrap();
@@ -41,7 +49,8 @@ fn main() -> ! {
             Parity::EXCLUDED,
             Baudrate::BAUD115200,
         );
-        UartePort::new(serial)
+        let dma = cortex_m::singleton!(: DmaBuffers = DmaBuffers::new()).unwrap();
+        UartePort::new(serial, dma)
     };
     let mut timer = Timer::new(board.TIMER0);
     let mut display = Display::new(board.display_pins);
@@ -73,26 +82,11 @@ fn main() -> ! {
         data = calibrated_measurement(data, &calibration);
 
         let theta = atan2f(data.y as f32, data.x as f32);
+        // atan2 is counter-clockwise from east, headings are clockwise from north
+        let magnetic = 90. - theta * 180. / PI;
+        let heading = true_heading(magnetic, LATITUDE, LONGITUDE);
 
-        let dir = if theta < -7. * PI / 8. {
-            Direction::West
-        } else if theta < -5. * PI / 8. {
-            Direction::SouthWest
-        } else if theta < -3. * PI / 8. {
-            Direction::South
-        } else if theta < -PI / 8. {
-            Direction::SouthEast
-        } else if theta < PI / 8. {
-            Direction::East
-        } else if theta < 3. * PI / 8. {
-            Direction::NorthEast
-        } else if theta < 5. * PI / 8. {
-            Direction::North
-        } else if theta < 7. * PI / 8. {
-            Direction::NorthWest
-        } else {
-            Direction::West
-        };
+        let dir = Direction::from_heading(heading);
 
         display.show(&mut timer, direction_to_led(dir), 100);
     }
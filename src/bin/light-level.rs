@@ -0,0 +1,58 @@
+#![no_main]
+#![no_std]
+
+use microbit::{board::Board, display::nonblocking::Display, pac};
+
+use microbit_v2_examples::{
+    self as _,
+    frame::{Frame, HEIGHT, MAX_BRIGHTNESS, WIDTH},
+    light::{LightConfig, LightSensor},
+};
+
+#[rtic::app(device = microbit::pac, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
+        display: Display<pac::TIMER1>,
+        light: LightSensor,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+        let display = Display::new(board.TIMER1, board.display_pins);
+        let light = LightSensor::new(
+            board.SAADC,
+            LightConfig {
+                auto_brightness: true,
+                ..Default::default()
+            },
+        );
+
+        (Shared {}, Local { display, light }, init::Monotonics())
+    }
+
+    /// Shows the light level as a bar filling up from the bottom, dimmer in the
+    /// dark.
+    #[task(binds = TIMER1, priority = 2, local = [display, light])]
+    fn timer1(cx: timer1::Context) {
+        let (display, light) = (cx.local.display, cx.local.light);
+        let Some(level) = light.handle_display_event(display) else {
+            return;
+        };
+        defmt::info!("light {} (raw {})", level, light.raw());
+
+        let lit = (usize::from(level) * WIDTH * HEIGHT / 255) as i32;
+        let mut frame = Frame::new();
+        for i in 0..lit {
+            let (x, y) = (i % WIDTH as i32, HEIGHT as i32 - 1 - i / WIDTH as i32);
+            frame.set_pixel(x, y, MAX_BRIGHTNESS);
+        }
+        display.show(&light.dim(frame));
+    }
+}
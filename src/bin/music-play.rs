@@ -1,9 +1,22 @@
 #![no_main]
 #![no_std]
 
-use microbit::{hal::gpio::Level, pac, Board};
-
-use microbit_v2_examples::{self as _, monotonic::MonoTimer, music::Music};
+use embedded_hal::digital::v2::InputPin;
+use heapless::spsc::{Consumer, Producer, Queue};
+use microbit::{
+    hal::gpio::{Floating, Input, Level, Pin},
+    pac, Board,
+};
+
+use microbit_v2_examples::{
+    self as _,
+    buttons::{Button, ButtonConfig, ButtonEvent, Buttons, Kind},
+    monotonic::{Instant, MonoTimer},
+    music::Music,
+};
+
+/// Volume change per click or hold.
+const STEP: u32 = 10;
 
 #[rtic::app(device = microbit::pac, dispatchers = [RTC0, RTC1, RTC2])]
 mod app {
@@ -17,14 +30,22 @@ mod app {
     #[shared]
     struct Shared {
         music: Music<pac::PWM0, pac::TIMER1>,
+        buttons: Buttons,
+        /// The `poll` waiting for the next deadline of `buttons`.
+        poll_handle: Option<poll::SpawnHandle>,
+        /// Events for `react`, from `poll` and from edges catching up.
+        producer: Producer<'static, ButtonEvent, 8>,
     }
 
     #[local]
     struct Local {
         gpiote: Gpiote,
+        btn_a_pin: Pin<Input<Floating>>,
+        btn_b_pin: Pin<Input<Floating>>,
+        consumer: Consumer<'static, ButtonEvent, 8>,
     }
 
-    #[init]
+    #[init(local = [queue: Queue<ButtonEvent, 8> = Queue::new()])]
     fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         let board = Board::new(cx.device, cx.core);
         let btn_a_pin = board.buttons.button_a.degrade();
@@ -36,19 +57,35 @@ mod app {
         let music = Music::new(speaker_pin, board.PWM0, board.TIMER1);
         let mono = MonoTimer::new(board.TIMER0);
         let gpiote = Gpiote::new(board.GPIOTE);
+        let (producer, consumer) = cx.local.queue.split();
 
+        // Both edges, the buttons module debounces them
         gpiote
             .channel0()
             .input_pin(&btn_a_pin)
-            .hi_to_lo()
+            .toggle()
             .enable_interrupt();
         gpiote
             .channel1()
             .input_pin(&btn_b_pin)
-            .hi_to_lo()
+            .toggle()
             .enable_interrupt();
 
-        (Shared { music }, Local { gpiote }, init::Monotonics(mono))
+        (
+            Shared {
+                music,
+                buttons: Buttons::new(ButtonConfig::default()),
+                poll_handle: None,
+                producer,
+            },
+            Local {
+                gpiote,
+                btn_a_pin,
+                btn_b_pin,
+                consumer,
+            },
+            init::Monotonics(mono),
+        )
     }
 
     #[task(binds = TIMER1, shared = [music])]
@@ -56,31 +93,69 @@ mod app {
         cx.shared.music.lock(|music| music.next_tick());
     }
 
-    #[task(binds = GPIOTE, local = [gpiote], shared = [music])]
-    fn gpiote(mut cx: gpiote::Context) {
-        let gpiote = cx.local.gpiote;
-        let apressed = gpiote.channel0().is_event_triggered();
-        let bpressed = gpiote.channel1().is_event_triggered();
-
-        defmt::info!(
-            "Button pressed {:?}",
-            match (apressed, bpressed) {
-                (false, false) => "",
-                (true, false) => "A",
-                (false, true) => "B",
-                (true, true) => "A + B",
-            }
-        );
-
-        cx.shared.music.lock(|music| {
-            if apressed {
-                music.set_volume(music.volume() - 10);
-            } else if bpressed {
-                music.set_volume(music.volume() + 10);
-            }
+    #[task(binds = GPIOTE, local = [gpiote, btn_a_pin, btn_b_pin],
+           shared = [buttons, poll_handle, producer])]
+    fn gpiote(cx: gpiote::Context) {
+        let local = cx.local;
+        local.gpiote.reset_events();
+        let a = local.btn_a_pin.is_low().unwrap();
+        let b = local.btn_b_pin.is_low().unwrap();
+
+        let mut shared = (cx.shared.buttons, cx.shared.poll_handle, cx.shared.producer);
+        shared.lock(|buttons, poll_handle, producer| {
+            buttons.edge(monotonics::now(), a, b, producer);
+            schedule(buttons.next_deadline(), poll_handle);
+            wake_react(producer);
+        });
+    }
+
+    /// Queues the button events that are due.
+    #[task(shared = [buttons, poll_handle, producer])]
+    fn poll(cx: poll::Context) {
+        let mut shared = (cx.shared.buttons, cx.shared.poll_handle, cx.shared.producer);
+        shared.lock(|buttons, poll_handle, producer| {
+            buttons.poll(monotonics::now(), producer);
+            schedule(buttons.next_deadline(), poll_handle);
+            wake_react(producer);
         });
+    }
+
+    /// Turns the volume down with A and up with B, all the way with a double
+    /// click and step by step while held.
+    #[task(local = [consumer], shared = [music])]
+    fn react(mut cx: react::Context) {
+        while let Some(event) = cx.local.consumer.dequeue() {
+            defmt::info!("{}", event);
+            cx.shared.music.lock(|music| {
+                let volume = *music.volume();
+                let volume = match (event.button, event.kind) {
+                    (Button::A, Kind::DoubleClick) => 0,
+                    (Button::B, Kind::DoubleClick) => 100,
+                    (Button::A, _) => volume.saturating_sub(STEP),
+                    (Button::B, _) => volume + STEP,
+                    (Button::AB, _) => volume,
+                };
+                music.set_volume(volume);
+                defmt::info!("volume {}", music.volume());
+            });
+        }
+    }
+
+    /// Have `react` handle what was queued.
+    fn wake_react(producer: &Producer<'static, ButtonEvent, 8>) {
+        if producer.len() > 0 {
+            react::spawn().ok();
+        }
+    }
 
-        gpiote.reset_events();
+    /// Move the `poll` waiting in `handle` to `deadline`.
+    fn schedule(deadline: Option<Instant>, handle: &mut Option<poll::SpawnHandle>) {
+        if let Some(handle) = handle.take() {
+            // Fails once it is due, even if it hasn't run yet
+            handle.cancel().ok();
+        }
+        // A `poll` that is due already schedules the next one when it runs
+        *handle = deadline.and_then(|deadline| poll::spawn_at(deadline).ok());
     }
 
     #[idle]
@@ -0,0 +1,132 @@
+#![no_main]
+#![no_std]
+
+use core::fmt::Write;
+
+use heapless::String;
+use lsm303agr::{interface::I2cInterface, mode::MagOneShot, AccelOutputDataRate, Lsm303agr};
+use microbit::{
+    board::Board,
+    display::nonblocking::{Display, Frame, MicrobitFrame},
+    hal::{
+        clocks::Clocks,
+        rtc::{Rtc, RtcInterrupt},
+        twim::Twim,
+    },
+    pac::{self, twim0::frequency::FREQUENCY_A},
+};
+use microbit_text::{scrolling::Animate, scrolling_text::ScrollingBufferedText};
+
+use microbit_v2_examples::{
+    self as _,
+    pedometer::{magnitude, Pedometer, PedometerConfig},
+};
+
+type Sensor = Lsm303agr<I2cInterface<Twim<pac::TWIM0>>, MagOneShot>;
+
+#[rtic::app(device = microbit::pac, peripherals = true)]
+mod app {
+    use super::*;
+
+    const SAMPLE_RATE_HZ: u32 = 50;
+    /// Samples between two scroller ticks, 12.5Hz
+    const SCROLL_DIVIDER: u32 = 4;
+
+    #[shared]
+    struct Shared {
+        display: Display<pac::TIMER1>,
+    }
+
+    #[local]
+    struct Local {
+        sample_timer: Rtc<pac::RTC0>,
+        sensor: Sensor,
+        pedometer: Pedometer,
+        scroller: ScrollingBufferedText<10>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+
+        // Starting the low-frequency clock (needed for RTC to work)
+        Clocks::new(board.CLOCK).start_lfclk();
+
+        // RTC at 50Hz (32_768 / (654 + 1))
+        let mut rtc0 = Rtc::new(board.RTC0, 654).unwrap();
+        rtc0.enable_event(RtcInterrupt::Tick);
+        rtc0.enable_interrupt(RtcInterrupt::Tick, None);
+        rtc0.enable_counter();
+
+        let i2c = Twim::new(board.TWIM0, board.i2c_internal.into(), FREQUENCY_A::K100);
+        let mut sensor = Lsm303agr::new_with_i2c(i2c);
+        sensor.init().unwrap();
+        sensor.set_accel_odr(AccelOutputDataRate::Hz100).unwrap();
+
+        let pedometer = Pedometer::new(PedometerConfig {
+            sample_rate_hz: SAMPLE_RATE_HZ,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let display = Display::new(board.TIMER1, board.display_pins);
+
+        let mut scroller = ScrollingBufferedText::default();
+        scroller.set_message(b"0");
+
+        (
+            Shared { display },
+            Local {
+                sample_timer: rtc0,
+                sensor,
+                pedometer,
+                scroller,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = TIMER1, priority = 2, shared = [display])]
+    fn timer1(mut cx: timer1::Context) {
+        cx.shared
+            .display
+            .lock(|display| display.handle_display_event());
+    }
+
+    #[task(binds = RTC0, priority = 1, shared = [display],
+           local = [sample_timer, sensor, pedometer, scroller,
+                    ticks: u32 = 0,
+                    frame: MicrobitFrame = MicrobitFrame::default()])]
+    fn rtc0(cx: rtc0::Context) {
+        let mut shared = cx.shared;
+        let local = cx.local;
+        local.sample_timer.reset_event(RtcInterrupt::Tick);
+
+        let data = local.sensor.accel_data().unwrap();
+        if local.pedometer.update(magnitude(data)) {
+            defmt::info!(
+                "steps: {}, cadence: {}/min",
+                local.pedometer.steps(),
+                local.pedometer.cadence()
+            );
+        }
+
+        *local.ticks += 1;
+        if *local.ticks < SCROLL_DIVIDER {
+            return;
+        }
+        *local.ticks = 0;
+
+        if local.scroller.is_finished() {
+            // Pick up the latest count each time the message has scrolled past
+            let mut message = String::<10>::new();
+            write!(message, "{}", local.pedometer.steps()).unwrap();
+            local.scroller.set_message(message.as_bytes());
+        }
+        local.scroller.tick();
+        local.frame.set(local.scroller);
+        shared.display.lock(|display| {
+            display.show_frame(local.frame);
+        });
+    }
+}
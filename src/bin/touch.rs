@@ -0,0 +1,91 @@
+#![no_main]
+#![no_std]
+
+use microbit::{board::Board, display::nonblocking::Display, pac};
+
+use microbit_v2_examples::{
+    self as _,
+    frame::{Frame, MAX_BRIGHTNESS},
+    monotonic::{Duration, Instant, MonoTimer},
+    touch::{Pad, TouchConfig, TouchSensor},
+};
+
+/// Where a touched pad lights up, the logo at the top and the pins below it.
+const PADS: [(Pad, i32, i32); 4] = [
+    (Pad::Logo, 2, 0),
+    (Pad::P0, 0, 4),
+    (Pad::P1, 2, 4),
+    (Pad::P2, 4, 4),
+];
+
+#[rtic::app(device = microbit::pac, peripherals = true, dispatchers = [SWI0_EGU0])]
+mod app {
+    use super::*;
+
+    /// 50 scans a second
+    const SCAN_PERIOD: Duration = Duration::millis(20);
+
+    #[monotonic(binds = TIMER0, default = true)]
+    type Mono = MonoTimer<pac::TIMER0>;
+
+    #[shared]
+    struct Shared {
+        display: Display<pac::TIMER1>,
+    }
+
+    #[local]
+    struct Local {
+        touch: TouchSensor<pac::TIMER2, 4>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::new(cx.device, cx.core);
+        let mono = MonoTimer::new(board.TIMER0);
+        let display = Display::new(board.TIMER1, board.display_pins);
+        let pins = board.pins;
+        let touch = TouchSensor::new(
+            board.TIMER2,
+            [
+                (Pad::Logo, pins.p1_04.degrade()),
+                (Pad::P0, pins.p0_02.degrade()),
+                (Pad::P1, pins.p0_03.degrade()),
+                (Pad::P2, pins.p0_04.degrade()),
+            ],
+            TouchConfig::default(),
+        );
+
+        scan::spawn().unwrap();
+
+        (Shared { display }, Local { touch }, init::Monotonics(mono))
+    }
+
+    #[task(binds = TIMER1, priority = 2, shared = [display])]
+    fn timer1(mut cx: timer1::Context) {
+        cx.shared
+            .display
+            .lock(|display| display.handle_display_event());
+    }
+
+    /// Lights a dot for every pad touched, keep your hands off the pads while
+    /// it starts.
+    #[task(shared = [display], local = [touch, due: Option<Instant> = None])]
+    fn scan(mut cx: scan::Context) {
+        let due = cx.local.due.get_or_insert_with(monotonics::now);
+        *due += SCAN_PERIOD;
+        scan::spawn_at(*due).unwrap();
+
+        let touch = cx.local.touch;
+        for event in touch.scan() {
+            defmt::info!("{}", event);
+        }
+
+        let mut frame = Frame::new();
+        for &(pad, x, y) in PADS.iter() {
+            if touch.is_touched(pad) {
+                frame.set_pixel(x, y, MAX_BRIGHTNESS);
+            }
+        }
+        cx.shared.display.lock(|display| display.show(&frame));
+    }
+}
@@ -0,0 +1,538 @@
+//! Debounced buttons A and B with clicks, double clicks and long presses.
+//!
+//! Feed [`Buttons`] the raw button levels whenever GPIOTE sees an edge and poll
+//! it when [`Buttons::next_deadline`] is due. A level has to hold for the
+//! debounce time before it counts, so a bouncing contact gives one press. An
+//! edge first catches up on the deadlines before it, so a poll that hasn't run
+//! yet loses nothing.
+//! Pressing A and B together is a press of [`Button::AB`], the single buttons
+//! are not reported for it.
+//!
+//! A short press is a [`Kind::Click`] once no second press followed within the
+//! double click time, or a [`Kind::DoubleClick`] with it. A press held for the
+//! long press time gives a [`Kind::LongPress`] and then a [`Kind::Hold`] every
+//! hold period until it is released.
+//!
+//! ```ignore
+//! // GPIOTE task, both edges of both buttons
+//! let (a, b) = (button_a.is_low()?, button_b.is_low()?);
+//! buttons.edge(monotonics::now(), a, b, &mut producer);
+//! // software task spawned at buttons.next_deadline()
+//! buttons.poll(monotonics::now(), &mut producer);
+//! ```
+
+use heapless::spsc::Producer;
+
+use crate::monotonic::{Duration, ExtU64, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Button {
+    A,
+    B,
+    /// A and B pressed together.
+    AB,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Kind {
+    Click,
+    DoubleClick,
+    LongPress,
+    /// Repeats while a long press is held.
+    Hold,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct ButtonEvent {
+    pub button: Button,
+    pub kind: Kind,
+    pub timestamp: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ButtonConfig {
+    /// How long a level has to hold to count.
+    pub debounce: Duration,
+    /// Most time from releasing a click to pressing again for a double click.
+    pub double_click: Duration,
+    pub long_press: Duration,
+    /// Time between two [`Kind::Hold`]s.
+    pub hold_period: Duration,
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        ButtonConfig {
+            debounce: 20.millis(),
+            double_click: 300.millis(),
+            long_press: 600.millis(),
+            hold_period: 200.millis(),
+        }
+    }
+}
+
+/// One button's contact.
+#[derive(Clone, Copy)]
+struct Debounce {
+    stable: bool,
+    /// A different level and when it was first seen.
+    pending: Option<(bool, Instant)>,
+}
+
+impl Debounce {
+    const fn new() -> Debounce {
+        Debounce {
+            stable: false,
+            pending: None,
+        }
+    }
+
+    fn edge(&mut self, now: Instant, level: bool) {
+        self.pending = match self.pending {
+            // Bounced back
+            _ if level == self.stable => None,
+            Some((pending, since)) if pending == level => Some((pending, since)),
+            _ => Some((level, now)),
+        };
+    }
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Idle,
+    Down {
+        button: Button,
+        since: Instant,
+        /// A click released in time for this press to make it a double click.
+        clicked: Option<Button>,
+        /// When the next hold is due once it is a long press.
+        hold: Option<Instant>,
+    },
+    /// After a click, waiting to see whether a double click follows.
+    Released {
+        button: Button,
+        until: Instant,
+    },
+}
+
+/// Turns button levels into [`ButtonEvent`]s.
+pub struct Buttons {
+    config: ButtonConfig,
+    contacts: [Debounce; 2],
+    state: State,
+    dropped: u32,
+}
+
+impl Buttons {
+    pub const fn new(config: ButtonConfig) -> Buttons {
+        Buttons {
+            config,
+            contacts: [Debounce::new(); 2],
+            state: State::Idle,
+            dropped: 0,
+        }
+    }
+
+    /// Raw levels of A and B after an edge, `true` while pressed. Queues the
+    /// events due before `now` first, one due at `now` waits for the edge.
+    pub fn edge<const N: usize>(
+        &mut self,
+        now: Instant,
+        a: bool,
+        b: bool,
+        queue: &mut Producer<'_, ButtonEvent, N>,
+    ) {
+        self.advance(|deadline| deadline < now, queue);
+        self.contacts[0].edge(now, a);
+        self.contacts[1].edge(now, b);
+    }
+
+    /// When [`Buttons::poll`] has to be called next.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let settle = self
+            .next_settle()
+            .map(|(_, since)| since + self.config.debounce);
+        settle.into_iter().chain(self.timeout()).min()
+    }
+
+    /// Queue the events due at `now`.
+    pub fn poll<const N: usize>(&mut self, now: Instant, queue: &mut Producer<'_, ButtonEvent, N>) {
+        self.advance(|deadline| deadline <= now, queue);
+    }
+
+    /// Events lost because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Handle the timeouts and settled levels for as long as they are `due`.
+    fn advance<const N: usize>(
+        &mut self,
+        due: impl Fn(Instant) -> bool,
+        queue: &mut Producer<'_, ButtonEvent, N>,
+    ) {
+        loop {
+            // A timeout is always before the pending level changes
+            match (self.timeout(), self.next_settle()) {
+                (Some(timeout), _) if due(timeout) => self.timed_out(timeout, queue),
+                (_, Some((i, since))) if due(since + self.config.debounce) => {
+                    let contact = &mut self.contacts[i];
+                    contact.stable = !contact.stable;
+                    contact.pending = None;
+                    self.level_changed(since, queue);
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// The contact settling first and when its level changed.
+    fn next_settle(&self) -> Option<(usize, Instant)> {
+        self.contacts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, contact)| contact.pending.map(|(_, since)| (i, since)))
+            .min_by_key(|&(_, since)| since)
+    }
+
+    /// When the current state times out. Held back while a level change from
+    /// before then is settling, the change may be what ends the state.
+    fn timeout(&self) -> Option<Instant> {
+        let timeout = match self.state {
+            State::Idle => None,
+            State::Down {
+                since, hold: None, ..
+            } => Some(since + self.config.long_press),
+            State::Down { hold, .. } => hold,
+            State::Released { until, .. } => Some(until),
+        }?;
+        match self.next_settle() {
+            Some((_, since)) if since <= timeout => None,
+            _ => Some(timeout),
+        }
+    }
+
+    /// Which button is held, both counting as [`Button::AB`].
+    fn held(&self) -> Option<Button> {
+        match (self.contacts[0].stable, self.contacts[1].stable) {
+            (false, false) => None,
+            (true, false) => Some(Button::A),
+            (false, true) => Some(Button::B),
+            (true, true) => Some(Button::AB),
+        }
+    }
+
+    /// A debounced level changed at `at`.
+    fn level_changed<const N: usize>(
+        &mut self,
+        at: Instant,
+        queue: &mut Producer<'_, ButtonEvent, N>,
+    ) {
+        let held = self.held();
+        self.state = match (self.state, held) {
+            (State::Idle, Some(button)) => State::Down {
+                button,
+                since: at,
+                clicked: None,
+                hold: None,
+            },
+            (State::Released { button, .. }, Some(held)) if held == button => State::Down {
+                button,
+                since: at,
+                clicked: Some(button),
+                hold: None,
+            },
+            // Another button can't make it a double click
+            (State::Released { button, .. }, Some(held)) => {
+                self.emit(button, Kind::Click, at, queue);
+                State::Down {
+                    button: held,
+                    since: at,
+                    clicked: None,
+                    hold: None,
+                }
+            }
+            // The second button joins a short press
+            (
+                State::Down {
+                    since,
+                    clicked,
+                    hold: None,
+                    ..
+                },
+                Some(Button::AB),
+            ) => State::Down {
+                button: Button::AB,
+                since,
+                clicked,
+                hold: None,
+            },
+            // Until both A and B are let go of
+            (state, Some(_)) => state,
+            (State::Down { hold: Some(_), .. }, None) => State::Idle,
+            (
+                State::Down {
+                    button,
+                    clicked: Some(first),
+                    ..
+                },
+                None,
+            ) if first == button => {
+                self.emit(button, Kind::DoubleClick, at, queue);
+                State::Idle
+            }
+            (
+                State::Down {
+                    button, clicked, ..
+                },
+                None,
+            ) => {
+                // Joined by the other button, the first press was a click
+                if let Some(first) = clicked {
+                    self.emit(first, Kind::Click, at, queue);
+                }
+                State::Released {
+                    button,
+                    until: at + self.config.double_click,
+                }
+            }
+            (state, None) => state,
+        };
+    }
+
+    /// The current state timed out at `at`.
+    fn timed_out<const N: usize>(&mut self, at: Instant, queue: &mut Producer<'_, ButtonEvent, N>) {
+        self.state = match self.state {
+            State::Released { button, .. } => {
+                self.emit(button, Kind::Click, at, queue);
+                State::Idle
+            }
+            State::Down {
+                button,
+                since,
+                clicked,
+                hold,
+            } => {
+                let kind = match hold {
+                    Some(_) => Kind::Hold,
+                    None => {
+                        // The click before won't be followed by a double click
+                        if let Some(first) = clicked {
+                            self.emit(first, Kind::Click, at, queue);
+                        }
+                        Kind::LongPress
+                    }
+                };
+                self.emit(button, kind, at, queue);
+                State::Down {
+                    button,
+                    since,
+                    clicked: None,
+                    hold: Some(at + self.config.hold_period),
+                }
+            }
+            State::Idle => State::Idle,
+        };
+    }
+
+    fn emit<const N: usize>(
+        &mut self,
+        button: Button,
+        kind: Kind,
+        timestamp: Instant,
+        queue: &mut Producer<'_, ButtonEvent, N>,
+    ) {
+        let event = ButtonEvent {
+            button,
+            kind,
+            timestamp,
+        };
+        if queue.enqueue(event).is_err() {
+            self.dropped += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use heapless::spsc::Queue;
+
+    use super::*;
+
+    fn at(ms: u64) -> Instant {
+        Instant::from_ticks(0) + ms.millis()
+    }
+
+    /// Feed `edges` of (ms, A, B) levels, polling at every deadline as the
+    /// firmware does, until `until`. An edge at a deadline is seen first, as
+    /// GPIOTE runs before the dispatcher of the poll. Without `polls` the
+    /// deadlines between edges are only met by the edges. Returns the buttons
+    /// and the events queued in a queue of `N - 1`.
+    fn run<const N: usize>(
+        edges: &[(u64, bool, bool)],
+        until: u64,
+        polls: bool,
+    ) -> (Buttons, Vec<(Button, Kind, u64)>) {
+        let mut buttons = Buttons::new(ButtonConfig::default());
+        let mut queue = Queue::<ButtonEvent, N>::new();
+        let (mut producer, mut consumer) = queue.split();
+        let poll_before = |buttons: &mut Buttons, producer: &mut Producer<_, N>, end| {
+            while let Some(deadline) = buttons.next_deadline().filter(|&d| d < end) {
+                buttons.poll(deadline, producer);
+            }
+        };
+        for &(ms, a, b) in edges {
+            if polls {
+                poll_before(&mut buttons, &mut producer, at(ms));
+            }
+            buttons.edge(at(ms), a, b, &mut producer);
+        }
+        poll_before(&mut buttons, &mut producer, at(until));
+        let events = core::iter::from_fn(|| consumer.dequeue())
+            .map(|event| {
+                let ms = (event.timestamp - at(0)).to_millis();
+                (event.button, event.kind, ms)
+            })
+            .collect();
+        (buttons, events)
+    }
+
+    fn events(edges: &[(u64, bool, bool)]) -> Vec<(Button, Kind, u64)> {
+        let events = run::<16>(edges, 5000, true).1;
+        assert_eq!(run::<16>(edges, 5000, false).1, events, "late polls");
+        events
+    }
+
+    #[test]
+    fn bounce_is_one_press() {
+        let bouncing = [
+            (0, true, false),
+            (3, false, false),
+            (5, true, false),
+            (8, false, false),
+            (10, true, false),
+            (100, false, false),
+            (102, true, false),
+            (104, false, false),
+        ];
+        // Released at 104, the click is sure once no second press followed
+        assert_eq!(events(&bouncing), [(Button::A, Kind::Click, 404)]);
+        // Shorter than the debounce time
+        assert_eq!(events(&[(0, true, false), (15, false, false)]), []);
+    }
+
+    #[test]
+    fn double_click_boundary() {
+        let clicks = |second| {
+            events(&[
+                (0, true, false),
+                (100, false, false),
+                (second, true, false),
+                (second + 100, false, false),
+            ])
+        };
+        assert_eq!(clicks(400), [(Button::A, Kind::DoubleClick, 500)]);
+        assert_eq!(
+            clicks(401),
+            [(Button::A, Kind::Click, 400), (Button::A, Kind::Click, 801)]
+        );
+    }
+
+    #[test]
+    fn long_press_and_hold() {
+        assert_eq!(
+            events(&[(0, false, true), (1100, false, false)]),
+            [
+                (Button::B, Kind::LongPress, 600),
+                (Button::B, Kind::Hold, 800),
+                (Button::B, Kind::Hold, 1000),
+            ]
+        );
+        // Held from a click, the click is reported first
+        assert_eq!(
+            events(&[
+                (0, true, false),
+                (100, false, false),
+                (200, true, false),
+                (900, false, false)
+            ]),
+            [
+                (Button::A, Kind::Click, 800),
+                (Button::A, Kind::LongPress, 800),
+            ]
+        );
+    }
+
+    #[test]
+    fn both_buttons() {
+        assert_eq!(
+            events(&[(0, true, false), (50, true, true), (200, false, false)]),
+            [(Button::AB, Kind::Click, 500)]
+        );
+        // Let go of one after the other
+        assert_eq!(
+            events(&[
+                (0, true, false),
+                (50, true, true),
+                (200, false, true),
+                (250, false, false)
+            ]),
+            [(Button::AB, Kind::Click, 550)]
+        );
+        assert_eq!(
+            events(&[(0, true, true), (700, false, false)]),
+            [(Button::AB, Kind::LongPress, 600)]
+        );
+    }
+
+    #[test]
+    fn other_button_ends_click() {
+        assert_eq!(
+            events(&[
+                (0, true, false),
+                (100, false, false),
+                (200, false, true),
+                (300, false, false)
+            ]),
+            [(Button::A, Kind::Click, 200), (Button::B, Kind::Click, 600)]
+        );
+    }
+
+    #[test]
+    fn full_queue() {
+        let (buttons, events) = run::<2>(&[(0, true, false), (1100, false, false)], 5000, true);
+        assert_eq!(events, [(Button::A, Kind::LongPress, 600)]);
+        assert_eq!(buttons.dropped(), 2);
+    }
+
+    #[test]
+    fn deadlines() {
+        let mut buttons = Buttons::new(ButtonConfig::default());
+        assert_eq!(buttons.next_deadline(), None);
+        let mut queue = Queue::<ButtonEvent, 4>::new();
+        let (mut producer, _) = queue.split();
+        buttons.edge(at(0), true, false, &mut producer);
+        assert_eq!(buttons.next_deadline(), Some(at(20)));
+        buttons.poll(at(20), &mut producer);
+        assert_eq!(buttons.next_deadline(), Some(at(600)));
+    }
+
+    #[test]
+    fn edge_after_missed_deadline() {
+        // The poll due at 20 never ran before the release
+        let mut buttons = Buttons::new(ButtonConfig::default());
+        let mut queue = Queue::<ButtonEvent, 4>::new();
+        let (mut producer, mut consumer) = queue.split();
+        buttons.edge(at(0), true, false, &mut producer);
+        buttons.edge(at(100), false, false, &mut producer);
+        assert_eq!(buttons.next_deadline(), Some(at(120)));
+        // Nor did the one at 120 that makes it a click, before a new press
+        buttons.edge(at(500), true, false, &mut producer);
+        let event = consumer.dequeue().unwrap();
+        assert_eq!((event.button, event.kind), (Button::A, Kind::Click));
+        assert_eq!(event.timestamp, at(400));
+        assert_eq!(consumer.dequeue(), None);
+    }
+}
@@ -0,0 +1,545 @@
+//! Line editor for serial consoles.
+//!
+//! Bytes typed in a terminal are fed one at a time to [`LineEditor::feed`], which
+//! echoes them back with the ANSI sequences needed to keep the terminal in sync.
+//!
+//! Supported keys: backspace, delete, left/right, home/end (also Ctrl-A/Ctrl-E),
+//! up/down through the history, Ctrl-C to discard the line and tab, which is handed
+//! back to the caller as [`Event::Tab`] to implement completion.
+//!
+//! ```ignore
+//! let mut editor = LineEditor::<64, 8>::new();
+//! editor.prompt(&mut serial).unwrap();
+//! loop {
+//!     let byte = nb::block!(serial.read()).unwrap();
+//!     match editor.feed(byte, &mut serial).unwrap() {
+//!         Some(Event::Line) => {
+//!             run(editor.line());
+//!             editor.prompt(&mut serial).unwrap();
+//!         }
+//!         Some(Event::Cancel) => editor.prompt(&mut serial).unwrap(),
+//!         Some(Event::Tab) => editor.complete_from(&["help", "status"], &mut serial).unwrap(),
+//!         None => {}
+//!     }
+//! }
+//! ```
+
+use core::fmt::{self, Write};
+
+use heapless::{Deque, String};
+
+const BELL: char = '\x07';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Event {
+    /// Enter was pressed, the line is available from [`LineEditor::line`].
+    Line,
+    /// Tab was pressed, see [`LineEditor::complete_from`].
+    Tab,
+    /// Ctrl-C discarded the line.
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    Esc,
+    Csi {
+        /// The first numeric parameter, e.g. 3 of `ESC [ 3 ~`.
+        param: u8,
+        /// Past the first parameter, e.g. the modifiers of `ESC [ 1 ; 5 C`.
+        more: bool,
+        /// An intermediate byte was seen, the sequence is none of ours.
+        foreign: bool,
+    },
+}
+
+/// Edits lines of up to `N` bytes and remembers the last `H` of them.
+pub struct LineEditor<const N: usize, const H: usize> {
+    prompt: &'static str,
+    line: String<N>,
+    cursor: usize,
+    history: Deque<String<N>, H>,
+    /// Position while browsing the history, 0 is the line being typed.
+    history_pos: usize,
+    /// The line being typed, kept while browsing the history.
+    draft: String<N>,
+    escape: Escape,
+    submitted: bool,
+    last_cr: bool,
+}
+
+impl<const N: usize, const H: usize> Default for LineEditor<N, H> {
+    fn default() -> Self {
+        LineEditor::new()
+    }
+}
+
+impl<const N: usize, const H: usize> LineEditor<N, H> {
+    pub fn new() -> Self {
+        LineEditor {
+            prompt: "> ",
+            line: String::new(),
+            cursor: 0,
+            history: Deque::new(),
+            history_pos: 0,
+            draft: String::new(),
+            escape: Escape::None,
+            submitted: false,
+            last_cr: false,
+        }
+    }
+
+    pub fn set_prompt(&mut self, prompt: &'static str) -> &mut Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// Print the prompt, at start up and after each [`Event`].
+    pub fn prompt<W: Write>(&self, out: &mut W) -> fmt::Result {
+        out.write_str(self.prompt)
+    }
+
+    /// The current line, complete after [`Event::Line`].
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Previous lines, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &str> {
+        self.history.iter().map(|line| line.as_str())
+    }
+
+    /// Handle one received byte, writing the echo to `out`.
+    pub fn feed<W: Write>(&mut self, byte: u8, out: &mut W) -> Result<Option<Event>, fmt::Error> {
+        // LF after CR is the rest of a CRLF, the submitted line stays readable
+        if core::mem::replace(&mut self.last_cr, false) && byte == b'\n' {
+            return Ok(None);
+        }
+        if self.submitted {
+            self.submitted = false;
+            self.line.clear();
+            self.cursor = 0;
+        }
+
+        match self.escape {
+            Escape::Esc => {
+                self.escape = if byte == b'[' || byte == b'O' {
+                    Escape::Csi {
+                        param: 0,
+                        more: false,
+                        foreign: false,
+                    }
+                } else {
+                    Escape::None
+                };
+                return Ok(None);
+            }
+            Escape::Csi {
+                param,
+                more,
+                foreign,
+            } => {
+                match byte {
+                    b'0'..=b'9' if !more => {
+                        let param = param.saturating_mul(10).saturating_add(byte - b'0');
+                        self.escape = Escape::Csi {
+                            param,
+                            more,
+                            foreign,
+                        };
+                    }
+                    // Parameter bytes
+                    0x30..=0x3F => {
+                        self.escape = Escape::Csi {
+                            param,
+                            more: true,
+                            foreign,
+                        }
+                    }
+                    // Intermediate bytes
+                    0x20..=0x2F => {
+                        self.escape = Escape::Csi {
+                            param,
+                            more: true,
+                            foreign: true,
+                        }
+                    }
+                    // The final byte
+                    0x40..=0x7E => {
+                        self.escape = Escape::None;
+                        if !foreign {
+                            self.control_sequence(byte, param, out)?;
+                        }
+                    }
+                    // Not part of a sequence, give up on it and take the byte as typed
+                    _ => {
+                        self.escape = Escape::None;
+                        return self.feed(byte, out);
+                    }
+                }
+                return Ok(None);
+            }
+            Escape::None => {}
+        }
+
+        match byte {
+            0x1B => self.escape = Escape::Esc,
+            b'\r' => {
+                self.last_cr = true;
+                return self.submit(out).map(Some);
+            }
+            // LF on its own also ends a line
+            b'\n' => return self.submit(out).map(Some),
+            b'\t' => return Ok(Some(Event::Tab)),
+            0x03 => {
+                out.write_str("^C\r\n")?;
+                self.line.clear();
+                self.cursor = 0;
+                self.history_pos = 0;
+                return Ok(Some(Event::Cancel));
+            }
+            0x01 => self.home(out)?,
+            0x05 => self.end(out)?,
+            0x08 | 0x7F if self.cursor > 0 => {
+                self.cursor -= 1;
+                out.write_char('\x08')?;
+                self.delete(out)?;
+            }
+            0x20..=0x7E => self.insert(byte as char, out)?,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Insert `s` at the cursor, as if it was typed.
+    pub fn insert_str<W: Write>(&mut self, s: &str, out: &mut W) -> fmt::Result {
+        for c in s.chars() {
+            self.insert(c, out)?;
+        }
+        Ok(())
+    }
+
+    /// Complete the word before the cursor with the longest prefix shared by the
+    /// matching `candidates`, listing them when that doesn't add anything.
+    pub fn complete_from<W: Write>(&mut self, candidates: &[&str], out: &mut W) -> fmt::Result {
+        let word_start = self.line[..self.cursor]
+            .rfind(' ')
+            .map_or(0, |space| space + 1);
+        let word = &self.line[word_start..self.cursor];
+
+        let mut matches = candidates.iter().filter(|c| c.starts_with(word));
+        let first = match matches.next() {
+            Some(first) => *first,
+            None => return out.write_char(BELL),
+        };
+        let common = matches.clone().fold(first.len(), |len, candidate| {
+            first
+                .bytes()
+                .zip(candidate.bytes())
+                .take(len)
+                .take_while(|(a, b)| a == b)
+                .count()
+        });
+
+        // A single match is finished off with a space
+        if matches.next().is_none() {
+            self.insert_str(&first[word.len()..], out)?;
+            return self.insert(' ', out);
+        }
+        if common > word.len() {
+            return self.insert_str(&first[word.len()..common], out);
+        }
+
+        // Nothing to add, show the options and redraw the line below them
+        out.write_str("\r\n")?;
+        for candidate in candidates.iter().filter(|c| c.starts_with(word)) {
+            out.write_str(candidate)?;
+            out.write_str("  ")?;
+        }
+        out.write_str("\r\n")?;
+        out.write_str(self.prompt)?;
+        out.write_str(&self.line)?;
+        move_left(out, self.line.len() - self.cursor)
+    }
+
+    fn control_sequence<W: Write>(&mut self, byte: u8, param: u8, out: &mut W) -> fmt::Result {
+        match (byte, param) {
+            (b'A', _) => self.history_up(out),
+            (b'B', _) => self.history_down(out),
+            (b'C', _) => {
+                if self.cursor < self.line.len() {
+                    out.write_str(&self.line[self.cursor..self.cursor + 1])?;
+                    self.cursor += 1;
+                }
+                Ok(())
+            }
+            (b'D', _) => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    out.write_char('\x08')?;
+                }
+                Ok(())
+            }
+            (b'H', _) | (b'~', 1) | (b'~', 7) => self.home(out),
+            (b'F', _) | (b'~', 4) | (b'~', 8) => self.end(out),
+            (b'~', 3) => self.delete(out),
+            _ => Ok(()),
+        }
+    }
+
+    fn submit<W: Write>(&mut self, out: &mut W) -> Result<Event, fmt::Error> {
+        out.write_str("\r\n")?;
+        let repeated = self.history.back().is_some_and(|last| *last == self.line);
+        if H > 0 && !self.line.is_empty() && !repeated {
+            if self.history.is_full() {
+                self.history.pop_front();
+            }
+            self.history.push_back(self.line.clone()).ok();
+        }
+        self.history_pos = 0;
+        self.submitted = true;
+        Ok(Event::Line)
+    }
+
+    fn insert<W: Write>(&mut self, c: char, out: &mut W) -> fmt::Result {
+        // Only ASCII is accepted so byte offsets are character offsets
+        if self.line.len() == N || !c.is_ascii() {
+            return out.write_char(BELL);
+        }
+        let mut rest = String::<N>::new();
+        rest.push_str(&self.line[self.cursor..]).ok();
+        self.line.truncate(self.cursor);
+        self.line.push(c).ok();
+        self.line.push_str(&rest).ok();
+        self.cursor += 1;
+
+        out.write_char(c)?;
+        out.write_str(&rest)?;
+        move_left(out, rest.len())
+    }
+
+    /// Remove the character under the cursor.
+    fn delete<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        if self.cursor == self.line.len() {
+            return Ok(());
+        }
+        let mut rest = String::<N>::new();
+        rest.push_str(&self.line[self.cursor + 1..]).ok();
+        self.line.truncate(self.cursor);
+        self.line.push_str(&rest).ok();
+
+        out.write_str(&rest)?;
+        out.write_char(' ')?;
+        move_left(out, rest.len() + 1)
+    }
+
+    fn home<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        move_left(out, self.cursor)?;
+        self.cursor = 0;
+        Ok(())
+    }
+
+    fn end<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_str(&self.line[self.cursor..])?;
+        self.cursor = self.line.len();
+        Ok(())
+    }
+
+    fn history_up<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        if self.history_pos == self.history.len() {
+            return out.write_char(BELL);
+        }
+        if self.history_pos == 0 {
+            self.draft = self.line.clone();
+        }
+        self.history_pos += 1;
+        let entry = self.history.iter().rev().nth(self.history_pos - 1).cloned();
+        self.replace_line(entry.unwrap_or_default(), out)
+    }
+
+    fn history_down<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        if self.history_pos == 0 {
+            return out.write_char(BELL);
+        }
+        self.history_pos -= 1;
+        let entry = if self.history_pos == 0 {
+            self.draft.clone()
+        } else {
+            self.history
+                .iter()
+                .rev()
+                .nth(self.history_pos - 1)
+                .cloned()
+                .unwrap_or_default()
+        };
+        self.replace_line(entry, out)
+    }
+
+    fn replace_line<W: Write>(&mut self, line: String<N>, out: &mut W) -> fmt::Result {
+        move_left(out, self.cursor)?;
+        out.write_str(&line)?;
+        // Erase whatever is left of a longer previous line
+        out.write_str("\x1B[K")?;
+        self.line = line;
+        self.cursor = self.line.len();
+        Ok(())
+    }
+}
+
+fn move_left<W: Write>(out: &mut W, n: usize) -> fmt::Result {
+    if n > 0 {
+        write!(out, "\x1B[{}D", n)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Editor = LineEditor<16, 2>;
+
+    /// Feed `bytes` as if typed, returns the events and the echo.
+    fn type_bytes(editor: &mut Editor, bytes: &[u8]) -> (Vec<Event>, std::string::String) {
+        let mut echo = std::string::String::new();
+        let events = bytes
+            .iter()
+            .filter_map(|&byte| editor.feed(byte, &mut echo).unwrap())
+            .collect();
+        (events, echo)
+    }
+
+    #[test]
+    fn typed_line() {
+        let mut editor = Editor::new();
+        let (events, echo) = type_bytes(&mut editor, b"help\r\n");
+        assert_eq!(events, [Event::Line]);
+        assert_eq!(echo, "help\r\n");
+        assert_eq!(editor.line(), "help");
+        // The next byte starts a new line
+        type_bytes(&mut editor, b"x");
+        assert_eq!(editor.line(), "x");
+    }
+
+    #[test]
+    fn lone_line_feed_ends_a_line() {
+        let mut editor = Editor::new();
+        let (events, _) = type_bytes(&mut editor, b"a\nb\n");
+        assert_eq!(events, [Event::Line, Event::Line]);
+        assert_eq!(editor.line(), "b");
+    }
+
+    #[test]
+    fn editing_in_the_middle() {
+        let mut editor = Editor::new();
+        // Left twice, backspace, insert
+        type_bytes(&mut editor, b"abcd\x1B[D\x1B[D\x7FX");
+        assert_eq!(editor.line(), "aXcd");
+        assert_eq!(editor.cursor(), 2);
+        // Delete under the cursor
+        type_bytes(&mut editor, b"\x1B[3~");
+        assert_eq!(editor.line(), "aXd");
+        // Home and end, in all their spellings
+        for (keys, cursor) in [
+            (&b"\x1B[H"[..], 0),
+            (b"\x1B[F", 3),
+            (b"\x1B[1~", 0),
+            (b"\x1B[4~", 3),
+            (b"\x01", 0),
+            (b"\x05", 3),
+            (b"\x1BOH", 0),
+        ] {
+            type_bytes(&mut editor, keys);
+            assert_eq!(editor.cursor(), cursor, "{:?}", keys);
+        }
+    }
+
+    #[test]
+    fn sequence_with_parameters_is_consumed() {
+        let mut editor = Editor::new();
+        // Ctrl+Left and Ctrl+Right move like the arrows
+        let (_, echo) = type_bytes(&mut editor, b"ab\x1B[1;5D\x1B[1;5C");
+        assert_eq!(editor.line(), "ab");
+        assert_eq!(editor.cursor(), 2);
+        assert_eq!(echo, "ab\x08b");
+
+        // Private and intermediate bytes are skipped whole
+        type_bytes(&mut editor, b"\x1B[?25h\x1B[2 q");
+        assert_eq!(editor.line(), "ab");
+    }
+
+    #[test]
+    fn broken_sequence_keeps_the_byte() {
+        let mut editor = Editor::new();
+        let (events, _) = type_bytes(&mut editor, b"ab\x1B[\r");
+        assert_eq!(events, [Event::Line]);
+        assert_eq!(editor.line(), "ab");
+    }
+
+    #[test]
+    fn history() {
+        let mut editor = Editor::new();
+        type_bytes(&mut editor, b"one\rtwo\rtwo\rthree\r");
+        // Repeats are kept once, only the last two lines
+        assert_eq!(editor.history().collect::<Vec<_>>(), ["two", "three"]);
+
+        type_bytes(&mut editor, b"dr\x1B[A");
+        assert_eq!(editor.line(), "three");
+        type_bytes(&mut editor, b"\x1B[A");
+        assert_eq!(editor.line(), "two");
+        let (_, echo) = type_bytes(&mut editor, b"\x1B[A");
+        assert_eq!(echo, "\x07");
+        // Back down to the draft
+        type_bytes(&mut editor, b"\x1B[B\x1B[B");
+        assert_eq!(editor.line(), "dr");
+        assert_eq!(editor.cursor(), 2);
+    }
+
+    #[test]
+    fn cancel() {
+        let mut editor = Editor::new();
+        let (events, echo) = type_bytes(&mut editor, b"oops\x03");
+        assert_eq!(events, [Event::Cancel]);
+        assert!(echo.ends_with("^C\r\n"));
+        assert_eq!(editor.line(), "");
+    }
+
+    #[test]
+    fn full_line_rings() {
+        let mut editor = Editor::new();
+        let (_, echo) = type_bytes(&mut editor, &[b'x'; 17]);
+        assert_eq!(editor.line().len(), 16);
+        assert!(echo.ends_with('\x07'));
+    }
+
+    #[test]
+    fn completion() {
+        let commands = ["help", "heap", "status"];
+        let mut editor = Editor::new();
+        let (events, _) = type_bytes(&mut editor, b"s\t");
+        assert_eq!(events, [Event::Tab]);
+        let mut echo = std::string::String::new();
+        editor.complete_from(&commands, &mut echo).unwrap();
+        assert_eq!(editor.line(), "status ");
+
+        // The common prefix, then the list
+        let mut editor = Editor::new();
+        type_bytes(&mut editor, b"h");
+        editor.complete_from(&commands, &mut echo).unwrap();
+        assert_eq!(editor.line(), "he");
+        let mut echo = std::string::String::new();
+        editor.complete_from(&commands, &mut echo).unwrap();
+        assert_eq!(echo, "\r\nhelp  heap  \r\n> he");
+
+        let mut echo = std::string::String::new();
+        let mut editor = Editor::new();
+        type_bytes(&mut editor, b"x");
+        editor.complete_from(&commands, &mut echo).unwrap();
+        assert_eq!(echo, "\x07");
+    }
+}
@@ -0,0 +1,131 @@
+//! Magnetic declination lookup for converting magnetic headings to true headings.
+//!
+//! The table is sampled from the World Magnetic Model (WMM2025) at 2026.0 on a
+//! 10° grid, rounded to whole degrees and bilinearly interpolated. It is accurate
+//! to roughly a degree away from the magnetic poles, which is well below what the
+//! compass itself can resolve.
+
+use libm::floorf;
+
+const GRID_STEP: f32 = 10.0;
+const MIN_LAT: f32 = -80.0;
+const MAX_LAT: f32 = 80.0;
+const MIN_LON: f32 = -180.0;
+const ROWS: usize = 17;
+const COLS: usize = 36;
+
+/// Declination in degrees (east positive), rows from 80°S to 80°N, columns from 180°W eastwards.
+#[rustfmt::skip]
+const DECLINATION: [[i16; COLS]; ROWS] = [
+    [129, 116, 105, 95, 86, 77, 69, 61, 53, 45, 37, 30, 22, 15, 7, 0, -8, -16, -24, -32, -41, -50, -59, -68, -78, -87, -98, -108, -120, -132, -145, -159, -174, 171, 156, 142], // -80
+    [86, 78, 71, 66, 61, 56, 51, 46, 40, 35, 28, 22, 16, 10, 4, -2, -7, -14, -21, -28, -36, -45, -53, -61, -69, -78, -86, -94, -104, -115, -130, -152, 174, 137, 112, 97], // -70
+    [49, 47, 46, 44, 42, 41, 39, 36, 33, 28, 22, 16, 10, 4, -1, -5, -10, -14, -20, -27, -35, -43, -51, -58, -64, -69, -73, -76, -77, -74, -62, -18, 30, 45, 49, 50], // -60
+    [32, 32, 32, 31, 31, 30, 30, 29, 27, 23, 17, 10, 2, -4, -9, -12, -14, -17, -21, -27, -35, -42, -49, -54, -57, -58, -57, -52, -43, -30, -13, 3, 15, 23, 28, 31], // -50
+    [23, 23, 23, 23, 23, 23, 23, 22, 21, 18, 12, 4, -5, -12, -17, -19, -20, -20, -22, -26, -32, -38, -43, -46, -46, -44, -38, -30, -20, -11, -3, 4, 10, 15, 19, 22], // -40
+    [17, 18, 18, 18, 17, 17, 17, 16, 16, 13, 6, -2, -11, -18, -22, -24, -24, -23, -21, -20, -23, -27, -31, -33, -32, -28, -22, -15, -8, -3, 1, 4, 7, 11, 14, 16], // -30
+    [14, 14, 14, 14, 14, 13, 12, 12, 11, 8, 2, -7, -15, -21, -24, -24, -23, -19, -15, -11, -10, -12, -16, -18, -18, -15, -12, -7, -3, 0, 1, 3, 5, 8, 11, 13], // -20
+    [11, 11, 11, 11, 11, 10, 10, 9, 8, 5, -2, -10, -17, -21, -22, -21, -17, -13, -8, -4, -2, -2, -5, -8, -9, -8, -6, -3, 0, 1, 1, 2, 4, 6, 9, 11], // -10
+    [10, 10, 9, 9, 9, 9, 8, 8, 6, 2, -4, -11, -17, -20, -19, -16, -12, -7, -4, -1, 1, 2, 0, -2, -4, -4, -3, -2, 0, 0, 0, 0, 2, 5, 7, 9], // 0
+    [9, 9, 9, 9, 9, 9, 8, 7, 5, 1, -5, -12, -16, -18, -16, -13, -8, -4, -1, 1, 2, 3, 2, 0, -1, -1, -1, -1, 0, -1, -2, -2, 0, 3, 6, 8], // 10
+    [8, 9, 9, 10, 10, 10, 9, 7, 4, -1, -7, -12, -15, -16, -14, -10, -6, -2, 0, 2, 3, 4, 3, 2, 1, 0, 0, -1, -1, -2, -4, -4, -3, 0, 3, 6], // 20
+    [6, 8, 10, 11, 12, 12, 11, 9, 4, -2, -8, -13, -15, -14, -12, -9, -5, -1, 1, 3, 4, 5, 5, 4, 3, 2, 1, 0, -2, -4, -6, -7, -6, -4, 0, 3], // 30
+    [4, 7, 10, 12, 14, 14, 13, 10, 5, -2, -9, -14, -16, -15, -12, -9, -5, -1, 1, 3, 5, 6, 6, 6, 6, 5, 4, 1, -2, -5, -9, -10, -9, -7, -3, 0], // 40
+    [2, 6, 10, 13, 16, 16, 15, 11, 5, -3, -11, -16, -18, -17, -14, -10, -6, -2, 1, 4, 6, 8, 10, 11, 11, 10, 7, 3, -2, -7, -11, -13, -12, -10, -6, -2], // 50
+    [1, 6, 10, 14, 17, 18, 17, 13, 5, -6, -16, -21, -22, -21, -18, -14, -9, -4, 0, 5, 9, 12, 15, 17, 18, 17, 13, 6, -2, -9, -14, -16, -15, -12, -8, -4], // 60
+    [-1, 4, 9, 14, 17, 19, 18, 12, 0, -14, -25, -30, -30, -27, -23, -18, -12, -6, 0, 6, 12, 17, 22, 26, 29, 29, 25, 16, 3, -9, -16, -19, -18, -15, -11, -6], // 70
+    [-7, -2, 3, 7, 9, 8, 2, -10, -25, -36, -41, -41, -38, -33, -27, -20, -13, -6, 2, 9, 17, 24, 31, 38, 44, 48, 50, 48, 36, 11, -11, -21, -23, -21, -17, -12], // 80
+];
+
+/// Magnetic declination in degrees (east positive) at the given latitude and longitude.
+///
+/// Latitudes beyond ±80° are clamped, longitudes wrap around.
+pub fn declination(lat: f32, lon: f32) -> f32 {
+    let lat = lat.clamp(MIN_LAT, MAX_LAT);
+    let lon = wrap_degrees(lon, MIN_LON);
+
+    let y = (lat - MIN_LAT) / GRID_STEP;
+    let x = (lon - MIN_LON) / GRID_STEP;
+    let row = (floorf(y) as usize).min(ROWS - 2);
+    let col = (floorf(x) as usize).min(COLS - 1);
+    let next_col = (col + 1) % COLS;
+    let fy = y - row as f32;
+    let fx = x - col as f32;
+
+    // Interpolate relative to one corner so cells crossing ±180° near the poles stay continuous.
+    let base = DECLINATION[row][col] as f32;
+    let corner = |r: usize, c: usize| base + wrap_degrees(DECLINATION[r][c] as f32 - base, -180.0);
+    let south = corner(row, col) * (1.0 - fx) + corner(row, next_col) * fx;
+    let north = corner(row + 1, col) * (1.0 - fx) + corner(row + 1, next_col) * fx;
+
+    wrap_degrees(south * (1.0 - fy) + north * fy, -180.0)
+}
+
+/// Convert a magnetic heading in degrees to a true heading in `[0, 360)` degrees.
+pub fn true_heading(magnetic: f32, lat: f32, lon: f32) -> f32 {
+    wrap_degrees(magnetic + declination(lat, lon), 0.0)
+}
+
+/// Wrap an angle in degrees into `[start, start + 360)`.
+fn wrap_degrees(angle: f32, start: f32) -> f32 {
+    angle - 360.0 * floorf((angle - start) / 360.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn city_declinations() {
+        // WMM2025 at 2026.0
+        let cities = [
+            ("London", 51.51, -0.13, 1.1),
+            ("New York", 40.71, -74.01, -12.7),
+            ("Sydney", -33.87, 151.21, 12.9),
+            ("Tokyo", 35.68, 139.69, -7.9),
+            ("Cape Town", -33.92, 18.42, -26.2),
+        ];
+        for (city, lat, lon, expected) in cities {
+            let actual = declination(lat, lon);
+            assert!(
+                (actual - expected).abs() <= 1.0,
+                "{}: {} instead of {}",
+                city,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn longitude_wraps() {
+        assert_close(declination(51.51, 359.87), declination(51.51, -0.13), 1e-3);
+        assert_close(declination(0.0, 180.0), declination(0.0, -180.0), 1e-3);
+    }
+
+    #[test]
+    fn heading_wraps_at_north() {
+        // East declination past 360°
+        let heading = true_heading(359.5, 51.51, -0.13);
+        assert!((0.0..360.0).contains(&heading));
+        assert_close(heading, 359.5 + declination(51.51, -0.13) - 360.0, 1e-3);
+
+        // West declination below 0°
+        let heading = true_heading(0.5, 40.71, -74.01);
+        assert_close(heading, 360.5 + declination(40.71, -74.01), 1e-3);
+
+        assert_eq!(wrap_degrees(360.0, 0.0), 0.0);
+        assert_eq!(wrap_degrees(0.0, 0.0), 0.0);
+        assert_eq!(wrap_degrees(-360.0, 0.0), 0.0);
+        assert_eq!(wrap_degrees(180.0, -180.0), -180.0);
+    }
+}
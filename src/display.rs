@@ -0,0 +1,101 @@
+//! LED matrix refreshed by an async task, the executor counterpart of
+//! `microbit::display::nonblocking::Display`.
+//!
+//! The image lives in an [`AsyncDisplay`] shared by the refresh task and the
+//! tasks drawing on it. Rows are lit one after the other, each LED for a share
+//! of the row time given by its brightness.
+//!
+//! ```ignore
+//! static DISPLAY: AsyncDisplay = AsyncDisplay::new();
+//!
+//! #[embassy_executor::task]
+//! async fn refresh(pins: DisplayPins, mut delay: Delay<pac::TIMER2>) {
+//!     DISPLAY.refresh(pins, &mut delay).await
+//! }
+//!
+//! DISPLAY.show(HEART);
+//! ```
+
+use core::cell::Cell;
+
+use cortex_m::interrupt::{self, Mutex};
+use embedded_hal::digital::v2::OutputPin;
+use microbit::gpio::DisplayPins;
+
+pub use crate::frame::MAX_BRIGHTNESS;
+use crate::monotonic::{Delay, Duration, Instance32};
+
+/// Time an LED of brightness 1 is lit in each row scan.
+const SLOT: Duration = Duration::micros(200);
+/// Time each row is scanned, about 110 frames a second.
+const ROW_TIME: Duration = Duration::micros(200 * MAX_BRIGHTNESS as u64);
+
+/// Brightness from 0 to 9 of each LED, row by row, e.g. from a
+/// [`Frame`](crate::frame::Frame).
+pub type Image = [[u8; 5]; 5];
+
+pub struct AsyncDisplay {
+    image: Mutex<Cell<Image>>,
+}
+
+impl AsyncDisplay {
+    pub const fn new() -> AsyncDisplay {
+        AsyncDisplay {
+            image: Mutex::new(Cell::new([[0; 5]; 5])),
+        }
+    }
+
+    /// Show `image` from the next frame on, brightness above 9 is shown as 9.
+    pub fn show(&self, image: Image) {
+        interrupt::free(|cs| self.image.borrow(cs).set(image));
+    }
+
+    pub fn clear(&self) {
+        self.show([[0; 5]; 5]);
+    }
+
+    /// The image being shown.
+    pub fn image(&self) -> Image {
+        interrupt::free(|cs| self.image.borrow(cs).get())
+    }
+
+    /// Scan the LED matrix forever, run this as its own task.
+    pub async fn refresh<T: Instance32>(&self, pins: DisplayPins, delay: &mut Delay<T>) -> ! {
+        let (mut cols, mut rows) = pins.degrade();
+        let mut row_start = delay.now();
+        loop {
+            // Don't rush to catch up after the task was held up
+            row_start = row_start.max(delay.now());
+            let image = self.image();
+            for (row_pin, row) in rows.iter_mut().zip(&image) {
+                for (col, &brightness) in cols.iter_mut().zip(row) {
+                    if brightness > 0 {
+                        col.set_low().ok();
+                    }
+                }
+                row_pin.set_high().ok();
+                // Turn off the dimmer LEDs as their share of the row ends
+                for level in 1..MAX_BRIGHTNESS {
+                    if row.contains(&level) {
+                        delay.until(row_start + SLOT * level as u32).await;
+                        for (col, _) in cols.iter_mut().zip(row).filter(|(_, &b)| b == level) {
+                            col.set_high().ok();
+                        }
+                    }
+                }
+                row_start += ROW_TIME;
+                delay.until(row_start).await;
+                row_pin.set_low().ok();
+                for col in cols.iter_mut() {
+                    col.set_high().ok();
+                }
+            }
+        }
+    }
+}
+
+impl Default for AsyncDisplay {
+    fn default() -> AsyncDisplay {
+        AsyncDisplay::new()
+    }
+}
@@ -0,0 +1,278 @@
+//! Dodge the rocks falling down the display, the sample [`Game`].
+//!
+//! The player on the bottom row moves with A and B or by tilting the board. A
+//! point for every rock that reaches the ground, and the rocks fall faster as
+//! the score goes up.
+
+use crate::frame::{Frame, HEIGHT, MAX_BRIGHTNESS, WIDTH};
+use crate::game::{Game, Input, Rect, Rng, Status};
+
+/// Most rocks falling at once.
+const ROCKS: usize = 4;
+/// Steps between two moves of the rocks at the start, and at top speed.
+const SLOWEST: u32 = 8;
+const FASTEST: u32 = 2;
+/// Tilt in milli-g that moves the player.
+const TILT_THRESHOLD: i32 = 300;
+/// Steps between two moves of the player when tilting.
+const TILT_REPEAT: u32 = 3;
+const ROCK_BRIGHTNESS: u8 = 4;
+
+pub struct Dodge {
+    rng: Rng,
+    player: Rect,
+    rocks: [Option<Rect>; ROCKS],
+    score: u32,
+    steps: u32,
+}
+
+impl Dodge {
+    pub fn new(seed: u32) -> Dodge {
+        let mut dodge = Dodge {
+            rng: Rng::new(seed),
+            player: Rect::point(0, 0),
+            rocks: [None; ROCKS],
+            score: 0,
+            steps: 0,
+        };
+        dodge.reset();
+        dodge
+    }
+
+    /// Steps between two moves of the rocks.
+    fn fall_period(&self) -> u32 {
+        SLOWEST.saturating_sub(self.score / 5).max(FASTEST)
+    }
+
+    fn move_player(&mut self, input: &Input) {
+        let mut dx = input.b.pressed as i32 - input.a.pressed as i32;
+        if dx == 0 && self.steps.is_multiple_of(TILT_REPEAT) {
+            dx = input.tilt.direction(TILT_THRESHOLD).0;
+        }
+        self.player = self.player.moved_within(dx, 0);
+    }
+
+    /// Move the rocks down, count the ones landing and drop a new one.
+    fn fall(&mut self) {
+        for slot in self.rocks.iter_mut() {
+            if let Some(rock) = slot {
+                rock.y += 1;
+                if !rock.is_visible() {
+                    *slot = None;
+                    self.score += 1;
+                }
+            }
+        }
+        let column = self.rng.below(WIDTH as u32) as i32;
+        if let Some(slot) = self.rocks.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(Rect::point(column, 0));
+        }
+    }
+
+    fn hit(&self) -> bool {
+        self.rocks
+            .iter()
+            .flatten()
+            .any(|rock| rock.overlaps(&self.player))
+    }
+}
+
+impl Game for Dodge {
+    fn reset(&mut self) {
+        self.player = Rect::point(WIDTH as i32 / 2, HEIGHT as i32 - 1);
+        self.rocks = [None; ROCKS];
+        self.score = 0;
+        self.steps = 0;
+    }
+
+    fn step(&mut self, input: &Input) -> Status {
+        self.steps += 1;
+        self.move_player(input);
+        if self.hit() {
+            return Status::Over;
+        }
+        if self.steps.is_multiple_of(self.fall_period()) {
+            self.fall();
+        }
+        if self.hit() {
+            Status::Over
+        } else {
+            Status::Playing
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        for rock in self.rocks.iter().flatten() {
+            rock.draw(frame, ROCK_BRIGHTNESS);
+        }
+        self.player.draw(frame, MAX_BRIGHTNESS);
+    }
+
+    fn score(&self) -> u32 {
+        self.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::game::Tilt;
+
+    const SEED: u32 = 0x1234_5678;
+
+    /// A new game with `rocks` and nothing else falling.
+    fn with_rocks(rocks: &[(i32, i32)]) -> Dodge {
+        let mut dodge = Dodge::new(SEED);
+        for (slot, &(x, y)) in dodge.rocks.iter_mut().zip(rocks) {
+            *slot = Some(Rect::point(x, y));
+        }
+        dodge
+    }
+
+    /// Steps until the rocks move with the next one.
+    fn before_fall(dodge: &mut Dodge) {
+        dodge.steps = dodge.fall_period() - 1;
+    }
+
+    fn press(a: bool, b: bool) -> Input {
+        let mut input = Input::new();
+        input.update(a, b, false, Tilt::default());
+        input
+    }
+
+    fn rocks(dodge: &Dodge) -> Vec<(i32, i32)> {
+        dodge
+            .rocks
+            .iter()
+            .flatten()
+            .map(|rock| (rock.x, rock.y))
+            .collect()
+    }
+
+    #[test]
+    fn starts_in_the_middle() {
+        let dodge = Dodge::new(SEED);
+        let mut frame = Frame::new();
+        dodge.draw(&mut frame);
+        assert_eq!(frame, Frame::literal("00000:00000:00000:00000:00900"));
+        assert_eq!(dodge.score(), 0);
+    }
+
+    #[test]
+    fn moves() {
+        let mut dodge = Dodge::new(SEED);
+        assert_eq!(dodge.step(&press(true, false)), Status::Playing);
+        assert_eq!(dodge.player, Rect::point(1, 4));
+        dodge.step(&press(true, false));
+        dodge.step(&press(true, false));
+        assert_eq!(dodge.player, Rect::point(0, 4));
+        dodge.step(&press(false, true));
+        assert_eq!(dodge.player, Rect::point(1, 4));
+
+        // Tilting moves every few steps
+        let mut dodge = Dodge::new(SEED);
+        let mut tilted = Input::new();
+        tilted.update(false, false, false, Tilt::new(500, 0));
+        for _ in 0..TILT_REPEAT * 2 {
+            dodge.step(&tilted);
+        }
+        assert_eq!(dodge.player, Rect::point(4, 4));
+    }
+
+    #[test]
+    fn rocks_fall_and_score() {
+        let mut dodge = with_rocks(&[(0, 3), (1, 4)]);
+        for _ in 0..SLOWEST - 1 {
+            assert_eq!(dodge.step(&Input::new()), Status::Playing);
+        }
+        assert_eq!(rocks(&dodge), [(0, 3), (1, 4)]);
+
+        // The rock on the ground scores, a new one drops at the top
+        assert_eq!(dodge.step(&Input::new()), Status::Playing);
+        assert_eq!(dodge.score(), 1);
+        let fallen = rocks(&dodge);
+        assert_eq!(fallen[0], (0, 4));
+        assert_eq!(fallen.len(), 2);
+        assert_eq!(fallen[1].1, 0);
+
+        let mut frame = Frame::new();
+        dodge.draw(&mut frame);
+        assert_eq!(frame.pixel(0, 4), ROCK_BRIGHTNESS);
+        assert_eq!(frame.pixel(2, 4), MAX_BRIGHTNESS);
+    }
+
+    #[test]
+    fn new_rocks_follow_the_seed() {
+        let columns = |seed| {
+            let mut dodge = Dodge::new(seed);
+            let mut rng = Rng::new(seed);
+            (0..ROCKS)
+                .map(|_| {
+                    before_fall(&mut dodge);
+                    dodge.step(&Input::new());
+                    (
+                        dodge.rocks.iter().flatten().last().unwrap().x,
+                        rng.below(5) as i32,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        for (dropped, expected) in columns(SEED) {
+            assert_eq!(dropped, expected);
+        }
+    }
+
+    #[test]
+    fn speeds_up() {
+        let mut dodge = Dodge::new(SEED);
+        let periods: Vec<u32> = [0, 4, 5, 10, 29, 30, 1000]
+            .into_iter()
+            .map(|score| {
+                dodge.score = score;
+                dodge.fall_period()
+            })
+            .collect();
+        assert_eq!(periods, [8, 8, 7, 6, 3, 2, 2]);
+
+        // At top speed rocks move every other step
+        let mut dodge = with_rocks(&[(0, 0)]);
+        dodge.score = 30;
+        dodge.step(&Input::new());
+        assert_eq!(rocks(&dodge), [(0, 0)]);
+        dodge.step(&Input::new());
+        assert_eq!(rocks(&dodge)[0], (0, 1));
+    }
+
+    #[test]
+    fn rock_landing_on_the_player_ends_the_round() {
+        let mut dodge = with_rocks(&[(2, 3)]);
+        before_fall(&mut dodge);
+        assert_eq!(dodge.step(&Input::new()), Status::Over);
+    }
+
+    #[test]
+    fn walking_into_a_rock_ends_the_round() {
+        let mut dodge = with_rocks(&[(3, 4)]);
+        assert_eq!(dodge.step(&press(false, true)), Status::Over);
+        // Reset clears the round
+        dodge.reset();
+        assert_eq!(rocks(&dodge), []);
+        assert_eq!((dodge.score(), dodge.player), (0, Rect::point(2, 4)));
+    }
+
+    #[test]
+    fn standing_still_loses() {
+        let play = || {
+            let mut dodge = Dodge::new(SEED);
+            let steps = (1..10_000)
+                .find(|_| dodge.step(&Input::new()) == Status::Over)
+                .expect("a rock lands in the middle");
+            (steps, dodge.score())
+        };
+        let (steps, score) = play();
+        assert!(steps > SLOWEST);
+        assert_eq!(play(), (steps, score));
+    }
+}
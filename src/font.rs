@@ -0,0 +1,246 @@
+//! Glyphs for scrolling text beyond the ASCII font of `microbit_text`.
+//!
+//! A [`Font`] looks a character up in its user registered glyphs first, then
+//! in the ASCII font, then in a Latin-1 extension. Accented capitals and the
+//! rarer Latin-1 letters fall back to their base letter, so every Latin-1
+//! character has a glyph. Anything else (CJK for one) shows as a hollow
+//! [`PLACEHOLDER`] so it isn't silently lost.
+//!
+//! ```ignore
+//! let mut font = Font::<4>::new();
+//! font.register('☺', images::HAPPY)?;
+//! let glyph = font.glyph('°');
+//! ```
+
+use heapless::LinearMap;
+
+use crate::frame::Frame;
+use crate::images;
+
+/// Shown for characters without a glyph.
+pub const PLACEHOLDER: Frame = Frame::literal("99999:90009:90009:90009:99999");
+
+/// Why a glyph couldn't be registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum FontError {
+    /// `G` glyphs are already registered.
+    Full,
+}
+
+/// The built-in glyphs plus up to `G` registered ones.
+pub struct Font<const G: usize> {
+    custom: LinearMap<char, Frame, G>,
+}
+
+impl<const G: usize> Font<G> {
+    pub const fn new() -> Self {
+        Font {
+            custom: LinearMap::new(),
+        }
+    }
+
+    /// Draw `c` as `glyph`, in place of a built-in or earlier glyph.
+    pub fn register(&mut self, c: char, glyph: Frame) -> Result<(), FontError> {
+        self.custom
+            .insert(c, glyph)
+            .map(|_| ())
+            .map_err(|_| FontError::Full)
+    }
+
+    /// Go back to the built-in glyph for `c`.
+    pub fn unregister(&mut self, c: char) {
+        self.custom.remove(&c);
+    }
+
+    /// What `c` looks like.
+    pub fn glyph(&self, c: char) -> Frame {
+        if let Some(glyph) = self.custom.get(&c) {
+            return *glyph;
+        }
+        builtin(c).unwrap_or(PLACEHOLDER)
+    }
+}
+
+impl<const G: usize> Default for Font<G> {
+    fn default() -> Self {
+        Font::new()
+    }
+}
+
+/// Glyph of `c` in the ASCII font or the extension.
+pub fn builtin(c: char) -> Option<Frame> {
+    if c == ' ' || c.is_ascii_graphic() {
+        return Some(ascii(c as u8));
+    }
+    if let Some((_, glyph)) = EXTENDED.iter().find(|(e, _)| *e == c) {
+        return Some(*glyph);
+    }
+    base_letter(c).map(ascii)
+}
+
+fn ascii(c: u8) -> Frame {
+    Frame::from_render(microbit_text::font::character(c))
+}
+
+/// The ASCII character standing in for a Latin-1 one without its own glyph.
+fn base_letter(c: char) -> Option<u8> {
+    let base = match c {
+        '\u{a0}' => b' ',
+        '¦' => b'|',
+        '¨' => b'"',
+        '´' => b'\'',
+        '¯' | '¬' | '\u{ad}' => b'-',
+        '·' => b'.',
+        '¸' => b',',
+        '«' => b'<',
+        '»' => b'>',
+        '©' => b'C',
+        '®' => b'R',
+        '§' => b'S',
+        '¹' => b'1',
+        'ª' | 'å' | 'æ' => b'a',
+        'º' | 'ø' => b'o',
+        'À'..='Æ' => b'A',
+        'Ç' => b'C',
+        'È'..='Ë' => b'E',
+        'Ì'..='Ï' => b'I',
+        'Ð' => b'D',
+        'Ñ' => b'N',
+        'Ò'..='Ö' | 'Ø' => b'O',
+        'Ù'..='Ü' => b'U',
+        'Ý' => b'Y',
+        'Þ' => b'P',
+        'ç' => b'c',
+        'ì'..='ï' => b'i',
+        'ð' => b'd',
+        'ý' | 'ÿ' => b'y',
+        'þ' => b'p',
+        _ => return None,
+    };
+    Some(base)
+}
+
+/// Glyphs of their own outside ASCII.
+static EXTENDED: [(char, Frame); 44] = [
+    ('°', Frame::literal("09900:90090:90090:09900:00000")),
+    ('±', Frame::literal("00900:09990:00900:00000:09990")),
+    ('×', Frame::literal("00000:90090:09900:09900:90090")),
+    ('÷', Frame::literal("00900:00000:09990:00000:00900")),
+    ('¡', Frame::literal("09000:00000:09000:09000:09000")),
+    ('¿', Frame::literal("00900:00000:09900:90009:09990")),
+    ('¢', Frame::literal("00900:09990:90900:09990:00900")),
+    ('£', Frame::literal("00990:09000:99900:09000:99990")),
+    ('¥', Frame::literal("90009:09090:99999:00900:00900")),
+    ('¤', Frame::literal("90009:09990:09090:09990:90009")),
+    ('€', Frame::literal("00999:09000:99990:09000:00999")),
+    ('µ', Frame::literal("00000:90090:90090:99900:90000")),
+    ('²', Frame::literal("99000:00900:09000:99900:00000")),
+    ('³', Frame::literal("99000:09900:00900:99000:00000")),
+    ('¼', Frame::literal("90009:90090:00900:09090:90099")),
+    ('½', Frame::literal("90009:90090:00900:09099:90090")),
+    ('¾', Frame::literal("99009:09090:00900:09090:90099")),
+    ('¶', Frame::literal("09999:99909:09909:00909:00909")),
+    ('ß', Frame::literal("09900:90090:90900:90090:90900")),
+    ('à', Frame::literal("90000:09990:90090:90090:09999")),
+    ('á', Frame::literal("00090:09990:90090:90090:09999")),
+    ('â', Frame::literal("09900:09990:90090:90090:09999")),
+    ('ã', Frame::literal("09990:09990:90090:90090:09999")),
+    ('ä', Frame::literal("90090:09990:90090:90090:09999")),
+    ('è', Frame::literal("90000:09900:99990:90000:09990")),
+    ('é', Frame::literal("00090:09900:99990:90000:09990")),
+    ('ê', Frame::literal("09900:09900:99990:90000:09990")),
+    ('ë', Frame::literal("90090:09900:99990:90000:09990")),
+    ('ò', Frame::literal("90000:09900:90090:90090:09900")),
+    ('ó', Frame::literal("00090:09900:90090:90090:09900")),
+    ('ô', Frame::literal("09900:09900:90090:90090:09900")),
+    ('õ', Frame::literal("09990:09900:90090:90090:09900")),
+    ('ö', Frame::literal("90090:09900:90090:90090:09900")),
+    ('ù', Frame::literal("90000:90090:90090:90090:09999")),
+    ('ú', Frame::literal("00090:90090:90090:90090:09999")),
+    ('û', Frame::literal("09900:90090:90090:90090:09999")),
+    ('ü', Frame::literal("90090:90090:90090:90090:09999")),
+    ('ñ', Frame::literal("09990:99900:90090:90090:90090")),
+    ('←', images::ARROW_W),
+    ('↑', images::ARROW_N),
+    ('→', images::ARROW_E),
+    ('↓', images::ARROW_S),
+    ('♥', images::HEART),
+    ('♪', images::MUSIC_QUAVER),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_glyphs() {
+        let font = Font::<1>::new();
+        assert_eq!(font.glyph('A'), ascii(b'A'));
+        assert_eq!(font.glyph(' '), Frame::new());
+        assert_ne!(font.glyph('a'), font.glyph('A'));
+    }
+
+    #[test]
+    fn latin1() {
+        let font = Font::<1>::new();
+        assert_eq!(
+            font.glyph('°'),
+            Frame::literal("09900:90090:90090:09900:00000")
+        );
+        assert_eq!(font.glyph('♥'), images::HEART);
+        // Fallbacks to the base letter
+        assert_eq!(font.glyph('å'), font.glyph('a'));
+        assert_eq!(font.glyph('Ä'), font.glyph('A'));
+        assert_eq!(font.glyph('Ø'), font.glyph('O'));
+        assert_eq!(font.glyph('ÿ'), font.glyph('y'));
+        assert_eq!(font.glyph('\u{a0}'), font.glyph(' '));
+        // Lowercase letters with a glyph of their own
+        assert_ne!(font.glyph('ä'), font.glyph('a'));
+        assert_ne!(font.glyph('ä'), PLACEHOLDER);
+    }
+
+    #[test]
+    fn placeholder() {
+        let font = Font::<1>::new();
+        assert_eq!(builtin('中'), None);
+        assert_eq!(font.glyph('中'), PLACEHOLDER);
+        assert_eq!(font.glyph('\n'), PLACEHOLDER);
+    }
+
+    #[test]
+    fn latin1_complete() {
+        for c in '\u{a0}'..='ÿ' {
+            assert!(builtin(c).is_some(), "{c}");
+        }
+    }
+
+    #[test]
+    fn registered() {
+        let mut font = Font::<2>::new();
+        font.register('☺', images::HAPPY).unwrap();
+        font.register('A', images::HEART).unwrap();
+        assert_eq!(font.glyph('☺'), images::HAPPY);
+        assert_eq!(font.glyph('A'), images::HEART);
+        assert_eq!(font.register('¼', images::SAD), Err(FontError::Full));
+        // Replacing a glyph takes no room
+        font.register('☺', images::SAD).unwrap();
+        assert_eq!(font.glyph('☺'), images::SAD);
+
+        font.unregister('A');
+        assert_eq!(font.glyph('A'), ascii(b'A'));
+        font.register('¼', images::HAPPY).unwrap();
+        assert_eq!(font.glyph('¼'), images::HAPPY);
+    }
+
+    #[test]
+    fn extended_glyphs_unique() {
+        for (i, (c, glyph)) in EXTENDED.iter().enumerate() {
+            assert!(EXTENDED[..i].iter().all(|(e, _)| e != c), "{c} twice");
+            assert!(
+                !c.is_ascii() && base_letter(*c).is_none(),
+                "{c} also falls back"
+            );
+            assert_ne!(*glyph, PLACEHOLDER, "{c}");
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! Greyscale image of the 5×5 LED matrix with drawing primitives.
+//!
+//! Coordinates are `(x, y)` from the top left, x to the right and y down. They
+//! are signed so shapes can reach past the edges, what falls outside is clipped.
+//!
+//! ```ignore
+//! let mut frame = Frame::new();
+//! frame.rect(0, 0, 5, 5, 3).line(0, 0, 4, 4, MAX_BRIGHTNESS);
+//! display.show(&mut timer, frame.into(), 1000);
+//!
+//! // MicroPython's image format
+//! let heart: Frame = "09090:99999:99999:09990:00900".parse()?;
+//! ```
+
+use core::str::FromStr;
+
+use microbit::display::nonblocking::{Frame as _, MicrobitFrame};
+use tiny_led_matrix::Render;
+
+/// Brightness of a fully lit LED.
+pub const MAX_BRIGHTNESS: u8 = 9;
+pub const WIDTH: usize = 5;
+pub const HEIGHT: usize = 5;
+
+/// Why a [`Frame`] couldn't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ParseError {
+    /// A character other than a digit, `:` or a newline.
+    InvalidChar,
+    /// A row without five LEDs.
+    RowLength,
+    /// Not five rows.
+    RowCount,
+}
+
+/// Brightness from 0 to [`MAX_BRIGHTNESS`] of each LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, defmt::Format)]
+pub struct Frame([[u8; WIDTH]; HEIGHT]);
+
+impl Frame {
+    /// A dark frame.
+    pub const fn new() -> Frame {
+        Frame([[0; WIDTH]; HEIGHT])
+    }
+
+    /// Brightness row by row, values above [`MAX_BRIGHTNESS`] are clamped.
+    pub fn from_rows(rows: [[u8; WIDTH]; HEIGHT]) -> Frame {
+        Frame(rows.map(|row| row.map(|b| b.min(MAX_BRIGHTNESS))))
+    }
+
+    /// On/off rows, like the images in [`led`](crate::led), lit at full brightness.
+    pub fn from_bits(rows: [[u8; WIDTH]; HEIGHT]) -> Frame {
+        Frame(rows.map(|row| row.map(|b| if b > 0 { MAX_BRIGHTNESS } else { 0 })))
+    }
+
+    /// Copy of anything the display can show, such as a `microbit_text` glyph.
+    pub fn from_render(image: &impl Render) -> Frame {
+        let mut frame = Frame::new();
+        for (y, row) in frame.0.iter_mut().enumerate() {
+            for (x, b) in row.iter_mut().enumerate() {
+                *b = image.brightness_at(x, y).min(MAX_BRIGHTNESS);
+            }
+        }
+        frame
+    }
+
+    /// Parse MicroPython's image format, five rows of five digits separated by
+    /// `:` or newlines, e.g. `"09090:99999:99999:09990:00900"`. A separator may
+    /// end the last row.
+    pub const fn parse(text: &str) -> Result<Frame, ParseError> {
+        let bytes = text.as_bytes();
+        let mut rows = [[0; WIDTH]; HEIGHT];
+        let (mut row, mut col, mut i) = (0, 0, 0);
+        while i < bytes.len() {
+            match bytes[i] {
+                digit @ b'0'..=b'9' => {
+                    if row == HEIGHT {
+                        return Err(ParseError::RowCount);
+                    }
+                    if col == WIDTH {
+                        return Err(ParseError::RowLength);
+                    }
+                    rows[row][col] = digit - b'0';
+                    col += 1;
+                }
+                b':' | b'\n' => {
+                    if col != WIDTH {
+                        return Err(ParseError::RowLength);
+                    }
+                    row += 1;
+                    col = 0;
+                }
+                _ => return Err(ParseError::InvalidChar),
+            }
+            i += 1;
+        }
+        if col == WIDTH {
+            row += 1;
+        } else if col != 0 {
+            return Err(ParseError::RowLength);
+        }
+        if row != HEIGHT {
+            return Err(ParseError::RowCount);
+        }
+        Ok(Frame(rows))
+    }
+
+    /// [`Frame::parse`] for constants, an invalid image fails the build.
+    ///
+    /// # Panics
+    ///
+    /// If `text` isn't a valid image.
+    pub const fn literal(text: &str) -> Frame {
+        match Frame::parse(text) {
+            Ok(frame) => frame,
+            Err(_) => panic!("invalid image"),
+        }
+    }
+
+    pub fn rows(&self) -> [[u8; WIDTH]; HEIGHT] {
+        self.0
+    }
+
+    /// Brightness at `(x, y)`, 0 outside the frame.
+    pub fn pixel(&self, x: i32, y: i32) -> u8 {
+        index(x, y).map_or(0, |(x, y)| self.0[y][x])
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, brightness: u8) -> &mut Self {
+        if let Some((x, y)) = index(x, y) {
+            self.0[y][x] = brightness.min(MAX_BRIGHTNESS);
+        }
+        self
+    }
+
+    pub fn fill(&mut self, brightness: u8) -> &mut Self {
+        *self = Frame([[brightness.min(MAX_BRIGHTNESS); WIDTH]; HEIGHT]);
+        self
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.fill(0)
+    }
+
+    /// Line from `(x0, y0)` to `(x1, y1)`, both ends included.
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, brightness: u8) -> &mut Self {
+        // Bresenham, but only over the steps along the longer axis that land
+        // inside the frame, so far away ends don't cost billions of steps
+        let (x0, y0, x1, y1) = (x0 as i64, y0 as i64, x1 as i64, y1 as i64);
+        let (dx, dy) = ((x1 - x0).abs(), (y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (major, minor) = (dx.max(dy), dx.min(dy));
+        if major == 0 {
+            return self.set_pixel(x0 as i32, y0 as i32, brightness);
+        }
+        let (start, step, size) = if dx >= dy {
+            (x0, sx, WIDTH as i64)
+        } else {
+            (y0, sy, HEIGHT as i64)
+        };
+        let (first, last) = if step > 0 {
+            (-start, size - 1 - start)
+        } else {
+            (start - (size - 1), start)
+        };
+        for i in first.max(0)..=last.min(major) {
+            // Steps along the shorter axis after `i` along the longer one,
+            // rounded the same way as the error term of the usual loop
+            let j = ((2 * i as i128 * minor as i128 + major as i128) / (2 * major as i128)) as i64;
+            let (x, y) = if dx >= dy {
+                (x0 + sx * i, y0 + sy * j)
+            } else {
+                (x0 + sx * j, y0 + sy * i)
+            };
+            // both lie between the ends, so they fit
+            self.set_pixel(x as i32, y as i32, brightness);
+        }
+        self
+    }
+
+    /// Outline of the `width` by `height` rectangle with its top left at `(x, y)`.
+    pub fn rect(&mut self, x: i32, y: i32, width: i32, height: i32, brightness: u8) -> &mut Self {
+        if width <= 0 || height <= 0 {
+            return self;
+        }
+        let (right, bottom) = (x.saturating_add(width - 1), y.saturating_add(height - 1));
+        self.line(x, y, right, y, brightness)
+            .line(x, bottom, right, bottom, brightness)
+            .line(x, y, x, bottom, brightness)
+            .line(right, y, right, bottom, brightness)
+    }
+
+    pub fn fill_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        brightness: u8,
+    ) -> &mut Self {
+        let rows = y.max(0)..y.saturating_add(height).min(HEIGHT as i32);
+        let cols = x.max(0)..x.saturating_add(width).min(WIDTH as i32);
+        for row in rows {
+            for col in cols.clone() {
+                self.set_pixel(col, row, brightness);
+            }
+        }
+        self
+    }
+
+    /// Swap bright and dark, brightness `b` becomes `9 - b`.
+    pub fn invert(&mut self) -> &mut Self {
+        self.map(|b| MAX_BRIGHTNESS - b)
+    }
+
+    /// Scale the brightness so [`MAX_BRIGHTNESS`] becomes `max`, LEDs that are
+    /// lit stay lit.
+    pub fn scale(&mut self, max: u8) -> &mut Self {
+        let max = max.min(MAX_BRIGHTNESS);
+        self.map(|b| match b {
+            0 => 0,
+            b => (b * max / MAX_BRIGHTNESS).max(1),
+        })
+    }
+
+    /// Move the picture `dx` to the right and `dy` down, the LEDs left behind
+    /// are dark.
+    pub fn shift(&mut self, dx: i32, dy: i32) -> &mut Self {
+        let old = *self;
+        self.remap(|x, y| old.pixel(x - dx, y - dy))
+    }
+
+    /// Move the picture `dx` to the right and `dy` down, what leaves one edge
+    /// comes back on the other.
+    pub fn scroll(&mut self, dx: i32, dy: i32) -> &mut Self {
+        let old = *self;
+        self.remap(|x, y| {
+            old.pixel(
+                (x - dx).rem_euclid(WIDTH as i32),
+                (y - dy).rem_euclid(HEIGHT as i32),
+            )
+        })
+    }
+
+    /// Turn a quarter clockwise.
+    pub fn rotate_90(&mut self) -> &mut Self {
+        let old = *self;
+        let last = HEIGHT as i32 - 1;
+        self.remap(|x, y| old.pixel(y, last - x))
+    }
+
+    pub fn rotate_180(&mut self) -> &mut Self {
+        let old = *self;
+        let (right, bottom) = (WIDTH as i32 - 1, HEIGHT as i32 - 1);
+        self.remap(|x, y| old.pixel(right - x, bottom - y))
+    }
+
+    /// Mirror left to right.
+    pub fn flip_horizontal(&mut self) -> &mut Self {
+        let old = *self;
+        let right = WIDTH as i32 - 1;
+        self.remap(|x, y| old.pixel(right - x, y))
+    }
+
+    /// Mirror top to bottom.
+    pub fn flip_vertical(&mut self) -> &mut Self {
+        let old = *self;
+        let bottom = HEIGHT as i32 - 1;
+        self.remap(|x, y| old.pixel(x, bottom - y))
+    }
+
+    fn map(&mut self, f: impl Fn(u8) -> u8) -> &mut Self {
+        self.0 = self.0.map(|row| row.map(&f));
+        self
+    }
+
+    /// Set each LED to `f(x, y)`.
+    fn remap(&mut self, f: impl Fn(i32, i32) -> u8) -> &mut Self {
+        for (y, row) in self.0.iter_mut().enumerate() {
+            for (x, b) in row.iter_mut().enumerate() {
+                *b = f(x as i32, y as i32);
+            }
+        }
+        self
+    }
+}
+
+impl FromStr for Frame {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Frame, ParseError> {
+        Frame::parse(text)
+    }
+}
+
+/// Array indices of `(x, y)` if it is inside the frame.
+fn index(x: i32, y: i32) -> Option<(usize, usize)> {
+    let x = usize::try_from(x).ok().filter(|&x| x < WIDTH)?;
+    let y = usize::try_from(y).ok().filter(|&y| y < HEIGHT)?;
+    Some((x, y))
+}
+
+/// Rows for `microbit::display::blocking::Display::show`, which lights every
+/// LED that isn't 0 at full brightness.
+impl From<Frame> for [[u8; WIDTH]; HEIGHT] {
+    fn from(frame: Frame) -> Self {
+        frame.0
+    }
+}
+
+impl Render for Frame {
+    fn brightness_at(&self, x: usize, y: usize) -> u8 {
+        self.0[y][x]
+    }
+}
+
+impl From<&Frame> for MicrobitFrame {
+    fn from(frame: &Frame) -> Self {
+        let mut microbit_frame = MicrobitFrame::default();
+        microbit_frame.set(frame);
+        microbit_frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tells every turn and mirror apart.
+    const PICTURE: Frame = Frame::literal("12300:40000:50000:00000:00000");
+
+    fn drawn(draw: impl FnOnce(&mut Frame) -> &mut Frame) -> Frame {
+        *draw(&mut Frame::new())
+    }
+
+    fn changed(draw: impl FnOnce(&mut Frame) -> &mut Frame) -> Frame {
+        *draw(&mut PICTURE.clone())
+    }
+
+    #[test]
+    fn parse() {
+        let rows = [
+            [0, 9, 0, 9, 0],
+            [9, 9, 9, 9, 9],
+            [9, 9, 9, 9, 9],
+            [0, 9, 9, 9, 0],
+            [0, 0, 9, 0, 0],
+        ];
+        let heart = Frame::from_rows(rows);
+        assert_eq!("09090:99999:99999:09990:00900".parse(), Ok(heart));
+        assert_eq!("09090\n99999\n99999\n09990\n00900\n".parse(), Ok(heart));
+        assert_eq!("09090:99999:99999:09990:00900:".parse(), Ok(heart));
+        assert_eq!(heart.rows(), rows);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |text: &str| text.parse::<Frame>().unwrap_err();
+        assert_eq!(
+            error("09090:99999:99x99:09990:00900"),
+            ParseError::InvalidChar
+        );
+        assert_eq!(
+            error("09090:99999: 9999:09990:00900"),
+            ParseError::InvalidChar
+        );
+        assert_eq!(error("0909:99999:99999:09990:00900"), ParseError::RowLength);
+        assert_eq!(
+            error("090900:99999:99999:09990:00900"),
+            ParseError::RowLength
+        );
+        assert_eq!(error("09090:99999:99999:09990:009"), ParseError::RowLength);
+        assert_eq!(error("09090:99999:99999:09990"), ParseError::RowCount);
+        assert_eq!(
+            error("09090:99999:99999:09990:00900:00000"),
+            ParseError::RowCount
+        );
+        assert_eq!(error(""), ParseError::RowCount);
+    }
+
+    #[test]
+    fn pixels() {
+        let mut frame = Frame::new();
+        frame
+            .set_pixel(4, 0, 7)
+            .set_pixel(0, 4, 20)
+            .set_pixel(5, 0, 9);
+        frame.set_pixel(-1, 2, 9);
+        assert_eq!(frame, Frame::literal("00007:00000:00000:00000:90000"));
+        assert_eq!(frame.pixel(4, 0), 7);
+        assert_eq!(frame.pixel(5, 0), 0);
+        assert_eq!(frame.pixel(0, -1), 0);
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            drawn(|f| f.line(0, 0, 4, 0, 9)),
+            Frame::literal("99999:00000:00000:00000:00000")
+        );
+        assert_eq!(
+            drawn(|f| f.line(4, 4, 0, 0, 5)),
+            Frame::literal("50000:05000:00500:00050:00005")
+        );
+        assert_eq!(
+            drawn(|f| f.line(0, 0, 1, 4, 9)),
+            Frame::literal("90000:90000:09000:09000:09000")
+        );
+        assert_eq!(
+            drawn(|f| f.line(2, 2, 2, 2, 9)),
+            Frame::literal("00000:00000:00900:00000:00000")
+        );
+        // Clipped at both ends
+        assert_eq!(
+            drawn(|f| f.line(-3, 2, 7, 2, 9)),
+            Frame::literal("00000:00000:99999:00000:00000")
+        );
+        assert_eq!(
+            drawn(|f| f.line(i32::MIN, i32::MIN, i32::MAX, i32::MAX, 9)),
+            Frame::literal("90000:09000:00900:00090:00009")
+        );
+        assert_eq!(
+            drawn(|f| f.line(i32::MAX, 2, i32::MIN, 2, 9)),
+            Frame::literal("00000:00000:99999:00000:00000")
+        );
+    }
+
+    /// Every pixel of the line in turn, the plain Bresenham loop.
+    fn bresenham(x0: i32, y0: i32, x1: i32, y1: i32, brightness: u8) -> Frame {
+        let mut frame = Frame::new();
+        let (dx, sx) = ((x1 - x0).abs(), (x1 - x0).signum());
+        let (dy, sy) = (-(y1 - y0).abs(), (y1 - y0).signum());
+        let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+        loop {
+            frame.set_pixel(x, y, brightness);
+            if x == x1 && y == y1 {
+                return frame;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += sx;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    #[test]
+    fn lines_match_bresenham() {
+        let range = -6..=10;
+        for x0 in range.clone() {
+            for y0 in range.clone() {
+                for x1 in range.clone() {
+                    for y1 in range.clone() {
+                        assert_eq!(
+                            drawn(|f| f.line(x0, y0, x1, y1, 9)),
+                            bresenham(x0, y0, x1, y1, 9),
+                            "({x0}, {y0}) to ({x1}, {y1})"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rects() {
+        assert_eq!(
+            drawn(|f| f.rect(0, 0, 5, 5, 9)),
+            Frame::literal("99999:90009:90009:90009:99999")
+        );
+        assert_eq!(
+            drawn(|f| f.rect(1, 1, 0, 3, 9).rect(1, 1, 3, -1, 9)),
+            Frame::new()
+        );
+        assert_eq!(
+            drawn(|f| f.fill_rect(1, 1, 3, 2, 5)),
+            Frame::literal("00000:05550:05550:00000:00000")
+        );
+        assert_eq!(
+            drawn(|f| f.fill_rect(3, -2, 5, 4, 9)),
+            Frame::literal("00099:00099:00000:00000:00000")
+        );
+        assert_eq!(drawn(|f| f.fill_rect(0, 0, -5, 5, 9)), Frame::new());
+        // Sizes that would overflow or loop for billions of pixels
+        assert_eq!(
+            drawn(|f| f.fill_rect(i32::MAX - 1, 0, i32::MAX, 5, 9)),
+            Frame::new()
+        );
+        assert_eq!(
+            drawn(|f| f.fill_rect(-10, -10, i32::MAX, i32::MAX, 9)),
+            *Frame::new().fill(9)
+        );
+        assert_eq!(
+            drawn(|f| f.rect(-1, 1, i32::MAX, 3, 9)),
+            Frame::literal("00000:99999:00000:99999:00000")
+        );
+        assert_eq!(
+            drawn(|f| f.rect(i32::MAX, i32::MAX, i32::MAX, i32::MAX, 9)),
+            Frame::new()
+        );
+    }
+
+    #[test]
+    fn shift() {
+        assert_eq!(
+            changed(|f| f.shift(1, 1)),
+            Frame::literal("00000:01230:04000:05000:00000")
+        );
+        assert_eq!(
+            changed(|f| f.shift(-1, 0)),
+            Frame::literal("23000:00000:00000:00000:00000")
+        );
+        assert_eq!(changed(|f| f.shift(0, 5)), Frame::new());
+    }
+
+    #[test]
+    fn scroll() {
+        assert_eq!(
+            changed(|f| f.scroll(-1, 0)),
+            Frame::literal("23001:00004:00005:00000:00000")
+        );
+        assert_eq!(
+            changed(|f| f.scroll(0, -1)),
+            Frame::literal("40000:50000:00000:00000:12300")
+        );
+        assert_eq!(changed(|f| f.scroll(5, -10)), PICTURE);
+    }
+
+    #[test]
+    fn rotate() {
+        assert_eq!(
+            changed(|f| f.rotate_90()),
+            Frame::literal("00541:00002:00003:00000:00000")
+        );
+        assert_eq!(
+            changed(|f| f.rotate_180()),
+            Frame::literal("00000:00000:00005:00004:00321")
+        );
+        assert_eq!(
+            changed(|f| f.rotate_90().rotate_90()),
+            changed(|f| f.rotate_180())
+        );
+        assert_eq!(
+            changed(|f| f.rotate_90().rotate_90().rotate_90().rotate_90()),
+            PICTURE
+        );
+    }
+
+    #[test]
+    fn flip() {
+        assert_eq!(
+            changed(|f| f.flip_horizontal()),
+            Frame::literal("00321:00004:00005:00000:00000")
+        );
+        assert_eq!(
+            changed(|f| f.flip_vertical()),
+            Frame::literal("00000:00000:50000:40000:12300")
+        );
+        assert_eq!(
+            changed(|f| f.flip_horizontal().flip_vertical()),
+            changed(|f| f.rotate_180())
+        );
+    }
+
+    #[test]
+    fn invert() {
+        assert_eq!(
+            changed(|f| f.invert()),
+            Frame::literal("87699:59999:49999:99999:99999")
+        );
+        assert_eq!(changed(|f| f.invert().invert()), PICTURE);
+    }
+
+    #[test]
+    fn scale() {
+        let frame = Frame::literal("12300:40000:50000:90000:00000");
+        let scaled = |max| *frame.clone().scale(max);
+        assert_eq!(scaled(5), Frame::literal("11100:20000:20000:50000:00000"));
+        // Lit LEDs stay lit
+        assert_eq!(scaled(0), Frame::literal("11100:10000:10000:10000:00000"));
+        assert_eq!(scaled(MAX_BRIGHTNESS), frame);
+        assert_eq!(scaled(200), frame);
+    }
+}
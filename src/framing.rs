@@ -0,0 +1,221 @@
+//! Packet framing for binary data over the serial port.
+//!
+//! A frame is the payload followed by its CRC-16/CCITT-FALSE (big endian), COBS
+//! encoded so it contains no zero bytes, then terminated by a single zero byte.
+//! Receivers resynchronise on the next zero after any corruption.
+
+use heapless::Vec;
+
+pub const DELIMITER: u8 = 0;
+const CRC_SIZE: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum FrameError {
+    /// The output buffer can't hold the result.
+    BufferTooSmall,
+    /// The frame isn't valid COBS.
+    Malformed,
+    /// The frame was damaged in transit.
+    Crc,
+}
+
+/// Worst case COBS encoded size of `len` bytes, without the delimiter.
+pub const fn max_encoded_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// Worst case size of a frame carrying `len` payload bytes, delimiter included.
+pub const fn max_frame_len(len: usize) -> usize {
+    max_encoded_len(len + CRC_SIZE) + 1
+}
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFF_u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// COBS encode `src` into `dst`, returns the encoded length.
+pub fn cobs_encode(src: &[u8], dst: &mut [u8]) -> Result<usize, FrameError> {
+    if dst.len() < max_encoded_len(src.len()) {
+        return Err(FrameError::BufferTooSmall);
+    }
+    let mut encoder = CobsEncoder::new(dst);
+    for &byte in src {
+        encoder.push(byte);
+    }
+    Ok(encoder.finish())
+}
+
+/// Decode a COBS block without its delimiter, returns the decoded length.
+pub fn cobs_decode(src: &[u8], dst: &mut [u8]) -> Result<usize, FrameError> {
+    let mut pos = 0;
+    let mut out = 0;
+    while pos < src.len() {
+        let code = src[pos] as usize;
+        if code == 0 {
+            return Err(FrameError::Malformed);
+        }
+        let end = pos + code;
+        if end > src.len() {
+            return Err(FrameError::Malformed);
+        }
+        for &byte in &src[pos + 1..end] {
+            if byte == 0 {
+                return Err(FrameError::Malformed);
+            }
+            *dst.get_mut(out).ok_or(FrameError::BufferTooSmall)? = byte;
+            out += 1;
+        }
+        pos = end;
+        // A full block isn't followed by an implicit zero, neither is the last one
+        if code < 0xFF && pos < src.len() {
+            *dst.get_mut(out).ok_or(FrameError::BufferTooSmall)? = 0;
+            out += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Build a complete frame for `payload` into `dst`, returns the frame length.
+pub fn encode_frame(payload: &[u8], dst: &mut [u8]) -> Result<usize, FrameError> {
+    if dst.len() < max_frame_len(payload.len()) {
+        return Err(FrameError::BufferTooSmall);
+    }
+    let crc = crc16(payload).to_be_bytes();
+    let mut encoder = CobsEncoder::new(dst);
+    for &byte in payload.iter().chain(crc.iter()) {
+        encoder.push(byte);
+    }
+    let len = encoder.finish();
+    dst[len] = DELIMITER;
+    Ok(len + 1)
+}
+
+/// Check and unpack a frame, with or without its trailing delimiter, into `dst`.
+///
+/// Returns the payload length.
+pub fn decode_frame(frame: &[u8], dst: &mut [u8]) -> Result<usize, FrameError> {
+    let frame = frame.strip_suffix(&[DELIMITER]).unwrap_or(frame);
+    let len = cobs_decode(frame, dst)?;
+    if len < CRC_SIZE {
+        return Err(FrameError::Malformed);
+    }
+    let payload_len = len - CRC_SIZE;
+    let crc = u16::from_be_bytes([dst[payload_len], dst[payload_len + 1]]);
+    if crc16(&dst[..payload_len]) != crc {
+        return Err(FrameError::Crc);
+    }
+    Ok(payload_len)
+}
+
+/// Collects received bytes until a delimiter completes a frame.
+///
+/// Frames longer than `N` are dropped whole.
+pub struct FrameBuffer<const N: usize> {
+    buf: Vec<u8, N>,
+    complete: bool,
+    overflow: bool,
+    dropped: u32,
+}
+
+impl<const N: usize> Default for FrameBuffer<N> {
+    fn default() -> Self {
+        FrameBuffer::new()
+    }
+}
+
+impl<const N: usize> FrameBuffer<N> {
+    pub const fn new() -> Self {
+        FrameBuffer {
+            buf: Vec::new(),
+            complete: false,
+            overflow: false,
+            dropped: 0,
+        }
+    }
+
+    /// Add a byte, returns the frame it completes, without the delimiter.
+    pub fn push(&mut self, byte: u8) -> Option<&[u8]> {
+        if self.complete {
+            self.complete = false;
+            self.buf.clear();
+        }
+        if byte != DELIMITER {
+            if self.buf.push(byte).is_err() {
+                self.overflow = true;
+            }
+            return None;
+        }
+        if self.overflow {
+            self.overflow = false;
+            self.dropped += 1;
+            self.buf.clear();
+            return None;
+        }
+        if self.buf.is_empty() {
+            return None;
+        }
+        self.complete = true;
+        Some(&self.buf)
+    }
+
+    /// Number of frames too long for the buffer.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+/// Encodes into a buffer already known to be large enough.
+struct CobsEncoder<'a> {
+    dst: &'a mut [u8],
+    code_pos: usize,
+    pos: usize,
+    code: u8,
+}
+
+impl<'a> CobsEncoder<'a> {
+    fn new(dst: &'a mut [u8]) -> Self {
+        CobsEncoder {
+            dst,
+            code_pos: 0,
+            pos: 1,
+            code: 1,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if byte == 0 {
+            self.close_block();
+            return;
+        }
+        self.dst[self.pos] = byte;
+        self.pos += 1;
+        self.code += 1;
+        if self.code == 0xFF {
+            self.close_block();
+        }
+    }
+
+    fn close_block(&mut self) {
+        self.dst[self.code_pos] = self.code;
+        self.code_pos = self.pos;
+        self.pos += 1;
+        self.code = 1;
+    }
+
+    fn finish(self) -> usize {
+        self.dst[self.code_pos] = self.code;
+        self.pos
+    }
+}
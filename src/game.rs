@@ -0,0 +1,472 @@
+//! Scaffolding for small games on the 5×5 display.
+//!
+//! A [`Game`] sees the buttons, the logo and the tilt of the board only as an
+//! [`Input`] snapshot and draws into a [`Frame`], so its logic runs on the host
+//! as well. [`Runner`] steps it at a fixed rate, scrolls the score when a round
+//! is over and starts the next one when A or B is pressed.
+//!
+//! ```ignore
+//! // every STEP, from a task re-spawned with spawn_at(last + STEP)
+//! touch.scan();
+//! let logo = touch.is_touched(Pad::Logo);
+//! input.update(a.is_low()?, b.is_low()?, logo, Tilt::new(accel.x, accel.y));
+//! let frame = runner.step(&input);
+//! display.show(&frame);
+//! ```
+
+use core::fmt::Write;
+
+use heapless::String;
+use microbit_text::scrolling::Animate;
+
+use crate::frame::{Frame, HEIGHT, WIDTH};
+use crate::scroller::TextScroller;
+
+/// A button, or the logo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct Key {
+    pub held: bool,
+    /// Went down since the step before.
+    pub pressed: bool,
+}
+
+impl Key {
+    fn update(&mut self, held: bool) {
+        self.pressed = held && !self.held;
+        self.held = held;
+    }
+}
+
+/// Acceleration across the board in milli-g, as in [`gesture`](crate::gesture):
+/// negative `x` with the left edge down, negative `y` with the logo up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct Tilt {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Tilt {
+    pub const fn new(x: i32, y: i32) -> Tilt {
+        Tilt { x, y }
+    }
+
+    /// Which way a ball on the display would roll, -1, 0 or 1 on each axis in
+    /// display coordinates, 0 below `threshold` milli-g.
+    pub fn direction(&self, threshold: i32) -> (i32, i32) {
+        let axis = |g: i32| if g.abs() < threshold { 0 } else { g.signum() };
+        (axis(self.x), -axis(self.y))
+    }
+}
+
+/// The controls as they were at a step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct Input {
+    pub a: Key,
+    pub b: Key,
+    pub logo: Key,
+    pub tilt: Tilt,
+}
+
+impl Input {
+    /// Nothing held, board level.
+    pub const fn new() -> Input {
+        let key = Key {
+            held: false,
+            pressed: false,
+        };
+        Input {
+            a: key,
+            b: key,
+            logo: key,
+            tilt: Tilt::new(0, 0),
+        }
+    }
+
+    /// Take the next snapshot, presses shorter than a step may be missed.
+    pub fn update(&mut self, a: bool, b: bool, logo: bool, tilt: Tilt) {
+        self.a.update(a);
+        self.b.update(b);
+        self.logo.update(logo);
+        self.tilt = tilt;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Status {
+    Playing,
+    Over,
+}
+
+pub trait Game {
+    /// Set up a new round.
+    fn reset(&mut self);
+
+    /// Move the game on by one step.
+    fn step(&mut self, input: &Input) -> Status;
+
+    /// Draw the game into `frame`, which is cleared first.
+    fn draw(&self, frame: &mut Frame);
+
+    fn score(&self) -> u32;
+}
+
+/// Cells `x`, `y` to `x + width - 1`, `y + height - 1`, for sprites and walls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// A single cell.
+    pub const fn point(x: i32, y: i32) -> Rect {
+        Rect::new(x, y, 1, 1)
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// Whether the two share a cell.
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Whether any of it is on the display.
+    pub fn is_visible(&self) -> bool {
+        self.overlaps(&Rect::new(0, 0, WIDTH as i32, HEIGHT as i32))
+    }
+
+    /// Moved by `dx`, `dy` but kept on the display.
+    pub fn moved_within(&self, dx: i32, dy: i32) -> Rect {
+        Rect {
+            x: (self.x + dx).clamp(0, (WIDTH as i32 - self.width).max(0)),
+            y: (self.y + dy).clamp(0, (HEIGHT as i32 - self.height).max(0)),
+            ..*self
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, brightness: u8) {
+        frame.fill_rect(self.x, self.y, self.width, self.height, brightness);
+    }
+}
+
+/// Whether two sprites drawn into frames of their own light a common LED.
+pub fn collides(a: &Frame, b: &Frame) -> bool {
+    let (a, b) = (a.rows(), b.rows());
+    a.iter()
+        .flatten()
+        .zip(b.iter().flatten())
+        .any(|(&a, &b)| a > 0 && b > 0)
+}
+
+/// Small xorshift generator for where things appear, seed it from the RNG
+/// peripheral.
+#[derive(Debug, Clone, Copy)]
+pub struct Rng(u32);
+
+impl Rng {
+    pub const fn new(seed: u32) -> Rng {
+        // xorshift never leaves 0
+        Rng(if seed == 0 { 0x2545_f491 } else { seed })
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    /// A number from 0 to `n - 1`, `n` above 0.
+    pub fn below(&mut self, n: u32) -> u32 {
+        self.next_u32() % n
+    }
+}
+
+enum Phase {
+    Playing,
+    /// Scrolling the score.
+    Over,
+}
+
+/// Runs a [`Game`] round after round, one [`Runner::step`] per fixed time step.
+pub struct Runner<G: Game> {
+    game: G,
+    phase: Phase,
+    scroller: TextScroller<16, 1, 0>,
+    frame: Frame,
+}
+
+impl<G: Game> Runner<G> {
+    /// Starts a round of `game`.
+    pub fn new(mut game: G) -> Self {
+        game.reset();
+        let mut runner = Runner {
+            game,
+            phase: Phase::Playing,
+            scroller: TextScroller::new(),
+            frame: Frame::new(),
+        };
+        runner.scroller.set_repeat(0);
+        runner.draw();
+        runner
+    }
+
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// What the last step showed.
+    pub fn frame(&self) -> Frame {
+        self.frame
+    }
+
+    /// Whether a round is being played, as opposed to the score shown.
+    pub fn is_playing(&self) -> bool {
+        matches!(self.phase, Phase::Playing)
+    }
+
+    /// Move on by one step, returns what to show.
+    pub fn step(&mut self, input: &Input) -> Frame {
+        match self.phase {
+            Phase::Playing => {
+                if self.game.step(input) == Status::Over {
+                    self.game_over();
+                }
+            }
+            Phase::Over if input.a.pressed || input.b.pressed => {
+                self.game.reset();
+                self.phase = Phase::Playing;
+            }
+            Phase::Over => self.scroller.tick(),
+        }
+        self.draw();
+        self.frame
+    }
+
+    fn game_over(&mut self) {
+        // "Score " and at most 10 digits
+        let mut score = String::<16>::new();
+        write!(score, "Score {}", self.game.score()).unwrap();
+        self.scroller.show_text(&score).unwrap();
+        self.phase = Phase::Over;
+    }
+
+    fn draw(&mut self) {
+        self.frame = match self.phase {
+            Phase::Playing => {
+                let mut frame = Frame::new();
+                self.game.draw(&mut frame);
+                frame
+            }
+            Phase::Over => Frame::from_render(&self.scroller),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// Over after `length` steps with a point for each, lights a pixel per step.
+    struct Countdown {
+        length: u32,
+        steps: u32,
+        resets: u32,
+    }
+
+    impl Game for Countdown {
+        fn reset(&mut self) {
+            self.steps = 0;
+            self.resets += 1;
+        }
+
+        fn step(&mut self, _input: &Input) -> Status {
+            self.steps += 1;
+            if self.steps < self.length {
+                Status::Playing
+            } else {
+                Status::Over
+            }
+        }
+
+        fn draw(&self, frame: &mut Frame) {
+            frame.set_pixel(self.steps as i32, 0, 9);
+        }
+
+        fn score(&self) -> u32 {
+            self.steps
+        }
+    }
+
+    fn keys(input: &mut Input, a: bool, b: bool) -> &Input {
+        input.update(a, b, false, Tilt::default());
+        input
+    }
+
+    #[test]
+    fn key_presses() {
+        let mut key = Key::default();
+        let pressed: Vec<(bool, bool)> = [true, true, false, true, false, false]
+            .into_iter()
+            .map(|held| {
+                key.update(held);
+                (key.held, key.pressed)
+            })
+            .collect();
+        assert_eq!(
+            pressed,
+            [
+                (true, true),
+                (true, false),
+                (false, false),
+                (true, true),
+                (false, false),
+                (false, false)
+            ]
+        );
+
+        let mut input = Input::new();
+        input.update(true, false, true, Tilt::new(1, 2));
+        input.update(true, true, false, Tilt::new(3, 4));
+        assert!(input.a.held && !input.a.pressed);
+        assert!(input.b.held && input.b.pressed);
+        assert!(!input.logo.held && !input.logo.pressed);
+        assert_eq!(input.tilt, Tilt::new(3, 4));
+    }
+
+    #[test]
+    fn tilt_direction() {
+        let direction = |x, y| Tilt::new(x, y).direction(300);
+        assert_eq!(direction(0, 0), (0, 0));
+        assert_eq!(direction(299, -299), (0, 0));
+        // Left edge down rolls left, logo up rolls down the display
+        assert_eq!(direction(-300, 0), (-1, 0));
+        assert_eq!(direction(300, 0), (1, 0));
+        assert_eq!(direction(0, -300), (0, 1));
+        assert_eq!(direction(0, 1000), (0, -1));
+        assert_eq!(direction(-500, 500), (-1, -1));
+    }
+
+    #[test]
+    fn rects() {
+        let square = Rect::new(1, 1, 2, 2);
+        assert!(square.contains(2, 2) && !square.contains(3, 2) && !square.contains(0, 1));
+        assert!(square.overlaps(&Rect::point(2, 2)));
+        assert!(square.overlaps(&Rect::new(0, 0, 5, 5)));
+        // Touching edges don't overlap, neither does an empty rect
+        assert!(!square.overlaps(&Rect::point(3, 1)));
+        assert!(!square.overlaps(&Rect::point(1, 0)));
+        assert!(!square.overlaps(&Rect::new(1, 1, 0, 2)));
+        assert!(Rect::point(4, 4).overlaps(&Rect::point(4, 4)));
+
+        assert!(Rect::point(4, 4).is_visible());
+        assert!(Rect::new(-1, 0, 2, 1).is_visible());
+        assert!(!Rect::point(-1, 0).is_visible());
+        assert!(!Rect::point(0, 5).is_visible());
+
+        assert_eq!(square.moved_within(1, -1), Rect::new(2, 0, 2, 2));
+        assert_eq!(square.moved_within(5, 5), Rect::new(3, 3, 2, 2));
+        assert_eq!(square.moved_within(-5, 0), Rect::new(0, 1, 2, 2));
+        assert_eq!(
+            Rect::new(0, 0, 7, 1).moved_within(1, 9),
+            Rect::new(0, 4, 7, 1)
+        );
+    }
+
+    #[test]
+    fn collisions() {
+        let sprite = |x, y, b| *Frame::new().set_pixel(x, y, b);
+        assert!(collides(&sprite(1, 2, 9), &sprite(1, 2, 1)));
+        assert!(!collides(&sprite(1, 2, 9), &sprite(2, 1, 9)));
+        assert!(!collides(&sprite(1, 2, 9), &sprite(1, 2, 0)));
+        assert!(!collides(&Frame::new(), &Frame::new()));
+    }
+
+    #[test]
+    fn rng() {
+        let mut rng = Rng::new(0);
+        assert_ne!(rng.next_u32(), 0);
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        for _ in 0..100 {
+            let n = a.below(5);
+            assert!(n < 5);
+            assert_eq!(n, b.below(5));
+        }
+    }
+
+    #[test]
+    fn runner_rounds() {
+        let mut runner = Runner::new(Countdown {
+            length: 3,
+            steps: 0,
+            resets: 0,
+        });
+        let mut input = Input::new();
+        assert!(runner.is_playing());
+        assert_eq!(runner.game().resets, 1);
+        assert_eq!(runner.frame(), *Frame::new().set_pixel(0, 0, 9));
+
+        // Pressing a button while playing doesn't restart
+        runner.step(keys(&mut input, true, false));
+        assert_eq!(
+            runner.step(keys(&mut input, false, false)),
+            *Frame::new().set_pixel(2, 0, 9)
+        );
+        assert!(runner.is_playing());
+
+        // Then the score scrolls, again and again, even with A still held
+        let mut scroller = TextScroller::<16, 1, 0>::new();
+        scroller.set_repeat(0);
+        scroller.show_text("Score 3").unwrap();
+        assert_eq!(
+            runner.step(keys(&mut input, true, false)),
+            Frame::from_render(&scroller)
+        );
+        assert!(!runner.is_playing());
+        for _ in 0..100 {
+            scroller.tick();
+            assert_eq!(
+                runner.step(keys(&mut input, true, false)),
+                Frame::from_render(&scroller)
+            );
+        }
+        assert!(!runner.is_playing());
+        assert_eq!(runner.game().resets, 1);
+
+        // Until A or B goes down
+        runner.step(keys(&mut input, false, false));
+        assert_eq!(
+            runner.step(keys(&mut input, false, true)),
+            *Frame::new().set_pixel(0, 0, 9)
+        );
+        assert!(runner.is_playing());
+        assert_eq!(runner.game().resets, 2);
+        for _ in 0..3 {
+            runner.step(keys(&mut input, false, false));
+        }
+        assert!(!runner.is_playing());
+        runner.step(keys(&mut input, true, false));
+        assert!(runner.is_playing());
+        assert_eq!(runner.game().resets, 3);
+    }
+}
@@ -0,0 +1,404 @@
+//! Translated from <https://github.com/lancaster-university/codal-core/blob/master/source/driver-models/Accelerometer.cpp>
+//!
+//! Samples are expected in milli-g in the board frame used by MicroPython and MakeCode:
+//! tilting the left edge down reads negative x, holding the logo up reads negative y
+//! and lying face up reads negative z.
+
+use heapless::Deque;
+use lsm303agr::Measurement;
+
+const EVENT_QUEUE_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Gesture {
+    Shake,
+    LogoUp,
+    LogoDown,
+    TiltLeft,
+    TiltRight,
+    FaceUp,
+    FaceDown,
+    FreeFall,
+    ThreeG,
+    SixG,
+    EightG,
+}
+
+/// Thresholds in milli-g, damping values in samples.
+#[derive(Debug, Clone, Copy)]
+pub struct GestureConfig {
+    pub tilt_tolerance: i32,
+    pub freefall_tolerance: i32,
+    pub shake_tolerance: i32,
+    pub three_g_tolerance: i32,
+    pub six_g_tolerance: i32,
+    pub eight_g_tolerance: i32,
+    /// Samples a posture must be held before it is reported.
+    pub gesture_damping: u8,
+    /// Samples a shake is held for once detected.
+    pub shake_damping: u8,
+    /// Samples after which one zero crossing is forgotten.
+    pub shake_rtx: u8,
+    /// Zero crossings needed to report a shake.
+    pub shake_count_threshold: u8,
+}
+
+impl Default for GestureConfig {
+    fn default() -> GestureConfig {
+        GestureConfig {
+            tilt_tolerance: 200,
+            freefall_tolerance: 400,
+            shake_tolerance: 400,
+            three_g_tolerance: 3072,
+            six_g_tolerance: 6144,
+            eight_g_tolerance: 8192,
+            gesture_damping: 5,
+            shake_damping: 10,
+            shake_rtx: 30,
+            shake_count_threshold: 4,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ShakeState {
+    x: bool,
+    y: bool,
+    z: bool,
+    shaken: bool,
+    count: u8,
+    timer: u8,
+    impulse_3: bool,
+    impulse_6: bool,
+    impulse_8: bool,
+}
+
+pub struct GestureDetector {
+    config: GestureConfig,
+    shake: ShakeState,
+    current: Option<Gesture>,
+    last: Option<Gesture>,
+    sigma: u8,
+    impulse_sigma: u8,
+    events: Deque<Gesture, EVENT_QUEUE_SIZE>,
+}
+
+impl Default for GestureDetector {
+    fn default() -> GestureDetector {
+        GestureDetector::new(GestureConfig::default())
+    }
+}
+
+impl GestureDetector {
+    pub fn new(config: GestureConfig) -> Self {
+        GestureDetector {
+            config,
+            shake: ShakeState::default(),
+            current: None,
+            last: None,
+            sigma: 0,
+            impulse_sigma: 0,
+            events: Deque::new(),
+        }
+    }
+
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: GestureConfig) -> &mut Self {
+        self.config = config;
+        self
+    }
+
+    /// The last debounced gesture, `None` if the board is in no particular posture.
+    pub fn gesture(&self) -> Option<Gesture> {
+        self.last
+    }
+
+    /// Take the oldest gesture event raised by [`GestureDetector::update`].
+    pub fn next_event(&mut self) -> Option<Gesture> {
+        self.events.pop_front()
+    }
+
+    /// Feed one accelerometer sample, call it at the sensor output data rate.
+    pub fn update(&mut self, sample: Measurement) {
+        let config = self.config;
+        let force = force_squared(sample);
+
+        // High g impulses take priority over the posture and are not filtered
+        if force > squared(config.three_g_tolerance) {
+            if !self.shake.impulse_3 {
+                self.raise(Gesture::ThreeG);
+                self.shake.impulse_3 = true;
+            }
+            if force > squared(config.six_g_tolerance) && !self.shake.impulse_6 {
+                self.raise(Gesture::SixG);
+                self.shake.impulse_6 = true;
+            }
+            if force > squared(config.eight_g_tolerance) && !self.shake.impulse_8 {
+                self.raise(Gesture::EightG);
+                self.shake.impulse_8 = true;
+            }
+            self.impulse_sigma = 0;
+        }
+
+        // Reset the impulse events once the acceleration has subsided
+        if self.impulse_sigma < config.gesture_damping {
+            self.impulse_sigma += 1;
+        } else {
+            self.shake.impulse_3 = false;
+            self.shake.impulse_6 = false;
+            self.shake.impulse_8 = false;
+        }
+
+        let gesture = self.instantaneous_posture(sample, force);
+        if gesture == Some(Gesture::Shake) {
+            if self.last != Some(Gesture::Shake) {
+                self.last = Some(Gesture::Shake);
+                self.raise(Gesture::Shake);
+            }
+            return;
+        }
+
+        // Low pass filter the posture to reduce jitter
+        if gesture == self.current {
+            if self.sigma < config.gesture_damping {
+                self.sigma += 1;
+            }
+        } else {
+            self.current = gesture;
+            self.sigma = 0;
+        }
+
+        if self.current != self.last && self.sigma >= config.gesture_damping {
+            self.last = self.current;
+            if let Some(gesture) = self.current {
+                self.raise(gesture);
+            }
+        }
+    }
+
+    fn instantaneous_posture(&mut self, sample: Measurement, force: i64) -> Option<Gesture> {
+        let config = &self.config;
+        let shake = &mut self.shake;
+
+        // A shake is a succession of strong accelerations in opposite directions on any axis
+        let mut shake_detected = false;
+        for (value, direction) in [
+            (sample.x, &mut shake.x),
+            (sample.y, &mut shake.y),
+            (sample.z, &mut shake.z),
+        ] {
+            if (value < -config.shake_tolerance && *direction)
+                || (value > config.shake_tolerance && !*direction)
+            {
+                shake_detected = true;
+                *direction = !*direction;
+            }
+        }
+
+        if shake_detected && shake.count < config.shake_count_threshold {
+            shake.count += 1;
+            if shake.count == 1 {
+                shake.timer = 0;
+            }
+            if shake.count == config.shake_count_threshold {
+                shake.shaken = true;
+                shake.timer = 0;
+                return Some(Gesture::Shake);
+            }
+        }
+
+        if shake.count > 0 {
+            shake.timer += 1;
+            if shake.shaken && shake.timer >= config.shake_damping {
+                shake.shaken = false;
+                shake.timer = 0;
+                shake.count = 0;
+            } else if !shake.shaken && shake.timer >= config.shake_rtx {
+                shake.timer = 0;
+                shake.count -= 1;
+            }
+        }
+
+        if shake.shaken {
+            return Some(Gesture::Shake);
+        }
+
+        let level = 1000 - config.tilt_tolerance;
+        if force < squared(config.freefall_tolerance) {
+            Some(Gesture::FreeFall)
+        } else if sample.x < -level {
+            Some(Gesture::TiltLeft)
+        } else if sample.x > level {
+            Some(Gesture::TiltRight)
+        } else if sample.y < -level {
+            Some(Gesture::LogoUp)
+        } else if sample.y > level {
+            Some(Gesture::LogoDown)
+        } else if sample.z < -level {
+            Some(Gesture::FaceUp)
+        } else if sample.z > level {
+            Some(Gesture::FaceDown)
+        } else {
+            None
+        }
+    }
+
+    fn raise(&mut self, gesture: Gesture) {
+        // Drop the oldest event rather than the newest when nobody is listening
+        if self.events.is_full() {
+            self.events.pop_front();
+        }
+        self.events.push_back(gesture).ok();
+    }
+}
+
+fn force_squared(sample: Measurement) -> i64 {
+    let (x, y, z) = (sample.x as i64, sample.y as i64, sample.z as i64);
+    x * x + y * y + z * z
+}
+
+fn squared(tolerance: i32) -> i64 {
+    tolerance as i64 * tolerance as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAT: Measurement = Measurement {
+        x: 0,
+        y: 0,
+        z: -1000,
+    };
+
+    fn sample(x: i32, y: i32, z: i32) -> Measurement {
+        Measurement { x, y, z }
+    }
+
+    /// Feed `samples` and collect the events raised.
+    fn feed(detector: &mut GestureDetector, samples: &[Measurement]) -> Vec<Gesture> {
+        let mut events = Vec::new();
+        for &sample in samples {
+            detector.update(sample);
+            while let Some(event) = detector.next_event() {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn postures_are_debounced() {
+        let postures = [
+            (sample(0, 0, -1000), Gesture::FaceUp),
+            (sample(0, 0, 1000), Gesture::FaceDown),
+            (sample(-1000, 0, 0), Gesture::TiltLeft),
+            (sample(1000, 0, 0), Gesture::TiltRight),
+            (sample(0, -1000, 0), Gesture::LogoUp),
+            (sample(0, 1000, 0), Gesture::LogoDown),
+        ];
+        for (posture, gesture) in postures {
+            let mut detector = GestureDetector::default();
+            // The first sample and gesture_damping more
+            assert_eq!(feed(&mut detector, &[posture; 5]), []);
+            assert_eq!(detector.gesture(), None);
+            assert_eq!(feed(&mut detector, &[posture]), [gesture]);
+            assert_eq!(detector.gesture(), Some(gesture));
+            // Reported once while it is held
+            assert_eq!(feed(&mut detector, &[posture; 20]), []);
+        }
+    }
+
+    #[test]
+    fn short_posture_is_ignored() {
+        let mut detector = GestureDetector::default();
+        feed(&mut detector, &[FLAT; 6]);
+        let mut samples = vec![sample(1000, 0, 0); 5];
+        samples.extend([FLAT; 10]);
+        assert_eq!(feed(&mut detector, &samples), []);
+        assert_eq!(detector.gesture(), Some(Gesture::FaceUp));
+    }
+
+    #[test]
+    fn free_fall() {
+        let mut detector = GestureDetector::default();
+        feed(&mut detector, &[FLAT; 6]);
+        assert_eq!(
+            feed(&mut detector, &[sample(100, -50, 200); 6]),
+            [Gesture::FreeFall]
+        );
+    }
+
+    #[test]
+    fn shake_needs_enough_reversals() {
+        let mut detector = GestureDetector::default();
+        feed(&mut detector, &[FLAT; 6]);
+        let (left, right) = (sample(-900, 0, -1000), sample(900, 0, -1000));
+        assert_eq!(feed(&mut detector, &[right, left, right]), []);
+        assert_eq!(feed(&mut detector, &[left]), [Gesture::Shake]);
+        assert_eq!(detector.gesture(), Some(Gesture::Shake));
+
+        // Held for shake_damping samples, then the posture comes back
+        assert_eq!(feed(&mut detector, &[FLAT; 9]), []);
+        assert_eq!(detector.gesture(), Some(Gesture::Shake));
+        assert_eq!(feed(&mut detector, &[FLAT; 7]), [Gesture::FaceUp]);
+    }
+
+    #[test]
+    fn slow_reversals_are_forgotten() {
+        let mut detector = GestureDetector::default();
+        let (left, right) = (sample(-900, 0, -1000), sample(900, 0, -1000));
+        let mut samples = Vec::new();
+        for i in 0..8 {
+            samples.push(if i % 2 == 0 { right } else { left });
+            samples.extend([FLAT; 40]);
+        }
+        assert!(!feed(&mut detector, &samples).contains(&Gesture::Shake));
+    }
+
+    #[test]
+    fn impulses() {
+        let mut detector = GestureDetector::default();
+        feed(&mut detector, &[FLAT; 6]);
+        assert_eq!(
+            feed(&mut detector, &[sample(0, 0, 3500)]),
+            [Gesture::ThreeG]
+        );
+
+        let mut detector = GestureDetector::default();
+        feed(&mut detector, &[FLAT; 6]);
+        assert_eq!(
+            feed(&mut detector, &[sample(4000, 0, 5000)]),
+            [Gesture::ThreeG, Gesture::SixG]
+        );
+
+        let mut detector = GestureDetector::default();
+        feed(&mut detector, &[FLAT; 6]);
+        assert_eq!(
+            feed(&mut detector, &[sample(0, 0, 9000)]),
+            [Gesture::ThreeG, Gesture::SixG, Gesture::EightG]
+        );
+    }
+
+    #[test]
+    fn impulse_is_raised_once_until_it_subsides() {
+        let mut detector = GestureDetector::default();
+        feed(&mut detector, &[FLAT; 6]);
+        // Along gravity, so that it isn't a shake as well
+        let impulse = sample(0, 0, -3500);
+        assert_eq!(feed(&mut detector, &[impulse; 4]), [Gesture::ThreeG]);
+
+        // Too short a pause
+        let mut samples = vec![FLAT; 3];
+        samples.push(impulse);
+        assert_eq!(feed(&mut detector, &samples), []);
+
+        // gesture_damping quiet samples re-arm it
+        let mut samples = vec![FLAT; 5];
+        samples.push(impulse);
+        assert_eq!(feed(&mut detector, &samples), [Gesture::ThreeG]);
+    }
+}
@@ -0,0 +1,154 @@
+//! Named images matching the constants of MicroPython's `Image`.
+//!
+//! They are written in MicroPython's text format, so new ones can be drawn as
+//! text and pasted in:
+//!
+//! ```ignore
+//! display.show_frame(&MicrobitFrame::from(&images::HEART));
+//! ```
+
+use crate::frame::Frame;
+
+pub const HEART: Frame = Frame::literal("09090:99999:99999:09990:00900");
+pub const HEART_SMALL: Frame = Frame::literal("00000:09090:09990:00900:00000");
+pub const HAPPY: Frame = Frame::literal("00000:09090:00000:90009:09990");
+pub const SMILE: Frame = Frame::literal("00000:00000:00000:90009:09990");
+pub const SAD: Frame = Frame::literal("00000:09090:00000:09990:90009");
+pub const CONFUSED: Frame = Frame::literal("00000:09090:00000:09090:90909");
+pub const ANGRY: Frame = Frame::literal("90009:09090:00000:99999:90909");
+pub const ASLEEP: Frame = Frame::literal("00000:99099:00000:09990:00000");
+pub const SURPRISED: Frame = Frame::literal("09090:00000:00900:09090:00900");
+pub const SILLY: Frame = Frame::literal("90009:00000:99999:00909:00999");
+pub const FABULOUS: Frame = Frame::literal("99999:99099:00000:09090:09990");
+pub const MEH: Frame = Frame::literal("09090:00000:00090:00900:09000");
+pub const YES: Frame = Frame::literal("00000:00009:00090:90900:09000");
+pub const NO: Frame = Frame::literal("90009:09090:00900:09090:90009");
+pub const CLOCK12: Frame = Frame::literal("00900:00900:00900:00000:00000");
+pub const CLOCK1: Frame = Frame::literal("00090:00090:00900:00000:00000");
+pub const CLOCK2: Frame = Frame::literal("00000:00099:00900:00000:00000");
+pub const CLOCK3: Frame = Frame::literal("00000:00000:00999:00000:00000");
+pub const CLOCK4: Frame = Frame::literal("00000:00000:00900:00099:00000");
+pub const CLOCK5: Frame = Frame::literal("00000:00000:00900:00090:00090");
+pub const CLOCK6: Frame = Frame::literal("00000:00000:00900:00900:00900");
+pub const CLOCK7: Frame = Frame::literal("00000:00000:00900:09000:09000");
+pub const CLOCK8: Frame = Frame::literal("00000:00000:00900:99000:00000");
+pub const CLOCK9: Frame = Frame::literal("00000:00000:99900:00000:00000");
+pub const CLOCK10: Frame = Frame::literal("00000:99000:00900:00000:00000");
+pub const CLOCK11: Frame = Frame::literal("09000:09000:00900:00000:00000");
+pub const ARROW_N: Frame = Frame::literal("00900:09990:90909:00900:00900");
+pub const ARROW_NE: Frame = Frame::literal("00999:00099:00909:09000:90000");
+pub const ARROW_E: Frame = Frame::literal("00900:00090:99999:00090:00900");
+pub const ARROW_SE: Frame = Frame::literal("90000:09000:00909:00099:00999");
+pub const ARROW_S: Frame = Frame::literal("00900:00900:90909:09990:00900");
+pub const ARROW_SW: Frame = Frame::literal("00009:00090:90900:99000:99900");
+pub const ARROW_W: Frame = Frame::literal("00900:09000:99999:09000:00900");
+pub const ARROW_NW: Frame = Frame::literal("99900:99000:90900:00090:00009");
+pub const TRIANGLE: Frame = Frame::literal("00000:00900:09090:99999:00000");
+pub const TRIANGLE_LEFT: Frame = Frame::literal("90000:99000:90900:90090:99999");
+pub const CHESSBOARD: Frame = Frame::literal("09090:90909:09090:90909:09090");
+pub const DIAMOND: Frame = Frame::literal("00900:09090:90009:09090:00900");
+pub const DIAMOND_SMALL: Frame = Frame::literal("00000:00900:09090:00900:00000");
+pub const SQUARE: Frame = Frame::literal("99999:90009:90009:90009:99999");
+pub const SQUARE_SMALL: Frame = Frame::literal("00000:09990:09090:09990:00000");
+pub const RABBIT: Frame = Frame::literal("90900:90900:99990:99090:99990");
+pub const COW: Frame = Frame::literal("90009:90009:99999:09990:00900");
+pub const MUSIC_CROTCHET: Frame = Frame::literal("00900:00900:00900:99900:99900");
+pub const MUSIC_QUAVER: Frame = Frame::literal("00900:00990:00909:99900:99900");
+pub const MUSIC_QUAVERS: Frame = Frame::literal("09999:09009:09009:99099:99099");
+pub const PITCHFORK: Frame = Frame::literal("90909:90909:99999:00900:00900");
+pub const XMAS: Frame = Frame::literal("00900:09990:00900:09990:99999");
+pub const PACMAN: Frame = Frame::literal("09999:99090:99900:99990:09999");
+pub const TARGET: Frame = Frame::literal("00900:09990:99099:09990:00900");
+pub const TSHIRT: Frame = Frame::literal("99099:99999:09990:09990:09990");
+pub const ROLLERSKATE: Frame = Frame::literal("00099:00099:99999:99999:09090");
+pub const DUCK: Frame = Frame::literal("09900:99900:09999:09990:00000");
+pub const HOUSE: Frame = Frame::literal("00900:09990:99999:09990:09090");
+pub const TORTOISE: Frame = Frame::literal("00000:09990:99999:09090:00000");
+pub const BUTTERFLY: Frame = Frame::literal("99099:99999:00900:99999:99099");
+pub const STICKFIGURE: Frame = Frame::literal("00900:99999:00900:09090:90009");
+pub const GHOST: Frame = Frame::literal("99999:90909:99999:99999:90909");
+pub const SWORD: Frame = Frame::literal("00900:00900:00900:09990:00900");
+pub const GIRAFFE: Frame = Frame::literal("99000:09000:09000:09990:09090");
+pub const SKULL: Frame = Frame::literal("09990:90909:99999:09990:09990");
+pub const UMBRELLA: Frame = Frame::literal("09990:99999:00900:90900:09900");
+pub const SNAKE: Frame = Frame::literal("99000:99099:09090:09990:00000");
+pub const SCISSORS: Frame = Frame::literal("99009:99090:00900:99090:99009");
+
+/// Clock faces from 12 o'clock on, an hour apart.
+pub const ALL_CLOCKS: [Frame; 12] = [
+    CLOCK12, CLOCK1, CLOCK2, CLOCK3, CLOCK4, CLOCK5, CLOCK6, CLOCK7, CLOCK8, CLOCK9, CLOCK10,
+    CLOCK11,
+];
+
+/// Arrows from north on, clockwise.
+pub const ALL_ARROWS: [Frame; 8] = [
+    ARROW_N, ARROW_NE, ARROW_E, ARROW_SE, ARROW_S, ARROW_SW, ARROW_W, ARROW_NW,
+];
+
+/// Every image with its name.
+pub const ALL: &[(&str, Frame)] = &[
+    ("HEART", HEART),
+    ("HEART_SMALL", HEART_SMALL),
+    ("HAPPY", HAPPY),
+    ("SMILE", SMILE),
+    ("SAD", SAD),
+    ("CONFUSED", CONFUSED),
+    ("ANGRY", ANGRY),
+    ("ASLEEP", ASLEEP),
+    ("SURPRISED", SURPRISED),
+    ("SILLY", SILLY),
+    ("FABULOUS", FABULOUS),
+    ("MEH", MEH),
+    ("YES", YES),
+    ("NO", NO),
+    ("CLOCK12", CLOCK12),
+    ("CLOCK1", CLOCK1),
+    ("CLOCK2", CLOCK2),
+    ("CLOCK3", CLOCK3),
+    ("CLOCK4", CLOCK4),
+    ("CLOCK5", CLOCK5),
+    ("CLOCK6", CLOCK6),
+    ("CLOCK7", CLOCK7),
+    ("CLOCK8", CLOCK8),
+    ("CLOCK9", CLOCK9),
+    ("CLOCK10", CLOCK10),
+    ("CLOCK11", CLOCK11),
+    ("ARROW_N", ARROW_N),
+    ("ARROW_NE", ARROW_NE),
+    ("ARROW_E", ARROW_E),
+    ("ARROW_SE", ARROW_SE),
+    ("ARROW_S", ARROW_S),
+    ("ARROW_SW", ARROW_SW),
+    ("ARROW_W", ARROW_W),
+    ("ARROW_NW", ARROW_NW),
+    ("TRIANGLE", TRIANGLE),
+    ("TRIANGLE_LEFT", TRIANGLE_LEFT),
+    ("CHESSBOARD", CHESSBOARD),
+    ("DIAMOND", DIAMOND),
+    ("DIAMOND_SMALL", DIAMOND_SMALL),
+    ("SQUARE", SQUARE),
+    ("SQUARE_SMALL", SQUARE_SMALL),
+    ("RABBIT", RABBIT),
+    ("COW", COW),
+    ("MUSIC_CROTCHET", MUSIC_CROTCHET),
+    ("MUSIC_QUAVER", MUSIC_QUAVER),
+    ("MUSIC_QUAVERS", MUSIC_QUAVERS),
+    ("PITCHFORK", PITCHFORK),
+    ("XMAS", XMAS),
+    ("PACMAN", PACMAN),
+    ("TARGET", TARGET),
+    ("TSHIRT", TSHIRT),
+    ("ROLLERSKATE", ROLLERSKATE),
+    ("DUCK", DUCK),
+    ("HOUSE", HOUSE),
+    ("TORTOISE", TORTOISE),
+    ("BUTTERFLY", BUTTERFLY),
+    ("STICKFIGURE", STICKFIGURE),
+    ("GHOST", GHOST),
+    ("SWORD", SWORD),
+    ("GIRAFFE", GIRAFFE),
+    ("SKULL", SKULL),
+    ("UMBRELLA", UMBRELLA),
+    ("SNAKE", SNAKE),
+    ("SCISSORS", SCISSORS),
+];
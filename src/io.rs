@@ -0,0 +1,166 @@
+//! Formatting helpers for `embedded_io` writers such as
+//! [`UartePort`](crate::serial_setup::UartePort).
+//!
+//! [`write_flush!`](crate::write_flush) and [`writeln_flush!`](crate::writeln_flush)
+//! format, send and wait for the output to leave, returning errors instead of
+//! panicking:
+//!
+//! ```ignore
+//! writeln_flush!(serial, "x {} mg", accel.x)?;
+//! writeln_flush!(serial, "x {} g", Fixed::new(accel.x, 3))?;
+//! ```
+
+use core::fmt::{self, Write};
+
+use embedded_io::WriteFmtError;
+use heapless::String;
+
+/// Format `args`, then block until the output is sent.
+pub fn write_flush<W: embedded_io::Write>(
+    w: &mut W,
+    args: fmt::Arguments,
+) -> Result<(), WriteFmtError<W::Error>> {
+    w.write_fmt(args)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Format `args` followed by CRLF, then block until the output is sent.
+pub fn writeln_flush<W: embedded_io::Write>(
+    w: &mut W,
+    args: fmt::Arguments,
+) -> Result<(), WriteFmtError<W::Error>> {
+    w.write_fmt(args)?;
+    w.write_all(b"\r\n")?;
+    w.flush()?;
+    Ok(())
+}
+
+/// `write!` for `embedded_io` writers that also waits for the output to be sent.
+#[macro_export]
+macro_rules! write_flush {
+    ($dst:expr, $($arg:tt)*) => {
+        $crate::io::write_flush(&mut $dst, format_args!($($arg)*))
+    };
+}
+
+/// `writeln!` for `embedded_io` writers, ending lines with CRLF for terminals,
+/// that also waits for the output to be sent.
+#[macro_export]
+macro_rules! writeln_flush {
+    ($dst:expr) => {
+        $crate::io::writeln_flush(&mut $dst, format_args!(""))
+    };
+    ($dst:expr, $($arg:tt)*) => {
+        $crate::io::writeln_flush(&mut $dst, format_args!($($arg)*))
+    };
+}
+
+/// `core::fmt::Write` for an `embedded_io` writer, for code written against
+/// `fmt::Write`. The error behind a failed write is kept.
+pub struct FmtWriter<W: embedded_io::Write> {
+    inner: W,
+    error: Option<W::Error>,
+}
+
+impl<W: embedded_io::Write> FmtWriter<W> {
+    pub fn new(inner: W) -> Self {
+        FmtWriter { inner, error: None }
+    }
+
+    /// The error that made the last write fail.
+    pub fn take_error(&mut self) -> Option<W::Error> {
+        self.error.take()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: embedded_io::Write> Write for FmtWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Fixed point number shown with `decimals` digits after the point, e.g. a
+/// reading in mg as g with `Fixed::new(-1024, 3)`, shown as `-1.024`.
+///
+/// Width and alignment flags apply to the whole number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixed {
+    value: i32,
+    decimals: u8,
+}
+
+impl Fixed {
+    /// `decimals` is capped at 9, the most an `i32` has.
+    pub const fn new(value: i32, decimals: u8) -> Self {
+        Fixed {
+            value,
+            decimals: if decimals > 9 { 9 } else { decimals },
+        }
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = 10_u32.pow(self.decimals as u32);
+        let value = self.value.unsigned_abs();
+        // Sign, 10 digits and the point
+        let mut s = String::<12>::new();
+        if self.value < 0 {
+            s.push('-').ok();
+        }
+        write!(s, "{}", value / scale)?;
+        if self.decimals > 0 {
+            write!(
+                s,
+                ".{:0width$}",
+                value % scale,
+                width = self.decimals as usize
+            )?;
+        }
+        f.pad(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::format;
+
+    use super::*;
+
+    #[test]
+    fn fixed_point() {
+        assert_eq!(format!("{}", Fixed::new(-1024, 3)), "-1.024");
+        assert_eq!(format!("{}", Fixed::new(1024, 3)), "1.024");
+        assert_eq!(format!("{}", Fixed::new(5, 3)), "0.005");
+        assert_eq!(format!("{}", Fixed::new(-5, 3)), "-0.005");
+        assert_eq!(format!("{}", Fixed::new(-50, 1)), "-5.0");
+        assert_eq!(format!("{}", Fixed::new(0, 0)), "0");
+        assert_eq!(format!("{}", Fixed::new(-42, 0)), "-42");
+    }
+
+    #[test]
+    fn fixed_point_limits() {
+        assert_eq!(format!("{}", Fixed::new(i32::MIN, 0)), "-2147483648");
+        // The longest there is fills the buffer
+        assert_eq!(format!("{}", Fixed::new(i32::MIN, 9)), "-2.147483648");
+        assert_eq!(format!("{}", Fixed::new(i32::MAX, 9)), "2.147483647");
+        assert_eq!(Fixed::new(1, 20), Fixed::new(1, 9));
+        assert_eq!(format!("{}", Fixed::new(1, 20)), "0.000000001");
+    }
+
+    #[test]
+    fn fixed_point_padding() {
+        assert_eq!(format!("{:>8}|", Fixed::new(-15, 1)), "    -1.5|");
+        assert_eq!(format!("{:<8}|", Fixed::new(-15, 1)), "-1.5    |");
+        assert_eq!(format!("{:^6}|", Fixed::new(15, 1)), " 1.5  |");
+        assert_eq!(format!("{:2}", Fixed::new(12345, 2)), "123.45");
+    }
+}
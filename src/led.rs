@@ -10,6 +10,23 @@ pub enum Direction {
     NorthWest,
 }
 
+impl Direction {
+    /// The closest of the eight directions to a heading in degrees, clockwise from north.
+    pub fn from_heading(heading: f32) -> Direction {
+        let sector = libm::roundf(heading / 45.0) as i32;
+        match sector.rem_euclid(8) {
+            0 => Direction::North,
+            1 => Direction::NorthEast,
+            2 => Direction::East,
+            3 => Direction::SouthEast,
+            4 => Direction::South,
+            5 => Direction::SouthWest,
+            6 => Direction::West,
+            _ => Direction::NorthWest,
+        }
+    }
+}
+
 const NORTH: [[u8; 5]; 5] = [
     [0, 0, 1, 0, 0],
     [0, 1, 1, 1, 0],
@@ -1,10 +1,36 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
+#[cfg(not(test))]
 use defmt_rtt as _;
+#[cfg(not(test))]
 use panic_probe as _;
 
+pub mod accel_fifo;
+pub mod animation;
+pub mod buttons;
 pub mod calibration;
+pub mod console;
+pub mod declination;
+pub mod display;
+pub mod dodge;
+pub mod font;
+pub mod frame;
+pub mod framing;
+pub mod game;
+pub mod gesture;
+pub mod images;
+pub mod io;
 pub mod led;
+pub mod light;
 pub mod music;
+pub mod pedometer;
+pub mod rpc;
+pub mod rtc_monotonic;
+pub mod scroller;
 pub mod serial_setup;
+pub mod shell;
+pub mod telemetry;
+pub mod timers;
+pub mod touch;
+pub mod waker;
 pub mod monotonic;
\ No newline at end of file
@@ -0,0 +1,397 @@
+//! Ambient light level measured with the LEDs of the display.
+//!
+//! An LED held in reverse, its row low and its column high, charges like a small
+//! capacitor that light discharges. [`LightSensor`] borrows one row slot of
+//! `display::nonblocking::Display` every so often: at a row change it charges
+//! the columns and lets three of them float, and at the next row change, 6ms
+//! later, reads what is left of the charge on the analog inputs of columns 1, 3
+//! and 5 before handing the pins back to the display. [`LightSchedule`] does the
+//! timing, counting the display's row changes.
+//!
+//! ```ignore
+//! let mut light = LightSensor::new(board.SAADC, LightConfig::default());
+//!
+//! // in the display's timer interrupt, instead of display.handle_display_event()
+//! if let Some(level) = light.handle_display_event(&mut display) {
+//!     defmt::info!("light {}", level);
+//! }
+//! ```
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use microbit::{
+    display::nonblocking::Display,
+    hal::timer::Instance,
+    pac::{self, saadc::ch::pselp::PSELP_A},
+};
+
+use crate::frame::{Frame, MAX_BRIGHTNESS};
+use crate::monotonic::Instance32;
+
+/// Display rows, all on port 0.
+const ROW_PINS: [usize; 5] = [21, 22, 15, 24, 19];
+/// Display columns on port 0, column 4 is P1.05.
+const P0_COL_PINS: [usize; 4] = [28, 11, 31, 30];
+const P1_COL_PIN: usize = 5;
+/// Columns 1, 3 and 5 with their analog inputs.
+const SENSE_COLUMNS: [(usize, PSELP_A); 3] = [
+    (28, PSELP_A::ANALOGINPUT4),
+    (31, PSELP_A::ANALOGINPUT7),
+    (30, PSELP_A::ANALOGINPUT6),
+];
+/// About 20µs at 64MHz for the columns to charge.
+const CHARGE_CYCLES: u32 = 1280;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LightConfig {
+    /// Display rows of 6ms between two readings.
+    pub interval: u16,
+    /// Reading in the dark, 10 bits of the supply voltage.
+    pub dark: i16,
+    /// Reading in bright light.
+    pub bright: i16,
+    /// Dim frames passed to [`LightSensor::dim`] in the dark.
+    pub auto_brightness: bool,
+}
+
+impl Default for LightConfig {
+    /// A reading about every second. The levels are rough, log
+    /// [`LightSensor::raw`] to calibrate a board.
+    fn default() -> Self {
+        LightConfig {
+            interval: 160,
+            dark: 900,
+            bright: 300,
+            auto_brightness: false,
+        }
+    }
+}
+
+/// What to do on a display timer event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Action {
+    /// Let the display handle it.
+    Display,
+    /// Skip the row, charge the columns and let them float.
+    Charge,
+    /// Ignore a brightness event of the skipped row.
+    Skip,
+    /// Read the columns and give the pins back to the display.
+    Sample,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Rows left before the next reading.
+    Waiting(u16),
+    Sensing,
+}
+
+/// When to take the display's pins, a reading every `interval` rows.
+pub struct LightSchedule {
+    interval: u16,
+    phase: Phase,
+}
+
+impl LightSchedule {
+    /// The first reading is taken on the first row change.
+    pub const fn new(interval: u16) -> LightSchedule {
+        LightSchedule {
+            interval,
+            phase: Phase::Waiting(0),
+        }
+    }
+
+    /// What to do on a timer event, `row` if it is a row change.
+    pub fn on_event(&mut self, row: bool) -> Action {
+        match (self.phase, row) {
+            (Phase::Waiting(0), true) => {
+                self.phase = Phase::Sensing;
+                Action::Charge
+            }
+            (Phase::Waiting(rows), true) => {
+                self.phase = Phase::Waiting(rows - 1);
+                Action::Display
+            }
+            (Phase::Waiting(_), false) => Action::Display,
+            (Phase::Sensing, true) => {
+                self.phase = Phase::Waiting(self.interval);
+                Action::Sample
+            }
+            (Phase::Sensing, false) => Action::Skip,
+        }
+    }
+
+    pub fn is_sensing(&self) -> bool {
+        self.phase == Phase::Sensing
+    }
+}
+
+/// Light level from 0, dark, to 255 of a `raw` reading.
+pub fn level(raw: i16, config: &LightConfig) -> u8 {
+    let (dark, bright) = (i32::from(config.dark), i32::from(config.bright));
+    if dark == bright {
+        return 0;
+    }
+    ((dark - i32::from(raw)) * 255 / (dark - bright)).clamp(0, 255) as u8
+}
+
+/// Brightness for full brightness LEDs at a light `level`, from 1 to
+/// [`MAX_BRIGHTNESS`].
+pub fn auto_brightness(level: u8) -> u8 {
+    1 + (u16::from(level) * u16::from(MAX_BRIGHTNESS - 1) / 255) as u8
+}
+
+/// Reads the light level in between the display's rows.
+pub struct LightSensor {
+    saadc: pac::SAADC,
+    config: LightConfig,
+    schedule: LightSchedule,
+    raw: Option<i16>,
+}
+
+impl LightSensor {
+    pub fn new(saadc: pac::SAADC, config: LightConfig) -> LightSensor {
+        saadc.enable.write(|w| w.enable().enabled());
+        saadc.resolution.write(|w| w.val()._10bit());
+        saadc.oversample.write(|w| w.oversample().bypass());
+        saadc.samplerate.write(|w| w.mode().task());
+        // Full scale at the supply voltage the columns are charged to
+        saadc.ch[0].config.write(|w| {
+            w.refsel().vdd1_4();
+            w.gain().gain1_4();
+            w.tacq()._10us();
+            w.mode().se();
+            w.resp().bypass();
+            w.resn().bypass();
+            w.burst().disabled();
+            w
+        });
+        saadc.ch[0].pseln.write(|w| w.pseln().nc());
+
+        saadc.events_calibratedone.reset();
+        saadc.tasks_calibrateoffset.write(|w| unsafe { w.bits(1) });
+        while saadc.events_calibratedone.read().bits() == 0 {}
+
+        LightSensor {
+            saadc,
+            config,
+            schedule: LightSchedule::new(config.interval),
+            raw: None,
+        }
+    }
+
+    /// Handle the display's timer interrupt, returns the light level when a new
+    /// reading is taken.
+    pub fn handle_display_event<T>(&mut self, display: &mut Display<T>) -> Option<u8>
+    where
+        T: Instance + Instance32,
+    {
+        let timer = unsafe { &*T::ptr() };
+        let row = timer.events_compare[0].read().bits() != 0;
+        match self.schedule.on_event(row) {
+            Action::Display => display.handle_display_event(),
+            Action::Charge => {
+                timer.events_compare[0].reset();
+                timer.events_compare[1].reset();
+                charge();
+            }
+            Action::Skip => timer.events_compare[1].reset(),
+            Action::Sample => {
+                self.raw = Some(self.sample());
+                release();
+                // Show the row that is due now
+                display.handle_display_event();
+                return self.level();
+            }
+        }
+        None
+    }
+
+    /// Last reading, 10 bits of the supply voltage left on the columns.
+    pub fn raw(&self) -> Option<i16> {
+        self.raw
+    }
+
+    /// Last light level from 0, dark, to 255.
+    pub fn level(&self) -> Option<u8> {
+        self.raw.map(|raw| level(raw, &self.config))
+    }
+
+    /// `frame` dimmed for the light level if auto brightness is on.
+    pub fn dim(&self, mut frame: Frame) -> Frame {
+        if let (true, Some(level)) = (self.config.auto_brightness, self.level()) {
+            frame.scale(auto_brightness(level));
+        }
+        frame
+    }
+
+    /// Average of the sense columns.
+    fn sample(&mut self) -> i16 {
+        let total: i32 = SENSE_COLUMNS
+            .iter()
+            .map(|&(_, input)| i32::from(self.read(input)))
+            .sum();
+        (total / SENSE_COLUMNS.len() as i32) as i16
+    }
+
+    fn read(&mut self, input: PSELP_A) -> i16 {
+        let saadc = &self.saadc;
+        saadc.ch[0].pselp.write(|w| w.pselp().variant(input));
+        let mut value: i16 = 0;
+        saadc
+            .result
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(&mut value as *mut i16 as u32) });
+        saadc.result.maxcnt.write(|w| unsafe { w.maxcnt().bits(1) });
+        // The pointer has to be set before the ADC starts writing through it
+        compiler_fence(Ordering::SeqCst);
+
+        saadc.tasks_start.write(|w| unsafe { w.bits(1) });
+        saadc.tasks_sample.write(|w| unsafe { w.bits(1) });
+        while saadc.events_end.read().bits() == 0 {}
+        saadc.events_end.reset();
+        compiler_fence(Ordering::SeqCst);
+        value
+    }
+}
+
+/// Hold the LEDs in reverse, charge the columns and let the sense columns float.
+fn charge() {
+    let (p0, p1) = unsafe { (&*pac::P0::ptr(), &*pac::P1::ptr()) };
+    let rows = ROW_PINS.iter().fold(0, |bits, pin| bits | 1 << pin);
+    let cols = P0_COL_PINS.iter().fold(0, |bits, pin| bits | 1 << pin);
+    p0.outclr.write(|w| unsafe { w.bits(rows) });
+    p0.outset.write(|w| unsafe { w.bits(cols) });
+    p1.outset.write(|w| unsafe { w.bits(1 << P1_COL_PIN) });
+    cortex_m::asm::delay(CHARGE_CYCLES);
+    for &(pin, _) in SENSE_COLUMNS.iter() {
+        p0.pin_cnf[pin].write(|w| w.dir().input().input().disconnect());
+    }
+}
+
+/// Give the sense columns back to the display, high like the display leaves
+/// unlit columns.
+fn release() {
+    let p0 = unsafe { &*pac::P0::ptr() };
+    for &(pin, _) in SENSE_COLUMNS.iter() {
+        p0.outset.write(|w| unsafe { w.bits(1 << pin) });
+        p0.pin_cnf[pin].write(|w| w.dir().output());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Actions on row changes, each followed by a brightness event.
+    fn rows(schedule: &mut LightSchedule, count: usize) -> std::vec::Vec<(Action, Action)> {
+        (0..count)
+            .map(|_| (schedule.on_event(true), schedule.on_event(false)))
+            .collect()
+    }
+
+    #[test]
+    fn first_reading() {
+        let mut schedule = LightSchedule::new(3);
+        assert_eq!(schedule.on_event(false), Action::Display);
+        assert!(!schedule.is_sensing());
+        assert_eq!(schedule.on_event(true), Action::Charge);
+        assert!(schedule.is_sensing());
+    }
+
+    #[test]
+    fn skip_while_sensing() {
+        let mut schedule = LightSchedule::new(3);
+        schedule.on_event(true);
+        assert_eq!(schedule.on_event(false), Action::Skip);
+        assert_eq!(schedule.on_event(false), Action::Skip);
+        assert!(schedule.is_sensing());
+        assert_eq!(schedule.on_event(true), Action::Sample);
+        assert!(!schedule.is_sensing());
+    }
+
+    #[test]
+    fn waits_interval_rows() {
+        use Action::*;
+
+        let mut schedule = LightSchedule::new(3);
+        assert_eq!(
+            rows(&mut schedule, 10),
+            [
+                (Charge, Skip),
+                (Sample, Display),
+                (Display, Display),
+                (Display, Display),
+                (Display, Display),
+                (Charge, Skip),
+                (Sample, Display),
+                (Display, Display),
+                (Display, Display),
+                (Display, Display),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_interval() {
+        use Action::*;
+
+        let mut schedule = LightSchedule::new(0);
+        assert_eq!(
+            rows(&mut schedule, 4),
+            [
+                (Charge, Skip),
+                (Sample, Display),
+                (Charge, Skip),
+                (Sample, Display)
+            ]
+        );
+    }
+
+    #[test]
+    fn levels() {
+        let config = LightConfig::default();
+        assert_eq!(level(900, &config), 0);
+        assert_eq!(level(600, &config), 127);
+        assert_eq!(level(300, &config), 255);
+        // Clamped beyond the calibration
+        assert_eq!(level(1000, &config), 0);
+        assert_eq!(level(-5, &config), 255);
+
+        let rising = LightConfig {
+            dark: 100,
+            bright: 500,
+            ..config
+        };
+        assert_eq!(level(300, &rising), 127);
+        assert_eq!(level(600, &rising), 255);
+
+        let flat = LightConfig {
+            dark: 500,
+            bright: 500,
+            ..config
+        };
+        assert_eq!(level(500, &flat), 0);
+        assert_eq!(level(i16::MIN, &flat), 0);
+    }
+
+    #[test]
+    fn extreme_levels() {
+        let config = LightConfig {
+            dark: i16::MAX,
+            bright: i16::MIN,
+            ..LightConfig::default()
+        };
+        assert_eq!(level(i16::MAX, &config), 0);
+        assert_eq!(level(i16::MIN, &config), 255);
+    }
+
+    #[test]
+    fn auto_brightness_range() {
+        assert_eq!(auto_brightness(0), 1);
+        assert_eq!(auto_brightness(128), 5);
+        assert_eq!(auto_brightness(254), MAX_BRIGHTNESS - 1);
+        assert_eq!(auto_brightness(255), MAX_BRIGHTNESS);
+    }
+}
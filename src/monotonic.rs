@@ -1,9 +1,38 @@
+//! 1 MHz timebase on a 32-bit TIMER, extended to 64 bits.
+//!
+//! The counter wraps about every 71 minutes. A compare channel fires at each half
+//! of the counter range and the halves gone by are counted, which together with
+//! the counter gives instants that don't wrap. The halves are counted in the
+//! timer interrupt and by [`MonoTimer`]'s and [`Delay`]'s `now`, so code polling
+//! the timer without interrupts has to read the time at least every half range,
+//! about 35 minutes.
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
 use core::ops::Deref;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::Poll;
 
-pub use fugit::{self, ExtU32};
+use cortex_m::interrupt;
+pub use fugit::{self, ExtU64};
 use microbit::pac::{timer0, TIMER0, TIMER1, TIMER2, TIMER3, TIMER4};
 use rtic_monotonic::Monotonic;
 
+use crate::waker::WakerCell;
+
+pub type Instant = fugit::TimerInstantU64<1_000_000>;
+pub type Duration = fugit::TimerDurationU64<1_000_000>;
+
+/// Compare channel RTIC schedules tasks on, used by a [`Delay`] without RTIC.
+const SCHEDULE_CHANNEL: usize = 0;
+/// Compare channel the current time is captured into.
+const NOW_CHANNEL: usize = 1;
+/// Compare channels of the two [`Delay`]s.
+const DELAY_CHANNELS: [usize; 2] = [2, SCHEDULE_CHANNEL];
+/// Compare channel firing at each half of the counter range.
+const OVERFLOW_CHANNEL: usize = 3;
+const HALF: u32 = 1 << 31;
+
 pub struct MonoTimer<T: Instance32>(T);
 
 impl<T: Instance32> MonoTimer<T> {
@@ -14,41 +43,375 @@ impl<T: Instance32> MonoTimer<T> {
         timer.bitmode.write(|w| w.bitmode()._32bit());
         MonoTimer(timer)
     }
+
+    /// Start the timer and split it into two independent async delays, for use
+    /// from an executor instead of RTIC.
+    ///
+    /// The timer interrupt has to be bound to [`wake`] and unmasked.
+    pub fn into_delays(self) -> (Delay<T>, Delay<T>) {
+        start::<T>(&self.0);
+        (Delay::new(0), Delay::new(1))
+    }
 }
 
 impl<T: Instance32> Monotonic for MonoTimer<T> {
-    type Instant = fugit::TimerInstantU32<1_000_000>;
-    type Duration = fugit::TimerDurationU32<1_000_000>;
+    type Instant = Instant;
+    type Duration = Duration;
+
+    // Halves of the counter range are counted even with no task scheduled
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
 
     unsafe fn reset(&mut self) {
         self.0.intenset.modify(|_, w| w.compare0().set());
-        self.0.tasks_clear.write(|w| w.bits(1));
-        self.0.tasks_start.write(|w| w.bits(1));
+        start::<T>(&self.0);
     }
 
     #[inline(always)]
     fn now(&mut self) -> Self::Instant {
-        self.0.tasks_capture[1].write(|w| unsafe { w.bits(1) });
-        Self::Instant::from_ticks(self.0.cc[1].read().bits())
+        now::<T>()
     }
 
+    /// Instants more than a counter range away fire early, RTIC checks the time
+    /// again and sets the compare anew.
     fn set_compare(&mut self, instant: Self::Instant) {
-        self.0.cc[0].write(|w| unsafe { w.cc().bits(instant.duration_since_epoch().ticks()) });
+        self.0.cc[SCHEDULE_CHANNEL].write(|w| unsafe { w.cc().bits(instant.ticks() as u32) });
     }
 
     fn clear_compare_flag(&mut self) {
-        self.0.events_compare[0].write(|w| w);
+        self.0.events_compare[SCHEDULE_CHANNEL].write(|w| w);
     }
 
     #[inline(always)]
     fn zero() -> Self::Instant {
         Self::Instant::from_ticks(0)
     }
+
+    fn on_interrupt(&mut self) {
+        interrupt::free(|_| count_half::<T>(&self.0));
+    }
+}
+
+/// Async delay on one compare channel of a running [`MonoTimer`].
+///
+/// ```ignore
+/// let (mut delay, _) = MonoTimer::new(board.TIMER2).into_delays();
+/// unsafe { NVIC::unmask(Interrupt::TIMER2) };
+///
+/// #[interrupt]
+/// fn TIMER2() {
+///     monotonic::wake::<pac::TIMER2>();
+/// }
+///
+/// delay.after(500.millis()).await;
+/// ```
+pub struct Delay<T: Instance32> {
+    /// Which of the two delays of the timer this is.
+    index: usize,
+    _timer: PhantomData<T>,
+}
+
+impl<T: Instance32> Delay<T> {
+    fn new(index: usize) -> Self {
+        Delay {
+            index,
+            _timer: PhantomData,
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        now::<T>()
+    }
+
+    /// Wait until `instant`, returns at once if it has passed.
+    pub async fn until(&mut self, instant: Instant) {
+        poll_fn(|cx| {
+            T::state().wakers[self.index].register(cx.waker());
+            if self.arm(instant) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Wait for `duration`.
+    pub async fn after(&mut self, duration: Duration) {
+        let instant = self.now() + duration;
+        self.until(instant).await
+    }
+
+    /// Raise the timer interrupt at `instant`. Returns true, leaving the
+    /// interrupt off, if `instant` has passed already.
+    pub(crate) fn arm(&mut self, instant: Instant) -> bool {
+        let timer = unsafe { &*T::ptr() };
+        let channel = DELAY_CHANNELS[self.index];
+        interrupt::free(|_| {
+            // Instants more than a counter range away fire early and are set again
+            timer.cc[channel].write(|w| unsafe { w.bits(instant.ticks() as u32) });
+            timer.events_compare[channel].write(|w| w);
+            timer
+                .intenset
+                .write(|w| unsafe { w.bits(compare_mask(channel)) });
+            // The compare event is missed if the time passed before it was set
+            if now::<T>() >= instant {
+                self.disarm();
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    pub(crate) fn disarm(&mut self) {
+        let timer = unsafe { &*T::ptr() };
+        let channel = DELAY_CHANNELS[self.index];
+        timer
+            .intenclr
+            .write(|w| unsafe { w.bits(compare_mask(channel)) });
+        timer.events_compare[channel].write(|w| w);
+    }
+}
+
+impl<T: Instance32> embedded_hal_async::delay::DelayNs for Delay<T> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.after(Duration::micros(ns.div_ceil(1_000) as u64))
+            .await
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        self.after(Duration::micros(us as u64)).await
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        self.after(Duration::millis(ms as u64)).await
+    }
+}
+
+/// Wake the [`Delay`]s of `T` that are due, called from the timer interrupt.
+pub fn wake<T: Instance32>() {
+    let timer = unsafe { &*T::ptr() };
+    interrupt::free(|_| count_half::<T>(timer));
+    for (waker, channel) in T::state().wakers.iter().zip(DELAY_CHANNELS) {
+        if timer.events_compare[channel].read().bits() != 0 {
+            timer.events_compare[channel].write(|w| w);
+            timer
+                .intenclr
+                .write(|w| unsafe { w.bits(compare_mask(channel)) });
+            waker.wake();
+        }
+    }
+}
+
+/// State of a timer shared by its users and its interrupt.
+pub struct TimerState {
+    /// Halves of the counter range gone by since the timer started.
+    halves: AtomicU32,
+    wakers: [WakerCell; 2],
 }
 
-pub trait Instance32: Deref<Target = timer0::RegisterBlock> {}
-impl Instance32 for TIMER0 {}
-impl Instance32 for TIMER1 {}
-impl Instance32 for TIMER2 {}
-impl Instance32 for TIMER3 {}
-impl Instance32 for TIMER4 {}
+impl TimerState {
+    const fn new() -> TimerState {
+        TimerState {
+            halves: AtomicU32::new(0),
+            wakers: [WakerCell::new(), WakerCell::new()],
+        }
+    }
+}
+
+fn start<T: Instance32>(timer: &timer0::RegisterBlock) {
+    T::state().halves.store(0, Ordering::Relaxed);
+    timer.cc[OVERFLOW_CHANNEL].write(|w| unsafe { w.bits(next_half(0)) });
+    timer.events_compare[OVERFLOW_CHANNEL].write(|w| w);
+    timer
+        .intenset
+        .write(|w| unsafe { w.bits(compare_mask(OVERFLOW_CHANNEL)) });
+    timer.tasks_clear.write(|w| unsafe { w.bits(1) });
+    timer.tasks_start.write(|w| unsafe { w.bits(1) });
+}
+
+fn now<T: Instance32>() -> Instant {
+    let timer = unsafe { &*T::ptr() };
+    // Keeps the capture and the read together when several users read the time
+    interrupt::free(|_| {
+        count_half::<T>(timer);
+        timer.tasks_capture[NOW_CHANNEL].write(|w| unsafe { w.bits(1) });
+        let counter = timer.cc[NOW_CHANNEL].read().bits();
+        Instant::from_ticks(extend(T::state().halves.load(Ordering::Relaxed), counter))
+    })
+}
+
+/// Count a half of the counter range if one ended, call in a critical section.
+fn count_half<T: Instance32>(timer: &timer0::RegisterBlock) {
+    if timer.events_compare[OVERFLOW_CHANNEL].read().bits() == 0 {
+        return;
+    }
+    timer.events_compare[OVERFLOW_CHANNEL].write(|w| w);
+    let state = T::state();
+    let (halves, compare) = half_ended(state.halves.load(Ordering::Relaxed));
+    state.halves.store(halves, Ordering::Relaxed);
+    timer.cc[OVERFLOW_CHANNEL].write(|w| unsafe { w.bits(compare) });
+}
+
+/// Halves counted once the compare at the end of a half fired, and the compare
+/// value for the next one.
+const fn half_ended(halves: u32) -> (u32, u32) {
+    let halves = halves.wrapping_add(1);
+    (halves, next_half(halves))
+}
+
+/// Ticks since the start from the halves of the counter range counted so far and
+/// the counter.
+///
+/// The counter may have run into the next half before that half was counted,
+/// the result is right as long as it hasn't run any further.
+const fn extend(halves: u32, counter: u32) -> u64 {
+    ((halves as u64) << 31) + (counter ^ ((halves & 1) << 31)) as u64
+}
+
+/// Counter value ending the half after `halves` halves.
+const fn next_half(halves: u32) -> u32 {
+    if halves & 1 == 0 {
+        HALF
+    } else {
+        0
+    }
+}
+
+/// INTENSET/INTENCLR bit of a compare channel.
+const fn compare_mask(channel: usize) -> u32 {
+    1 << (16 + channel)
+}
+
+pub trait Instance32: Deref<Target = timer0::RegisterBlock> {
+    fn ptr() -> *const timer0::RegisterBlock;
+    fn state() -> &'static TimerState;
+}
+
+macro_rules! instance32 {
+    ($($timer:ident),*) => {
+        $(
+            impl Instance32 for $timer {
+                fn ptr() -> *const timer0::RegisterBlock {
+                    $timer::ptr()
+                }
+
+                fn state() -> &'static TimerState {
+                    static STATE: TimerState = TimerState::new();
+                    &STATE
+                }
+            }
+        )*
+    };
+}
+
+instance32!(TIMER0, TIMER1, TIMER2, TIMER3, TIMER4);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The overflow compare of a timer running for `ticks`, and the halves
+    /// counted from it.
+    struct Fake {
+        ticks: u64,
+        compare: u32,
+        /// The compare fired and wasn't counted yet.
+        event: bool,
+        halves: u32,
+    }
+
+    impl Fake {
+        fn new() -> Fake {
+            Fake {
+                ticks: 0,
+                compare: next_half(0),
+                event: false,
+                halves: 0,
+            }
+        }
+
+        /// Run for `ticks`, the compare fires when the counter reaches it.
+        fn run(&mut self, ticks: u64) {
+            let until_compare = match self.compare.wrapping_sub(self.ticks as u32) {
+                0 => 1 << 32,
+                ticks => u64::from(ticks),
+            };
+            assert!(
+                !self.event || until_compare > ticks,
+                "a half went by uncounted"
+            );
+            self.event |= until_compare <= ticks;
+            self.ticks += ticks;
+        }
+
+        /// The time without counting a half that just ended, as when the
+        /// interrupt is still pending.
+        fn lagging(&self) -> u64 {
+            extend(self.halves, self.ticks as u32)
+        }
+
+        fn now(&mut self) -> u64 {
+            if self.event {
+                self.event = false;
+                (self.halves, self.compare) = half_ended(self.halves);
+            }
+            extend(self.halves, self.ticks as u32)
+        }
+    }
+
+    #[test]
+    fn extend_counter() {
+        const H: u64 = 1 << 31;
+        assert_eq!(extend(0, 0), 0);
+        assert_eq!(extend(0, HALF - 1), H - 1);
+        assert_eq!(extend(1, HALF), H);
+        assert_eq!(extend(2, 0), 2 * H);
+        assert_eq!(extend(3, HALF + 7), 3 * H + 7);
+        // Counter a half ahead of the count
+        assert_eq!(extend(0, HALF + 7), H + 7);
+        assert_eq!(extend(1, 7), 2 * H + 7);
+        assert_eq!(extend(2, HALF), 3 * H);
+    }
+
+    #[test]
+    fn compares_alternate() {
+        assert_eq!(next_half(0), HALF);
+        assert_eq!(half_ended(0), (1, 0));
+        assert_eq!(half_ended(1), (2, HALF));
+        assert_eq!(half_ended(u32::MAX), (0, HALF));
+    }
+
+    #[test]
+    fn across_half_and_wrap() {
+        let mut timer = Fake::new();
+        for boundary in [1 << 31, 1 << 32, 3 << 31, 1 << 33] {
+            timer.run(boundary - 2 - timer.ticks);
+            for _ in 0..4 {
+                assert_eq!(timer.now(), timer.ticks);
+                timer.run(1);
+            }
+        }
+        assert_eq!(timer.halves, 4);
+    }
+
+    #[test]
+    fn half_count_lagging() {
+        let mut timer = Fake::new();
+        for boundary in [1 << 31, 1 << 32, 3 << 31, 1 << 33] {
+            timer.run(boundary + 1000 - timer.ticks);
+            // The interrupt hasn't run yet
+            assert_eq!(timer.lagging(), timer.ticks);
+            assert_eq!(timer.now(), timer.ticks);
+        }
+    }
+
+    #[test]
+    fn read_every_half() {
+        let mut timer = Fake::new();
+        for _ in 0..20 {
+            timer.run(u64::from(HALF) - 1);
+            assert_eq!(timer.now(), timer.ticks);
+        }
+    }
+}
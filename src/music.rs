@@ -1,5 +1,6 @@
 use core::ops::RangeBounds;
 
+use heapless::Vec;
 use microbit::hal::{
     gpio::{Output, Pin, PushPull},
     pwm::{self, Channel, CounterMode, Pwm},
@@ -11,10 +12,19 @@ const DEFAULT_BPM: u32 = 120; // 500ms
 const PERIODS: &[u32] = &[440, 494, 262, 294, 330, 349, 392];
 /// A#, -, C#, D#, -, F#, G#
 const PERIODS_SHARP: &[u32] = &[466, 0, 277, 311, 0, 370, 415];
+/// Longest melody [`Music::play`] keeps.
+pub const MAX_MELODY_LEN: usize = 256;
+
+/// Why [`Music::play`] refused a melody.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum MelodyError {
+    /// The melody has more than [`MAX_MELODY_LEN`] bytes.
+    TooLong,
+}
 
 pub struct Music<PWM: pwm::Instance, TIMER: timer::Instance> {
     pos: usize,
-    notes: &'static [u8],
+    notes: Vec<u8, MAX_MELODY_LEN>,
     bpm: u32,
     volume: u32,
     timer: TIMER,
@@ -28,7 +38,7 @@ impl<PWM: pwm::Instance, TIMER: timer::Instance> Music<PWM, TIMER> {
         buzzer.set_counter_mode(CounterMode::UpAndDown);
         let mut music = Music {
             pos: 0,
-            notes: &[],
+            notes: Vec::new(),
             volume: 90,
             bpm: DEFAULT_BPM,
             buzzer,
@@ -76,7 +86,9 @@ impl<PWM: pwm::Instance, TIMER: timer::Instance> Music<PWM, TIMER> {
         }
     }
 
-    /// Play notes split with whitespace.
+    /// Play notes split with whitespace, a copy is kept so the melody can come
+    /// from a temporary buffer. A melody longer than [`MAX_MELODY_LEN`] bytes
+    /// is refused and whatever is playing goes on.
     ///
     /// Note: (#|b)(pitch)(octave)(:duration)
     ///
@@ -91,12 +103,17 @@ impl<PWM: pwm::Instance, TIMER: timer::Instance> Music<PWM, TIMER> {
     ///               g4 g4 f4 f4 e4 e4 d4 -
     ///               g4 g4 f4 f4 e4 e4 d4 -"#)
     /// ```
-    pub fn play(&mut self, notes: &'static [u8], bpm: u32) {
+    pub fn play(&mut self, notes: &[u8], bpm: u32) -> Result<(), MelodyError> {
+        if notes.len() > MAX_MELODY_LEN {
+            return Err(MelodyError::TooLong);
+        }
         self.reset_timer();
-        self.notes = notes;
+        self.notes.clear();
+        self.notes.extend_from_slice(notes).ok();
         self.bpm = bpm;
         self.pos = 0;
         self.start_timer();
+        Ok(())
     }
 
     pub fn stop(&mut self) {
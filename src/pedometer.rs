@@ -0,0 +1,407 @@
+//! Step counter working on accelerometer magnitude samples taken at a fixed rate.
+//!
+//! The magnitude is band-pass filtered around walking frequencies, then a step is
+//! counted each time the signal swings from below to above the midpoint of its
+//! recent peak and valley envelope.
+
+use core::f32::consts::PI;
+
+use libm::sqrtf;
+use lsm303agr::Measurement;
+
+const CADENCE_STEPS: usize = 4;
+
+/// Why a [`PedometerConfig`] was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum PedometerError {
+    /// `sample_rate_hz` is 0.
+    SampleRate,
+    /// `high_pass_hz` isn't a positive number.
+    HighPass,
+    /// `low_pass_hz` isn't a positive number.
+    LowPass,
+    /// `envelope_secs` isn't a positive number.
+    Envelope,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PedometerConfig {
+    /// Rate `update` is called at.
+    pub sample_rate_hz: u32,
+    /// Cut-off removing gravity and slow posture changes.
+    pub high_pass_hz: f32,
+    /// Cut-off removing vibration and sensor noise.
+    pub low_pass_hz: f32,
+    /// Time constant of the peak and valley envelope.
+    pub envelope_secs: f32,
+    /// Peak to valley swing in milli-g below which nothing is counted.
+    pub min_amplitude: f32,
+    /// Shortest time between two steps.
+    pub min_step_ms: u32,
+    /// Longest time between two steps of the same walk.
+    pub max_step_ms: u32,
+}
+
+impl Default for PedometerConfig {
+    fn default() -> PedometerConfig {
+        PedometerConfig {
+            sample_rate_hz: 50,
+            high_pass_hz: 0.5,
+            low_pass_hz: 3.0,
+            envelope_secs: 1.5,
+            min_amplitude: 150.0,
+            min_step_ms: 250,
+            max_step_ms: 2000,
+        }
+    }
+}
+
+pub struct Pedometer {
+    config: PedometerConfig,
+    high_pass_alpha: f32,
+    low_pass_alpha: f32,
+    envelope_decay: f32,
+    started: bool,
+    last_input: f32,
+    high_passed: f32,
+    filtered: f32,
+    peak: f32,
+    valley: f32,
+    armed: bool,
+    /// Samples taken, the time base, so that rates not dividing a second
+    /// don't drift.
+    now: u32,
+    last_step: Option<u32>,
+    /// Samples between the last steps.
+    intervals: [u32; CADENCE_STEPS],
+    interval_len: usize,
+    interval_pos: usize,
+    steps: u32,
+}
+
+impl Default for Pedometer {
+    fn default() -> Pedometer {
+        Pedometer::with_config(PedometerConfig::default())
+    }
+}
+
+impl Pedometer {
+    pub fn new(config: PedometerConfig) -> Result<Self, PedometerError> {
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+        if config.sample_rate_hz == 0 {
+            return Err(PedometerError::SampleRate);
+        }
+        if !positive(config.high_pass_hz) {
+            return Err(PedometerError::HighPass);
+        }
+        if !positive(config.low_pass_hz) {
+            return Err(PedometerError::LowPass);
+        }
+        if !positive(config.envelope_secs) {
+            return Err(PedometerError::Envelope);
+        }
+        Ok(Pedometer::with_config(config))
+    }
+
+    /// `config` has a sample rate and positive cut-offs and envelope time.
+    fn with_config(config: PedometerConfig) -> Self {
+        let dt = 1.0 / config.sample_rate_hz as f32;
+        let high_pass_rc = 1.0 / (2.0 * PI * config.high_pass_hz);
+        let low_pass_rc = 1.0 / (2.0 * PI * config.low_pass_hz);
+        Pedometer {
+            config,
+            high_pass_alpha: high_pass_rc / (high_pass_rc + dt),
+            low_pass_alpha: dt / (low_pass_rc + dt),
+            envelope_decay: 1.0 - dt / config.envelope_secs,
+            started: false,
+            last_input: 0.0,
+            high_passed: 0.0,
+            filtered: 0.0,
+            peak: 0.0,
+            valley: 0.0,
+            armed: false,
+            now: 0,
+            last_step: None,
+            intervals: [0; CADENCE_STEPS],
+            interval_len: 0,
+            interval_pos: 0,
+            steps: 0,
+        }
+    }
+
+    pub fn config(&self) -> &PedometerConfig {
+        &self.config
+    }
+
+    /// Steps counted since creation or the last [`Pedometer::reset`].
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Steps per minute averaged over the last few steps, 0 when not walking.
+    pub fn cadence(&self) -> u32 {
+        let walking = self.last_step.is_some_and(|last| {
+            self.millis(self.now.wrapping_sub(last)) <= self.config.max_step_ms
+        });
+        if !walking || self.interval_len == 0 {
+            return 0;
+        }
+        let total: u32 = self.intervals[..self.interval_len].iter().sum();
+        let minute = 60 * u64::from(self.config.sample_rate_hz);
+        (minute * self.interval_len as u64 / u64::from(total.max(1))) as u32
+    }
+
+    /// The band-pass filtered signal, handy for plotting and tuning.
+    pub fn filtered(&self) -> f32 {
+        self.filtered
+    }
+
+    pub fn reset(&mut self) {
+        *self = Pedometer::with_config(self.config);
+    }
+
+    /// Feed one magnitude sample in milli-g, returns whether it completed a step.
+    pub fn update(&mut self, magnitude: f32) -> bool {
+        self.now = self.now.wrapping_add(1);
+
+        if !self.started {
+            self.started = true;
+            self.last_input = magnitude;
+        }
+
+        self.high_passed = self.high_pass_alpha * (self.high_passed + magnitude - self.last_input);
+        self.last_input = magnitude;
+        self.filtered += self.low_pass_alpha * (self.high_passed - self.filtered);
+
+        let signal = self.filtered;
+        self.peak = signal.max(self.peak * self.envelope_decay);
+        self.valley = signal.min(self.valley * self.envelope_decay);
+
+        let amplitude = self.peak - self.valley;
+        if amplitude < self.config.min_amplitude {
+            self.armed = false;
+            return false;
+        }
+
+        // Hysteresis around the midpoint so noise on a slow swing counts once
+        let middle = (self.peak + self.valley) / 2.0;
+        let hysteresis = amplitude / 5.0;
+        if signal < middle - hysteresis {
+            self.armed = true;
+            return false;
+        }
+        if !self.armed || signal < middle + hysteresis {
+            return false;
+        }
+        self.armed = false;
+
+        let interval = self.last_step.map(|last| self.now.wrapping_sub(last));
+        match interval.map(|interval| (interval, self.millis(interval))) {
+            Some((_, ms)) if ms < self.config.min_step_ms => false,
+            Some((interval, ms)) if ms <= self.config.max_step_ms => {
+                self.intervals[self.interval_pos] = interval;
+                self.interval_pos = (self.interval_pos + 1) % CADENCE_STEPS;
+                self.interval_len = (self.interval_len + 1).min(CADENCE_STEPS);
+                self.step()
+            }
+            _ => {
+                // First step of a new walk, there is no interval to average yet
+                self.interval_len = 0;
+                self.step()
+            }
+        }
+    }
+
+    fn step(&mut self) -> bool {
+        self.last_step = Some(self.now);
+        self.steps += 1;
+        true
+    }
+
+    /// Milliseconds of `samples`.
+    fn millis(&self, samples: u32) -> u32 {
+        let ms = u64::from(samples) * 1000 / u64::from(self.config.sample_rate_hz);
+        ms.min(u64::from(u32::MAX)) as u32
+    }
+}
+
+/// Magnitude in milli-g of an accelerometer measurement.
+pub fn magnitude(measurement: Measurement) -> f32 {
+    let (x, y, z) = (
+        measurement.x as f32,
+        measurement.y as f32,
+        measurement.z as f32,
+    );
+    sqrtf(x * x + y * y + z * z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Magnitudes of a walk at `step_hz` for `secs`, gravity plus a swing per
+    /// step with a sharper heel strike and some sensor noise.
+    fn walk(rate: u32, step_hz: f32, secs: u32) -> Vec<f32> {
+        let mut noise = 0x1234_5678u32;
+        (0..rate * secs)
+            .map(|i| {
+                noise ^= noise << 13;
+                noise ^= noise >> 17;
+                noise ^= noise << 5;
+                let phase = 2.0 * PI * step_hz * i as f32 / rate as f32;
+                let jitter = (noise % 41) as f32 - 20.0;
+                1000.0 + 300.0 * libm::sinf(phase) + 80.0 * libm::sinf(2.0 * phase) + jitter
+            })
+            .collect()
+    }
+
+    fn count(pedometer: &mut Pedometer, samples: &[f32]) -> u32 {
+        samples
+            .iter()
+            .filter(|&&magnitude| pedometer.update(magnitude))
+            .count() as u32
+    }
+
+    #[test]
+    fn zero_sample_rate_is_rejected() {
+        let config = PedometerConfig {
+            sample_rate_hz: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            Pedometer::new(config).err(),
+            Some(PedometerError::SampleRate)
+        );
+    }
+
+    #[test]
+    fn filter_times_are_checked() {
+        let rejected = |config: PedometerConfig| Pedometer::new(config).err();
+        let default = PedometerConfig::default();
+        for value in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let config = PedometerConfig {
+                high_pass_hz: value,
+                ..default
+            };
+            assert_eq!(rejected(config), Some(PedometerError::HighPass));
+            let config = PedometerConfig {
+                low_pass_hz: value,
+                ..default
+            };
+            assert_eq!(rejected(config), Some(PedometerError::LowPass));
+            let config = PedometerConfig {
+                envelope_secs: value,
+                ..default
+            };
+            assert_eq!(rejected(config), Some(PedometerError::Envelope));
+        }
+        assert!(Pedometer::new(default).is_ok());
+    }
+
+    #[test]
+    fn counts_a_walk() {
+        let mut pedometer = Pedometer::default();
+        let steps = count(&mut pedometer, &walk(50, 1.8, 20));
+        // 36 steps, the filters take the first one or two
+        assert!((33..=36).contains(&steps), "{} steps", steps);
+        assert_eq!(pedometer.steps(), steps);
+        assert!((104..=112).contains(&pedometer.cadence()));
+    }
+
+    /// Sample rate and step count in the name of a recorded walk.
+    fn walk_name(name: &str) -> Option<(u32, u32)> {
+        let mut parts = name.strip_suffix("steps.csv")?.rsplit('-');
+        let steps = parts.next()?.parse().ok()?;
+        let rate = parts.next()?.strip_suffix("hz")?.parse().ok()?;
+        Some((rate, steps))
+    }
+
+    #[test]
+    fn recorded_walks() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/walks");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap();
+            if !name.ends_with(".csv") {
+                continue;
+            }
+            let (rate, expected) = walk_name(name).expect(name);
+            let samples: Vec<f32> = std::fs::read_to_string(&path)
+                .unwrap()
+                .lines()
+                .filter_map(crate::telemetry::Record::parse_csv)
+                .map(|record| magnitude(record.accel))
+                .collect();
+            let config = PedometerConfig {
+                sample_rate_hz: rate,
+                ..Default::default()
+            };
+            let steps = count(&mut Pedometer::new(config).unwrap(), &samples);
+            let tolerance = (expected / 20).max(2);
+            assert!(
+                steps.abs_diff(expected) <= tolerance,
+                "{}: {} steps",
+                name,
+                steps
+            );
+        }
+    }
+
+    #[test]
+    fn walk_names() {
+        assert_eq!(walk_name("pocket-50hz-120steps.csv"), Some((50, 120)));
+        assert_eq!(walk_name("wrist-left-25hz-8steps.csv"), Some((25, 8)));
+        assert_eq!(walk_name("pocket-50-120steps.csv"), None);
+        assert_eq!(walk_name("pocket-50hz.csv"), None);
+    }
+
+    #[test]
+    fn standing_still_counts_nothing() {
+        let mut pedometer = Pedometer::default();
+        let mut noise = 1u32;
+        let samples: Vec<f32> = (0..1000)
+            .map(|_| {
+                noise = noise.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                1000.0 + ((noise >> 16) % 61) as f32 - 30.0
+            })
+            .collect();
+        assert_eq!(count(&mut pedometer, &samples), 0);
+        assert_eq!(pedometer.cadence(), 0);
+    }
+
+    #[test]
+    fn cadence_does_not_drift_at_30hz() {
+        let config = PedometerConfig {
+            sample_rate_hz: 30,
+            ..Default::default()
+        };
+        let mut pedometer = Pedometer::new(config).unwrap();
+        // 15 samples a step, 500ms rather than 15 * 33ms
+        count(&mut pedometer, &walk(30, 2.0, 20));
+        assert_eq!(pedometer.cadence(), 120);
+    }
+
+    #[test]
+    fn cadence_drops_when_stopping() {
+        let mut pedometer = Pedometer::default();
+        count(&mut pedometer, &walk(50, 1.8, 10));
+        assert!(pedometer.cadence() > 0);
+        let steps = pedometer.steps();
+        count(&mut pedometer, &[1000.0; 150]);
+        assert_eq!(pedometer.steps(), steps);
+        assert_eq!(pedometer.cadence(), 0);
+    }
+
+    #[test]
+    fn reset_keeps_the_config() {
+        let config = PedometerConfig {
+            sample_rate_hz: 30,
+            ..Default::default()
+        };
+        let mut pedometer = Pedometer::new(config).unwrap();
+        count(&mut pedometer, &walk(30, 2.0, 5));
+        pedometer.reset();
+        assert_eq!(pedometer.steps(), 0);
+        assert_eq!(pedometer.config().sample_rate_hz, 30);
+    }
+}
@@ -0,0 +1,613 @@
+//! Request/response protocol for host tooling.
+//!
+//! Every message travels in a frame from [`crate::framing`], so it is COBS
+//! encoded and checked with a CRC-16. The payload is a sequence number followed
+//! by a [`Request`] or [`Response`]; the device answers each request with the
+//! sequence number it carried, which lets the host match answers to questions
+//! and drop stale ones after a retry.
+//!
+//! Payloads use the [postcard](https://docs.rs/postcard) wire format so a host
+//! can decode them with serde types mirroring the ones here: enum variants and
+//! unsigned integers are varints, signed integers are zigzag varints and `u8`s
+//! and arrays are raw bytes.
+//!
+//! ```ignore
+//! // host
+//! let mut client = Client::new();
+//! let len = client.request(&Request::ReadAccel, &mut frame)?;
+//! port.write_all(&frame[..len])?;
+//! // ... read up to the next zero byte
+//! let response = client.response(&received)?;
+//!
+//! // device
+//! let (seq, request) = decode_request(&received)?;
+//! let len = encode_response(seq, &handle(request), &mut frame)?;
+//! ```
+
+use heapless::Vec;
+use lsm303agr::Measurement;
+
+use crate::framing::{self, max_frame_len, FrameError};
+
+/// Melody bytes carried by one [`Request::LoadMelody`].
+pub const MELODY_CHUNK_SIZE: usize = 32;
+/// Largest encoded message, a [`Request::LoadMelody`]: a sequence number, a tag,
+/// the offset and a full chunk with its length.
+pub const MAX_MESSAGE_SIZE: usize = 3 + 1 + 3 + 1 + MELODY_CHUNK_SIZE;
+pub const MAX_FRAME_SIZE: usize = max_frame_len(MAX_MESSAGE_SIZE);
+/// Highest brightness in a [`Request::Display`] image.
+pub const MAX_BRIGHTNESS: u8 = 9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Ping,
+    ReadAccel,
+    ReadMag,
+    /// Both sensors in one go.
+    ReadSensors,
+    /// Show an image, rows of brightness from 0 to [`MAX_BRIGHTNESS`].
+    Display([[u8; 5]; 5]),
+    ClearDisplay,
+    /// Play one of the tunes built into the firmware.
+    PlayMusic {
+        tune: u8,
+        bpm: u16,
+    },
+    StopMusic,
+    /// Volume from 0 to 100.
+    SetVolume(u8),
+    /// Store part of a melody, in the notation of [`crate::music::Music::play`].
+    ///
+    /// Chunks are sent in order, offset 0 starts a new melody.
+    LoadMelody {
+        offset: u16,
+        notes: Vec<u8, MELODY_CHUNK_SIZE>,
+    },
+    /// Play the stored melody.
+    PlayMelody {
+        bpm: u16,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Response {
+    Pong,
+    /// Acceleration in mg.
+    Accel(Measurement),
+    /// Magnetic field in nT.
+    Mag(Measurement),
+    Sensors {
+        accel: Measurement,
+        mag: Measurement,
+    },
+    /// The request was carried out.
+    Done,
+    Error(ErrorCode),
+}
+
+/// Why the device couldn't carry out a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ErrorCode {
+    /// The request is valid but this firmware doesn't handle it.
+    Unsupported,
+    InvalidArgument,
+    /// Reading the sensor failed.
+    Sensor,
+    /// Still working on an earlier request.
+    Busy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    Frame(FrameError),
+    /// The frame is intact but doesn't hold a known message.
+    Malformed,
+    /// A response arrived with a sequence number that isn't awaited.
+    UnexpectedSequence(u16),
+}
+
+impl From<FrameError> for Error {
+    fn from(error: FrameError) -> Error {
+        Error::Frame(error)
+    }
+}
+
+/// Encode a request as a complete frame, returns the frame length.
+pub fn encode_request(seq: u16, request: &Request, dst: &mut [u8]) -> Result<usize, Error> {
+    let mut payload = [0; MAX_MESSAGE_SIZE];
+    let mut w = Writer::new(&mut payload);
+    w.varint(seq.into());
+    request.encode(&mut w);
+    let len = w.pos;
+    Ok(framing::encode_frame(&payload[..len], dst)?)
+}
+
+/// Decode a request frame, with or without its delimiter.
+pub fn decode_request(frame: &[u8]) -> Result<(u16, Request), Error> {
+    decode(frame, Request::decode)
+}
+
+/// Encode a response as a complete frame, returns the frame length.
+pub fn encode_response(seq: u16, response: &Response, dst: &mut [u8]) -> Result<usize, Error> {
+    let mut payload = [0; MAX_MESSAGE_SIZE];
+    let mut w = Writer::new(&mut payload);
+    w.varint(seq.into());
+    response.encode(&mut w);
+    let len = w.pos;
+    Ok(framing::encode_frame(&payload[..len], dst)?)
+}
+
+/// Decode a response frame, with or without its delimiter.
+pub fn decode_response(frame: &[u8]) -> Result<(u16, Response), Error> {
+    decode(frame, Response::decode)
+}
+
+fn decode<T>(frame: &[u8], message: fn(&mut Reader) -> Option<T>) -> Result<(u16, T), Error> {
+    // decode_frame needs room for the CRC as well
+    let mut payload = [0; MAX_MESSAGE_SIZE + 2];
+    let len = framing::decode_frame(frame, &mut payload)?;
+    let mut r = Reader::new(&payload[..len]);
+    let seq = r.varint().and_then(|seq| u16::try_from(seq).ok());
+    match (seq, seq.and_then(|_| message(&mut r))) {
+        (Some(seq), Some(message)) if r.is_empty() => Ok((seq, message)),
+        _ => Err(Error::Malformed),
+    }
+}
+
+/// Host side bookkeeping: numbers requests and checks the responses match.
+///
+/// One request is in flight at a time, a new one supersedes the last.
+#[derive(Debug, Default)]
+pub struct Client {
+    next_seq: u16,
+    pending: Option<u16>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Client::default()
+    }
+
+    /// Sequence number of the request awaiting a response.
+    pub fn pending(&self) -> Option<u16> {
+        self.pending
+    }
+
+    /// Encode `request` under a new sequence number, returns the frame length.
+    pub fn request(&mut self, request: &Request, dst: &mut [u8]) -> Result<usize, Error> {
+        let seq = self.next_seq;
+        let len = encode_request(seq, request, dst)?;
+        self.next_seq = seq.wrapping_add(1);
+        self.pending = Some(seq);
+        Ok(len)
+    }
+
+    /// Encode `request` again under the pending sequence number, after a timeout.
+    ///
+    /// A late answer to the first attempt is then accepted as well.
+    pub fn retry(&self, request: &Request, dst: &mut [u8]) -> Result<usize, Error> {
+        let seq = self.pending.unwrap_or(self.next_seq);
+        encode_request(seq, request, dst)
+    }
+
+    /// Give up on the pending request, its response will be rejected.
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Decode a response frame and match it to the pending request.
+    pub fn response(&mut self, frame: &[u8]) -> Result<Response, Error> {
+        let (seq, response) = decode_response(frame)?;
+        if self.pending != Some(seq) {
+            return Err(Error::UnexpectedSequence(seq));
+        }
+        self.pending = None;
+        Ok(response)
+    }
+}
+
+impl Request {
+    fn encode(&self, w: &mut Writer) {
+        match *self {
+            Request::Ping => w.varint(0),
+            Request::ReadAccel => w.varint(1),
+            Request::ReadMag => w.varint(2),
+            Request::ReadSensors => w.varint(3),
+            Request::Display(image) => {
+                w.varint(4);
+                for row in image {
+                    w.bytes(&row);
+                }
+            }
+            Request::ClearDisplay => w.varint(5),
+            Request::PlayMusic { tune, bpm } => {
+                w.varint(6);
+                w.u8(tune);
+                w.varint(bpm.into());
+            }
+            Request::StopMusic => w.varint(7),
+            Request::SetVolume(volume) => {
+                w.varint(8);
+                w.u8(volume);
+            }
+            Request::LoadMelody { offset, ref notes } => {
+                w.varint(9);
+                w.varint(offset.into());
+                w.varint(notes.len() as u32);
+                w.bytes(notes);
+            }
+            Request::PlayMelody { bpm } => {
+                w.varint(10);
+                w.varint(bpm.into());
+            }
+        }
+    }
+
+    fn decode(r: &mut Reader) -> Option<Request> {
+        Some(match r.varint()? {
+            0 => Request::Ping,
+            1 => Request::ReadAccel,
+            2 => Request::ReadMag,
+            3 => Request::ReadSensors,
+            4 => {
+                let mut image = [[0; 5]; 5];
+                for row in image.iter_mut() {
+                    for led in row.iter_mut() {
+                        *led = r.u8()?;
+                    }
+                }
+                Request::Display(image)
+            }
+            5 => Request::ClearDisplay,
+            6 => Request::PlayMusic {
+                tune: r.u8()?,
+                bpm: r.varint()?.try_into().ok()?,
+            },
+            7 => Request::StopMusic,
+            8 => Request::SetVolume(r.u8()?),
+            9 => {
+                let offset = r.varint()?.try_into().ok()?;
+                let len = r.varint()? as usize;
+                let mut notes = Vec::new();
+                for _ in 0..len {
+                    notes.push(r.u8()?).ok()?;
+                }
+                Request::LoadMelody { offset, notes }
+            }
+            10 => Request::PlayMelody {
+                bpm: r.varint()?.try_into().ok()?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl Response {
+    fn encode(&self, w: &mut Writer) {
+        match *self {
+            Response::Pong => w.varint(0),
+            Response::Accel(accel) => {
+                w.varint(1);
+                w.measurement(accel);
+            }
+            Response::Mag(mag) => {
+                w.varint(2);
+                w.measurement(mag);
+            }
+            Response::Sensors { accel, mag } => {
+                w.varint(3);
+                w.measurement(accel);
+                w.measurement(mag);
+            }
+            Response::Done => w.varint(4),
+            Response::Error(code) => {
+                w.varint(5);
+                w.varint(code as u32);
+            }
+        }
+    }
+
+    fn decode(r: &mut Reader) -> Option<Response> {
+        Some(match r.varint()? {
+            0 => Response::Pong,
+            1 => Response::Accel(r.measurement()?),
+            2 => Response::Mag(r.measurement()?),
+            3 => Response::Sensors {
+                accel: r.measurement()?,
+                mag: r.measurement()?,
+            },
+            4 => Response::Done,
+            5 => Response::Error(match r.varint()? {
+                0 => ErrorCode::Unsupported,
+                1 => ErrorCode::InvalidArgument,
+                2 => ErrorCode::Sensor,
+                3 => ErrorCode::Busy,
+                _ => return None,
+            }),
+            _ => return None,
+        })
+    }
+}
+
+/// Writes into a buffer of [`MAX_MESSAGE_SIZE`], which every message fits in.
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Writer { buf, pos: 0 }
+    }
+
+    fn u8(&mut self, byte: u8) {
+        self.buf[self.pos] = byte;
+        self.pos += 1;
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.u8(byte);
+        }
+    }
+
+    /// LEB128, seven bits at a time starting with the lowest.
+    fn varint(&mut self, mut value: u32) {
+        while value >= 0x80 {
+            self.u8(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.u8(value as u8);
+    }
+
+    fn zigzag(&mut self, value: i32) {
+        self.varint(((value << 1) ^ (value >> 31)) as u32);
+    }
+
+    fn measurement(&mut self, m: Measurement) {
+        self.zigzag(m.x);
+        self.zigzag(m.y);
+        self.zigzag(m.z);
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Reader { buf }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        let (&byte, rest) = self.buf.split_first()?;
+        self.buf = rest;
+        Some(byte)
+    }
+
+    fn varint(&mut self) -> Option<u32> {
+        let mut value = 0_u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.u8()?;
+            // The fifth byte holds the top four bits
+            if shift == 28 && byte > 0x0F {
+                return None;
+            }
+            value |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn zigzag(&mut self) -> Option<i32> {
+        let value = self.varint()?;
+        Some((value >> 1) as i32 ^ -((value & 1) as i32))
+    }
+
+    fn measurement(&mut self) -> Option<Measurement> {
+        Some(Measurement {
+            x: self.zigzag()?,
+            y: self.zigzag()?,
+            z: self.zigzag()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accel(x: i32, y: i32, z: i32) -> Measurement {
+        Measurement { x, y, z }
+    }
+
+    fn frame_of(payload: &[u8]) -> std::vec::Vec<u8> {
+        let mut frame = [0; 64];
+        let len = framing::encode_frame(payload, &mut frame).unwrap();
+        frame[..len].to_vec()
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        let mut image = [[0; 5]; 5];
+        image[2][3] = MAX_BRIGHTNESS;
+        let requests = [
+            Request::Ping,
+            Request::ReadAccel,
+            Request::ReadMag,
+            Request::ReadSensors,
+            Request::Display(image),
+            Request::ClearDisplay,
+            Request::PlayMusic { tune: 3, bpm: 300 },
+            Request::StopMusic,
+            Request::SetVolume(100),
+            Request::LoadMelody {
+                offset: 64,
+                notes: Vec::from_slice(b"c4:4 d e").unwrap(),
+            },
+            Request::PlayMelody { bpm: 120 },
+        ];
+        for (seq, request) in [0, 127, 128, u16::MAX]
+            .into_iter()
+            .zip(requests.iter().cycle())
+        {
+            let mut frame = [0; MAX_FRAME_SIZE];
+            let len = encode_request(seq, request, &mut frame).unwrap();
+            assert_eq!(decode_request(&frame[..len]), Ok((seq, request.clone())));
+        }
+        for request in requests {
+            let mut frame = [0; MAX_FRAME_SIZE];
+            let len = encode_request(7, &request, &mut frame).unwrap();
+            // With or without the delimiter
+            assert_eq!(decode_request(&frame[..len - 1]), Ok((7, request)));
+        }
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        let responses = [
+            Response::Pong,
+            Response::Accel(accel(-1000, 0, i32::MAX)),
+            Response::Mag(accel(i32::MIN, 45_000, -45_000)),
+            Response::Sensors {
+                accel: accel(1, -1, 64),
+                mag: accel(-64, 63, 0),
+            },
+            Response::Done,
+            Response::Error(ErrorCode::Unsupported),
+            Response::Error(ErrorCode::InvalidArgument),
+            Response::Error(ErrorCode::Sensor),
+            Response::Error(ErrorCode::Busy),
+        ];
+        for response in responses {
+            let mut frame = [0; MAX_FRAME_SIZE];
+            let len = encode_response(300, &response, &mut frame).unwrap();
+            assert_eq!(decode_response(&frame[..len]), Ok((300, response)));
+        }
+    }
+
+    #[test]
+    fn largest_message_fits() {
+        let request = Request::LoadMelody {
+            offset: u16::MAX,
+            notes: Vec::from_slice(&[0x80; MELODY_CHUNK_SIZE]).unwrap(),
+        };
+        let mut frame = [0; MAX_FRAME_SIZE];
+        let len = encode_request(u16::MAX, &request, &mut frame).unwrap();
+        assert_eq!(
+            decode_request(&frame[..len]),
+            Ok((u16::MAX, request.clone()))
+        );
+
+        let mut payload = [0; MAX_MESSAGE_SIZE + 2];
+        let payload_len = framing::decode_frame(&frame[..len], &mut payload).unwrap();
+        assert_eq!(payload_len, MAX_MESSAGE_SIZE);
+
+        let mut short = [0; MAX_FRAME_SIZE];
+        assert_eq!(
+            encode_request(u16::MAX, &request, &mut short[..len - 1]),
+            Err(Error::Frame(FrameError::BufferTooSmall))
+        );
+    }
+
+    #[test]
+    fn oversized_payload_is_rejected() {
+        let payload = [1; MAX_MESSAGE_SIZE + 1];
+        assert_eq!(
+            decode_request(&frame_of(&payload)),
+            Err(Error::Frame(FrameError::BufferTooSmall))
+        );
+    }
+
+    #[test]
+    fn truncated_and_trailing_input_is_malformed() {
+        // PlayMusic without its bpm
+        assert_eq!(decode_request(&frame_of(&[1, 6, 2])), Err(Error::Malformed));
+        // A varint cut short
+        assert_eq!(
+            decode_request(&frame_of(&[1, 6, 2, 0x80])),
+            Err(Error::Malformed)
+        );
+        // LoadMelody with fewer notes than announced
+        assert_eq!(
+            decode_request(&frame_of(&[1, 9, 0, 3, b'c'])),
+            Err(Error::Malformed)
+        );
+        // A chunk longer than MELODY_CHUNK_SIZE
+        let mut payload = std::vec![1, 9, 0, 33];
+        payload.extend([b'c'; 33]);
+        assert_eq!(decode_request(&frame_of(&payload)), Err(Error::Malformed));
+        // A byte after the message
+        assert_eq!(decode_request(&frame_of(&[1, 0, 0])), Err(Error::Malformed));
+        assert_eq!(decode_request(&frame_of(&[])), Err(Error::Malformed));
+        // Unknown tags
+        assert_eq!(decode_request(&frame_of(&[1, 11])), Err(Error::Malformed));
+        assert_eq!(
+            decode_response(&frame_of(&[1, 5, 4])),
+            Err(Error::Malformed)
+        );
+        // A sequence number beyond u16
+        assert_eq!(
+            decode_request(&frame_of(&[0x80, 0x80, 0x04, 0])),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn varint_limits() {
+        assert_eq!(
+            Reader::new(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]).varint(),
+            Some(u32::MAX)
+        );
+        // Bits beyond 32 and a sixth byte
+        assert_eq!(Reader::new(&[0xFF, 0xFF, 0xFF, 0xFF, 0x1F]).varint(), None);
+        assert_eq!(
+            Reader::new(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]).varint(),
+            None
+        );
+        // Overlong but in range, as postcard accepts it
+        assert_eq!(Reader::new(&[0x81, 0x00]).varint(), Some(1));
+    }
+
+    #[test]
+    fn client_matches_sequence_numbers() {
+        let mut client = Client::new();
+        let mut frame = [0; MAX_FRAME_SIZE];
+        let len = client.request(&Request::Ping, &mut frame).unwrap();
+        let (seq, _) = decode_request(&frame[..len]).unwrap();
+        assert_eq!(client.pending(), Some(seq));
+
+        // A retry goes out under the same number
+        let len = client.retry(&Request::Ping, &mut frame).unwrap();
+        assert_eq!(decode_request(&frame[..len]).unwrap().0, seq);
+
+        let len = encode_response(seq.wrapping_sub(1), &Response::Pong, &mut frame).unwrap();
+        assert_eq!(
+            client.response(&frame[..len]),
+            Err(Error::UnexpectedSequence(seq.wrapping_sub(1)))
+        );
+        let len = encode_response(seq, &Response::Pong, &mut frame).unwrap();
+        assert_eq!(client.response(&frame[..len]), Ok(Response::Pong));
+        // Answered once
+        assert_eq!(
+            client.response(&frame[..len]),
+            Err(Error::UnexpectedSequence(seq))
+        );
+
+        client.request(&Request::Ping, &mut frame).unwrap();
+        assert_eq!(client.pending(), Some(seq + 1));
+        client.cancel();
+        let len = encode_response(seq + 1, &Response::Pong, &mut frame).unwrap();
+        assert_eq!(
+            client.response(&frame[..len]),
+            Err(Error::UnexpectedSequence(seq + 1))
+        );
+    }
+}
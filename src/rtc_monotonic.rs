@@ -0,0 +1,126 @@
+//! Low power RTIC monotonic on an RTC at 32.768 kHz.
+//!
+//! Unlike [`MonoTimer`](crate::monotonic::MonoTimer) it runs from the LFCLK, so
+//! the HFCLK can stop while the CPU sleeps. The LFCLK has to be started first,
+//! e.g. with `Clocks::new(board.CLOCK).start_lfclk()`.
+//!
+//! The 24-bit counter wraps every 512 seconds. Like `MonoTimer` the halves of
+//! the counter range are counted, here on overflow and on a compare channel
+//! halfway, to make 64-bit instants.
+//!
+//! ```ignore
+//! #[monotonic(binds = RTC0, default = true)]
+//! type Mono = RtcMonotonic<pac::RTC0>;
+//!
+//! blink::spawn_after(Duration::millis(500)).unwrap();
+//! ```
+
+use core::ops::Deref;
+
+use microbit::pac::{rtc0, RTC0, RTC1, RTC2};
+use rtic_monotonic::Monotonic;
+
+pub type Instant = fugit::TimerInstantU64<32_768>;
+pub type Duration = fugit::TimerDurationU64<32_768>;
+
+/// Compare channel RTIC schedules tasks on.
+const SCHEDULE_CHANNEL: usize = 0;
+/// Compare channel firing halfway through the counter range.
+const HALF_CHANNEL: usize = 1;
+const HALF: u32 = 1 << 23;
+const COUNTER_MASK: u32 = (1 << 24) - 1;
+/// A compare set this close to the counter may not fire.
+const MIN_COMPARE_DISTANCE: u32 = 3;
+
+pub struct RtcMonotonic<T: RtcInstance> {
+    rtc: T,
+    /// Halves of the counter range gone by since the start.
+    halves: u32,
+}
+
+impl<T: RtcInstance> RtcMonotonic<T> {
+    pub fn new(rtc: T) -> Self {
+        rtc.tasks_stop.write(|w| unsafe { w.bits(1) });
+        rtc.prescaler.write(|w| unsafe { w.prescaler().bits(0) });
+        RtcMonotonic { rtc, halves: 0 }
+    }
+
+    /// Count a half of the counter range if one ended.
+    fn count_halves(&mut self) {
+        if self.rtc.events_ovrflw.read().bits() != 0 {
+            self.rtc.events_ovrflw.write(|w| w);
+            self.halves = self.halves.wrapping_add(1);
+        }
+        if self.rtc.events_compare[HALF_CHANNEL].read().bits() != 0 {
+            self.rtc.events_compare[HALF_CHANNEL].write(|w| w);
+            self.halves = self.halves.wrapping_add(1);
+        }
+    }
+}
+
+impl<T: RtcInstance> Monotonic for RtcMonotonic<T> {
+    type Instant = Instant;
+    type Duration = Duration;
+
+    // Halves of the counter range are counted even with no task scheduled
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    unsafe fn reset(&mut self) {
+        self.rtc.cc[HALF_CHANNEL].write(|w| w.bits(HALF));
+        // Events are only recorded while enabled
+        self.rtc
+            .evtenset
+            .write(|w| w.ovrflw().set().compare0().set().compare1().set());
+        self.rtc
+            .intenset
+            .write(|w| w.ovrflw().set().compare0().set().compare1().set());
+        self.rtc.tasks_clear.write(|w| w.bits(1));
+        self.rtc.tasks_start.write(|w| w.bits(1));
+    }
+
+    fn now(&mut self) -> Self::Instant {
+        self.count_halves();
+        let counter = self.rtc.counter.read().bits();
+        Instant::from_ticks(extend(self.halves, counter))
+    }
+
+    /// Instants more than a counter range away fire early, RTIC checks the time
+    /// again and sets the compare anew.
+    fn set_compare(&mut self, instant: Self::Instant) {
+        let counter = self.rtc.counter.read().bits();
+        let now = extend(self.halves, counter);
+        // Ones due right away are moved to the first compare value that fires
+        let compare = if instant.ticks() < now + MIN_COMPARE_DISTANCE as u64 {
+            counter + MIN_COMPARE_DISTANCE
+        } else {
+            instant.ticks() as u32
+        };
+        self.rtc.cc[SCHEDULE_CHANNEL].write(|w| unsafe { w.bits(compare & COUNTER_MASK) });
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.rtc.events_compare[SCHEDULE_CHANNEL].write(|w| w);
+    }
+
+    fn zero() -> Self::Instant {
+        Instant::from_ticks(0)
+    }
+
+    fn on_interrupt(&mut self) {
+        self.count_halves();
+    }
+}
+
+/// Ticks since the start from the halves of the counter range counted so far and
+/// the counter.
+///
+/// The counter may have run into the next half before that half was counted,
+/// the result is right as long as it hasn't run any further.
+const fn extend(halves: u32, counter: u32) -> u64 {
+    ((halves as u64) << 23) + (counter ^ ((halves & 1) << 23)) as u64
+}
+
+pub trait RtcInstance: Deref<Target = rtc0::RegisterBlock> {}
+impl RtcInstance for RTC0 {}
+impl RtcInstance for RTC1 {}
+impl RtcInstance for RTC2 {}
@@ -0,0 +1,334 @@
+//! Scrolling of text built at runtime, one message after another.
+//!
+//! [`TextScroller`] copies each message into a queue of `Q` strings of up to `N`
+//! characters, so the text can come from anywhere: a formatted sensor value, a
+//! line read from the serial port. Share it between RTIC tasks as a resource,
+//! queue text from any of them and tick it from the one driving the display.
+//! Characters are drawn with a [`Font`], which takes up to `G` glyphs of your own.
+//!
+//! ```ignore
+//! let mut scroller = TextScroller::<32, 4>::new();
+//! scroller.set_repeat(2);
+//! scroller.font_mut().register('☺', images::HAPPY)?;
+//! scroller.push("21°C ☺")?;
+//!
+//! // 16 times a second
+//! scroller.tick();
+//! frame.set(&scroller);
+//! display.show_frame(&frame);
+//! ```
+
+use heapless::{Deque, Vec};
+use microbit_text::scrolling::{Animate, Scrollable, ScrollingState};
+use tiny_led_matrix::Render;
+
+use crate::font::Font;
+use crate::frame::Frame;
+
+/// Why a message wasn't queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum TextError {
+    /// The message has more characters than a queue entry holds.
+    TooLong,
+    /// `Q` messages are already waiting.
+    QueueFull,
+}
+
+/// Scrolls queued messages of up to `N` characters, `Q` of them waiting at most.
+pub struct TextScroller<const N: usize, const Q: usize, const G: usize = 8> {
+    font: Font<G>,
+    text: Glyphs<N>,
+    queue: Deque<Vec<char, N>, Q>,
+    /// Ticks per column.
+    speed: u8,
+    wait: u8,
+    repeat: u8,
+    /// Passes of the message left, including the one scrolling, 0 for until
+    /// another message is queued.
+    passes: u8,
+    /// Nothing is scrolling.
+    idle: bool,
+}
+
+impl<const N: usize, const Q: usize, const G: usize> TextScroller<N, Q, G> {
+    /// Scrolls a column each tick and each message once.
+    pub fn new() -> Self {
+        TextScroller {
+            font: Font::new(),
+            text: Glyphs::default(),
+            queue: Deque::new(),
+            speed: 1,
+            wait: 0,
+            repeat: 1,
+            passes: 0,
+            idle: true,
+        }
+    }
+
+    /// Move a column every `ticks` calls to [`Animate::tick`], at least 1.
+    pub fn set_speed(&mut self, ticks: u8) {
+        self.speed = ticks.max(1);
+    }
+
+    /// Scroll each message `count` times, 0 to repeat it until another one is
+    /// queued. Applies from the next message.
+    pub fn set_repeat(&mut self, count: u8) {
+        self.repeat = count;
+    }
+
+    pub fn font(&self) -> &Font<G> {
+        &self.font
+    }
+
+    /// The font to register glyphs with, used from the next message.
+    pub fn font_mut(&mut self) -> &mut Font<G> {
+        &mut self.font
+    }
+
+    /// Scroll `text` after the messages already queued.
+    pub fn push(&mut self, text: &str) -> Result<(), TextError> {
+        let message = Self::message(text)?;
+        self.queue
+            .push_back(message)
+            .map_err(|_| TextError::QueueFull)
+    }
+
+    /// Drop the queue and the message scrolling, and scroll `text` from the
+    /// next tick. Leaves both alone if `text` is too long.
+    pub fn show_text(&mut self, text: &str) -> Result<(), TextError> {
+        let message = Self::message(text)?;
+        self.queue.clear();
+        self.idle = true;
+        self.queue
+            .push_back(message)
+            .map_err(|_| TextError::QueueFull)
+    }
+
+    /// Messages waiting behind the one scrolling.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// `text` as a queue entry.
+    fn message(text: &str) -> Result<Vec<char, N>, TextError> {
+        let mut message = Vec::new();
+        for c in text.chars() {
+            message.push(c).map_err(|_| TextError::TooLong)?;
+        }
+        Ok(message)
+    }
+
+    /// Start the next message, if there is one.
+    fn next(&mut self) {
+        match self.queue.pop_front() {
+            Some(message) => {
+                let font = &self.font;
+                self.text.glyphs = message.iter().map(|&c| font.glyph(c)).collect();
+                self.text.state.reset();
+                self.passes = self.repeat;
+                self.idle = false;
+            }
+            None => self.idle = true,
+        }
+    }
+
+    /// A pass of the message is over, scroll it again or move on.
+    fn pass_done(&mut self) {
+        let again = match self.passes {
+            0 => self.queue.is_empty(),
+            _ => {
+                self.passes -= 1;
+                self.passes > 0
+            }
+        };
+        if again {
+            self.text.reset();
+        } else {
+            self.next();
+        }
+    }
+}
+
+impl<const N: usize, const Q: usize, const G: usize> Default for TextScroller<N, Q, G> {
+    fn default() -> Self {
+        TextScroller::new()
+    }
+}
+
+impl<const N: usize, const Q: usize, const G: usize> Animate for TextScroller<N, Q, G> {
+    /// Whether every message has been scrolled.
+    fn is_finished(&self) -> bool {
+        self.idle && self.queue.is_empty()
+    }
+
+    /// Scroll the current message again from the start, if one is scrolling.
+    fn reset(&mut self) {
+        self.text.reset();
+        self.passes = self.repeat;
+        self.wait = 0;
+    }
+
+    fn tick(&mut self) {
+        self.wait += 1;
+        if self.wait < self.speed {
+            return;
+        }
+        self.wait = 0;
+        if self.idle {
+            self.next();
+            if self.idle {
+                return;
+            }
+        }
+        self.text.tick();
+        if self.text.is_finished() {
+            self.pass_done();
+        }
+    }
+}
+
+impl<const N: usize, const Q: usize, const G: usize> Render for TextScroller<N, Q, G> {
+    fn brightness_at(&self, x: usize, y: usize) -> u8 {
+        if self.idle {
+            0
+        } else {
+            self.text.brightness_at(x, y)
+        }
+    }
+}
+
+/// The glyphs of the message scrolling.
+#[derive(Default)]
+struct Glyphs<const N: usize> {
+    glyphs: Vec<Frame, N>,
+    state: ScrollingState,
+}
+
+impl<const N: usize> Scrollable for Glyphs<N> {
+    type Subimage = Frame;
+
+    fn length(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    fn state(&self) -> &ScrollingState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ScrollingState {
+        &mut self.state
+    }
+
+    fn subimage(&self, index: usize) -> &Frame {
+        &self.glyphs[index]
+    }
+}
+
+impl<const N: usize> Render for Glyphs<N> {
+    fn brightness_at(&self, x: usize, y: usize) -> u8 {
+        self.current_brightness_at(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::images;
+
+    type Scroller = TextScroller<8, 2, 1>;
+
+    fn column(frame: &Frame, x: usize) -> [u8; 5] {
+        core::array::from_fn(|y| frame.pixel(x as i32, y as i32))
+    }
+
+    /// The columns of `text`'s glyphs side by side.
+    fn glyph_columns(font: &Font<1>, text: &str) -> Vec<[u8; 5]> {
+        text.chars()
+            .flat_map(|c| {
+                let glyph = font.glyph(c);
+                (0..5).map(move |x| column(&glyph, x))
+            })
+            .collect()
+    }
+
+    /// The columns entering on the right over `count` ticks.
+    fn scrolled(scroller: &mut Scroller, count: usize) -> Vec<[u8; 5]> {
+        (0..count)
+            .map(|_| {
+                scroller.tick();
+                column(&Frame::from_render(scroller), 4)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_glyphs() {
+        let mut scroller = Scroller::new();
+        scroller.push("Hi!").unwrap();
+        let expected = glyph_columns(scroller.font(), "Hi!");
+        assert_eq!(scrolled(&mut scroller, 15), expected);
+        // The last glyph fills the display, then leaves on the left
+        assert_eq!(Frame::from_render(&scroller), scroller.font().glyph('!'));
+        assert_eq!(scrolled(&mut scroller, 4), [[0; 5]; 4]);
+        assert!(!scroller.is_finished());
+        scroller.tick();
+        assert!(scroller.is_finished());
+        assert_eq!(Frame::from_render(&scroller), Frame::new());
+    }
+
+    #[test]
+    fn renders_fallbacks_and_placeholder() {
+        let mut scroller = Scroller::new();
+        scroller.font_mut().register('☺', images::HAPPY).unwrap();
+        scroller.push("å中☺").unwrap();
+        let columns = scrolled(&mut scroller, 15);
+        assert_eq!(columns[..5], glyph_columns(scroller.font(), "a"));
+        assert_eq!(columns[5], [9; 5]);
+        assert_eq!(columns[5..10], glyph_columns(scroller.font(), "中"));
+        assert_eq!(Frame::from_render(&scroller), images::HAPPY);
+    }
+
+    #[test]
+    fn messages_in_turn() {
+        let mut scroller = Scroller::new();
+        assert!(scroller.is_finished());
+        scroller.push("ab").unwrap();
+        scroller.push("c").unwrap();
+        assert_eq!(scroller.push("d"), Err(TextError::QueueFull));
+        assert_eq!(scroller.push("123456789"), Err(TextError::TooLong));
+
+        let columns = scrolled(&mut scroller, 15 + 10);
+        assert_eq!(columns[..10], glyph_columns(scroller.font(), "ab"));
+        assert_eq!(columns[15..20], glyph_columns(scroller.font(), "c"));
+        assert_eq!(scroller.queued(), 0);
+        assert!(scroller.is_finished());
+    }
+
+    #[test]
+    fn show_text_replaces() {
+        let mut scroller = Scroller::new();
+        scroller.push("ab").unwrap();
+        scroller.push("c").unwrap();
+        scrolled(&mut scroller, 3);
+        scroller.show_text("x").unwrap();
+        assert_eq!(
+            scrolled(&mut scroller, 5),
+            glyph_columns(scroller.font(), "x")
+        );
+    }
+
+    #[test]
+    fn show_text_too_long_keeps_messages() {
+        let mut scroller = Scroller::new();
+        scroller.push("ab").unwrap();
+        scroller.push("c").unwrap();
+        scrolled(&mut scroller, 3);
+        assert_eq!(scroller.show_text("123456789"), Err(TextError::TooLong));
+        assert_eq!(scroller.queued(), 1);
+        let columns = scrolled(&mut scroller, 7 + 10);
+        assert_eq!(columns[..7], glyph_columns(scroller.font(), "ab")[3..]);
+        assert_eq!(columns[12..17], glyph_columns(scroller.font(), "c"));
+    }
+}
@@ -1,46 +1,587 @@
+//! Buffered UARTE port.
+//!
+//! Bytes are received by EasyDMA into two chunk buffers taking turns, chained by
+//! the ENDRX_STARTRX short so reception never stops, and moved into an RX ring
+//! buffer as they arrive. Queued bytes are sent from a TX ring buffer in DMA
+//! chunks. EasyDMA needs memory that outlives the port, so each port is given
+//! its own `&'static mut` [`DmaBuffers`], which lets UARTE0 and UARTE1 be used
+//! side by side. The buffers are serviced from [`UartePort::on_interrupt`],
+//! bound to the UARTE interrupt after [`UartePort::listen`] in RTIC apps.
+//! Without interrupts the blocking trait implementations service the port while
+//! they wait.
+//!
+//! Besides `core::fmt::Write` the port implements the serial traits of
+//! embedded-hal 0.2 and `embedded-hal-nb`, and `embedded_io::{Read, Write}`. Line
+//! errors are cleared as they happen and counted in [`UartePort::line_errors`],
+//! so the newer traits use [`Infallible`](core::convert::Infallible) errors.
+//!
+//! Under an async executor the port implements `embedded_io_async::{Read, Write}`
+//! instead, with the UARTE interrupt bound to [`wake`]. The port is serviced
+//! while a read, write or flush is awaited.
+//!
+//! ```ignore
+//! let buffers = cortex_m::singleton!(: DmaBuffers = DmaBuffers::new()).unwrap();
+//! let serial = UartePort::new(uarte, buffers);
+//!
+//! #[task(binds = UARTE0_UART0, shared = [serial])]
+//! fn uarte0(mut cx: uarte0::Context) {
+//!     cx.shared.serial.lock(|serial| serial.on_interrupt());
+//! }
+//!
+//! // Async
+//! #[interrupt]
+//! fn UARTE0_UART0() {
+//!     serial_setup::wake::<pac::UARTE0>();
+//! }
+//!
+//! let len = embedded_io_async::Read::read(&mut serial, &mut buf).await?;
+//! ```
+
+use core::convert::Infallible;
 use core::fmt;
+use core::future::poll_fn;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+use core::task::{Context, Poll};
 use embedded_hal::blocking::serial as bserial;
 use embedded_hal::serial;
-use microbit::hal::uarte::{Error, Instance, Uarte, UarteRx, UarteTx};
+use embedded_hal_nb::serial as serial_nb;
+use heapless::spsc::Queue;
+use microbit::hal::uarte::{Instance, Pins, Uarte};
+use microbit::pac::{uarte0, UARTE0, UARTE1};
+
+use crate::waker::WakerCell;
+
+pub use microbit::hal::uarte::Error;
+
+/// Largest number of bytes handed to EasyDMA per transmission.
+const TX_CHUNK_SIZE: usize = 32;
+/// Bytes of each of the two RX chunk buffers. The port has to be serviced
+/// before a whole chunk is received, 1.4ms at 115200 baud, or the chunk
+/// buffers are overwritten.
+const RX_CHUNK_SIZE: usize = 16;
+
+/// EasyDMA transfer memory of one port.
+pub struct DmaBuffers {
+    tx: [u8; TX_CHUNK_SIZE],
+    rx: [[u8; RX_CHUNK_SIZE]; 2],
+}
+
+impl DmaBuffers {
+    pub const fn new() -> DmaBuffers {
+        DmaBuffers {
+            tx: [0; TX_CHUNK_SIZE],
+            rx: [[0; RX_CHUNK_SIZE]; 2],
+        }
+    }
+}
 
-static mut TX_BUF: [u8; 1] = [0; 1];
-static mut RX_BUF: [u8; 1] = [0; 1];
+impl Default for DmaBuffers {
+    fn default() -> DmaBuffers {
+        DmaBuffers::new()
+    }
+}
+
+/// Returned when the TX ring buffer can't take the whole message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct TxFull;
+
+/// Line errors the UARTE reported, by kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct LineErrors {
+    /// A byte arrived before the previous one was taken and was lost.
+    pub overrun: u32,
+    pub parity: u32,
+    /// A byte without a valid stop bit, often a baud rate mismatch.
+    pub framing: u32,
+    /// The line was held low for longer than a byte.
+    pub break_: u32,
+}
 
-pub struct UartePort<T: Instance>(UarteTx<T>, UarteRx<T>);
+/// A UARTE port with `RX` and `TX` byte ring buffers.
+///
+/// The ring buffers hold one byte less than their size.
+pub struct UartePort<T: Instance, const RX: usize = 64, const TX: usize = 64> {
+    uarte: T,
+    _pins: Pins,
+    dma: &'static mut DmaBuffers,
+    rx: Queue<u8, RX>,
+    tx: Queue<u8, TX>,
+    tx_busy: bool,
+    /// Chunk buffer EasyDMA receives into after the current one.
+    rx_next: usize,
+    /// Bytes received since the start, counted by RXDRDY and ENDRX. Chunk `n`
+    /// holds bytes `n * RX_CHUNK_SIZE` on, in chunk buffer `n % 2`.
+    rx_received: u32,
+    /// Bytes moved from the chunk buffers into the RX buffer.
+    rx_taken: u32,
+    /// Chunks received.
+    rx_chunks: u32,
+    rx_overflows: u32,
+    tx_overflows: u32,
+    line_errors: LineErrors,
+}
 
 impl<T: Instance> UartePort<T> {
-    pub fn new(serial: Uarte<T>) -> UartePort<T> {
-        let (tx, rx) = serial
-            .split(unsafe { &mut TX_BUF }, unsafe { &mut RX_BUF })
-            .unwrap();
-        UartePort(tx, rx)
+    pub fn new(serial: Uarte<T>, dma: &'static mut DmaBuffers) -> UartePort<T> {
+        UartePort::with_capacity(serial, dma)
+    }
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> UartePort<T, RX, TX> {
+    /// Create a port with custom ring buffer sizes, e.g. `UartePort::<_, 256, 32>::with_capacity(uarte, dma)`.
+    pub fn with_capacity(serial: Uarte<T>, dma: &'static mut DmaBuffers) -> Self {
+        let (uarte, pins) = serial.free();
+        let mut port = UartePort {
+            uarte,
+            _pins: pins,
+            dma,
+            rx: Queue::new(),
+            tx: Queue::new(),
+            tx_busy: false,
+            rx_next: 0,
+            rx_received: 0,
+            rx_taken: 0,
+            rx_chunks: 0,
+            rx_overflows: 0,
+            tx_overflows: 0,
+            line_errors: LineErrors::default(),
+        };
+        port.start_rx();
+        port
+    }
+
+    /// Raise the UARTE interrupt whenever a byte arrives or a DMA chunk is sent.
+    pub fn listen(&mut self) {
+        self.uarte.intenset.write(|w| {
+            w.rxdrdy().set();
+            w.rxstarted().set();
+            w.endrx().set();
+            w.endtx().set();
+            w.error().set()
+        });
+    }
+
+    pub fn unlisten(&mut self) {
+        unlisten(&self.uarte);
+    }
+
+    /// Whether the UARTE interrupt services the port.
+    fn is_listening(&self) -> bool {
+        self.uarte.inten.read().endtx().is_enabled()
+    }
+
+    /// Move received bytes into the RX buffer and keep transmitting the TX buffer.
+    pub fn on_interrupt(&mut self) {
+        if self.uarte.events_error.read().bits() != 0 {
+            self.uarte.events_error.reset();
+            // Writing ones clears the error flags
+            let errors = self.uarte.errorsrc.read();
+            self.uarte
+                .errorsrc
+                .write(|w| unsafe { w.bits(errors.bits()) });
+            let counts = &mut self.line_errors;
+            counts.overrun += u32::from(errors.overrun().is_present());
+            counts.parity += u32::from(errors.parity().is_present());
+            counts.framing += u32::from(errors.framing().is_present());
+            counts.break_ += u32::from(errors.break_().is_present());
+        }
+
+        if self.uarte.events_rxdrdy.read().bits() != 0 {
+            self.uarte.events_rxdrdy.reset();
+            self.rx_received = self.rx_received.wrapping_add(1);
+        }
+        if self.uarte.events_endrx.read().bits() != 0 {
+            self.uarte.events_endrx.reset();
+            self.rx_chunks = self.rx_chunks.wrapping_add(1);
+            // RXDRDYs run together when the port is serviced late, and the one
+            // of the last byte may have come after it was checked above
+            let end = self.rx_chunks.wrapping_mul(RX_CHUNK_SIZE as u32);
+            if (end.wrapping_sub(self.rx_received) as i32) > 0 {
+                self.uarte.events_rxdrdy.reset();
+                self.rx_received = end;
+            }
+        }
+        if self.uarte.events_rxstarted.read().bits() != 0 {
+            // The short starts the next chunk with the pointer set now
+            self.uarte.events_rxstarted.reset();
+            self.set_rx_ptr();
+        }
+        self.take_received();
+
+        if self.uarte.events_endtx.read().bits() != 0 {
+            self.uarte.events_endtx.reset();
+            self.tx_busy = false;
+        }
+        self.start_tx();
+    }
+
+    /// Queue the whole of `data` for sending, or nothing if it doesn't fit.
+    pub fn try_write_all(&mut self, data: &[u8]) -> Result<(), TxFull> {
+        if TX - 1 - self.tx.len() < data.len() {
+            self.tx_overflows += data.len() as u32;
+            return Err(TxFull);
+        }
+        for &byte in data {
+            self.tx.enqueue(byte).ok();
+        }
+        self.start_tx();
+        Ok(())
+    }
+
+    /// Move received bytes into `buf`, returns how many were copied.
+    pub fn read_available(&mut self, buf: &mut [u8]) -> usize {
+        let mut count = 0;
+        for slot in buf.iter_mut() {
+            match self.rx.dequeue() {
+                Some(byte) => *slot = byte,
+                None => break,
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Bytes waiting in the RX buffer.
+    pub fn rx_len(&self) -> usize {
+        self.rx.len()
+    }
+
+    /// Room left in the TX buffer.
+    pub fn tx_free(&self) -> usize {
+        TX - 1 - self.tx.len()
+    }
+
+    /// Whether everything queued has been sent.
+    pub fn is_tx_idle(&self) -> bool {
+        !self.tx_busy && self.tx.is_empty()
+    }
+
+    /// Bytes dropped because the RX buffer was full.
+    pub fn rx_overflows(&self) -> u32 {
+        self.rx_overflows
+    }
+
+    /// Bytes rejected by [`UartePort::try_write_all`] because the TX buffer was full.
+    pub fn tx_overflows(&self) -> u32 {
+        self.tx_overflows
+    }
+
+    /// Line errors since the port was created.
+    pub fn line_errors(&self) -> LineErrors {
+        self.line_errors
+    }
+
+    /// Queue as much of `data` as fits, returns how many bytes were queued.
+    fn queue(&mut self, data: &[u8]) -> usize {
+        let len = data.len().min(self.tx_free());
+        for &byte in &data[..len] {
+            self.tx.enqueue(byte).ok();
+        }
+        self.start_tx();
+        len
+    }
+
+    fn poll_read(&mut self) -> Option<u8> {
+        self.on_interrupt();
+        self.rx.dequeue()
+    }
+
+    fn poll_write(&mut self, byte: u8) -> bool {
+        self.on_interrupt();
+        if self.tx.enqueue(byte).is_err() {
+            return false;
+        }
+        self.start_tx();
+        true
+    }
+
+    fn poll_flush(&mut self) -> bool {
+        self.on_interrupt();
+        self.is_tx_idle()
+    }
+
+    fn start_rx(&mut self) {
+        self.uarte
+            .rxd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(RX_CHUNK_SIZE as _) });
+        self.uarte.shorts.write(|w| w.endrx_startrx().enabled());
+        self.set_rx_ptr();
+        self.uarte.tasks_startrx.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Point EasyDMA at the chunk buffer after the one it receives into.
+    fn set_rx_ptr(&mut self) {
+        let ptr = self.dma.rx[self.rx_next].as_mut_ptr() as u32;
+        compiler_fence(SeqCst);
+        self.uarte.rxd.ptr.write(|w| unsafe { w.ptr().bits(ptr) });
+        self.rx_next ^= 1;
+    }
+
+    /// Move the bytes received since the last call into the RX buffer. A byte
+    /// is written to RAM right after its RXDRDY.
+    fn take_received(&mut self) {
+        compiler_fence(SeqCst);
+        while self.rx_taken != self.rx_received {
+            let at = self.rx_taken as usize;
+            let chunk = &self.dma.rx[at / RX_CHUNK_SIZE % 2];
+            let byte = unsafe { (&chunk[at % RX_CHUNK_SIZE] as *const u8).read_volatile() };
+            if self.rx.enqueue(byte).is_err() {
+                self.rx_overflows += 1;
+            }
+            self.rx_taken = self.rx_taken.wrapping_add(1);
+        }
+    }
+
+    fn start_tx(&mut self) {
+        if self.tx_busy || self.tx.is_empty() {
+            return;
+        }
+        let chunk = &mut self.dma.tx;
+        let mut len = 0;
+        while len < TX_CHUNK_SIZE {
+            match self.tx.dequeue() {
+                Some(byte) => chunk[len] = byte,
+                None => break,
+            }
+            len += 1;
+        }
+        compiler_fence(SeqCst);
+        self.uarte
+            .txd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(chunk.as_ptr() as u32) });
+        self.uarte
+            .txd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(len as _) });
+        self.uarte.tasks_starttx.write(|w| unsafe { w.bits(1) });
+        self.tx_busy = true;
     }
 }
 
-impl<T: Instance> fmt::Write for UartePort<T> {
+impl<T: Instance, const RX: usize, const TX: usize> fmt::Write for UartePort<T, RX, TX> {
+    /// Without [`UartePort::listen`] nothing else sends the TX buffer, so this
+    /// waits until all of it is sent.
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        self.0.write_str(s)
+        for &byte in s.as_bytes() {
+            nb::block!(serial::Write::write(self, byte)).map_err(|_| fmt::Error)?;
+        }
+        if !self.is_listening() {
+            nb::block!(serial::Write::flush(self)).map_err(|_| fmt::Error)?;
+        }
+        Ok(())
     }
 }
 
-impl<T: Instance> serial::Write<u8> for UartePort<T> {
+impl<T: Instance, const RX: usize, const TX: usize> serial::Write<u8> for UartePort<T, RX, TX> {
     type Error = Error;
 
     fn write(&mut self, b: u8) -> nb::Result<(), Self::Error> {
-        self.0.write(b)
+        self.poll_write(b)
+            .then_some(())
+            .ok_or(nb::Error::WouldBlock)
     }
 
     fn flush(&mut self) -> nb::Result<(), Self::Error> {
-        self.0.flush()
+        self.poll_flush().then_some(()).ok_or(nb::Error::WouldBlock)
     }
 }
 
-impl<T: Instance> bserial::write::Default<u8> for UartePort<T> {}
+impl<T: Instance, const RX: usize, const TX: usize> bserial::write::Default<u8>
+    for UartePort<T, RX, TX>
+{
+}
 
-impl<T: Instance> serial::Read<u8> for UartePort<T> {
+impl<T: Instance, const RX: usize, const TX: usize> serial::Read<u8> for UartePort<T, RX, TX> {
     type Error = Error;
 
     fn read(&mut self) -> nb::Result<u8, Self::Error> {
-        self.1.read()
+        self.poll_read().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> serial_nb::ErrorType for UartePort<T, RX, TX> {
+    type Error = Infallible;
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> serial_nb::Write for UartePort<T, RX, TX> {
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.poll_write(word)
+            .then_some(())
+            .ok_or(nb::Error::WouldBlock)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.poll_flush().then_some(()).ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> serial_nb::Read for UartePort<T, RX, TX> {
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.poll_read().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> embedded_io::ErrorType
+    for UartePort<T, RX, TX>
+{
+    type Error = Infallible;
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> embedded_io::Read for UartePort<T, RX, TX> {
+    /// Waits for at least one byte, then returns what the RX buffer holds.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            self.on_interrupt();
+            let len = self.read_available(buf);
+            if len > 0 {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> embedded_io::ReadReady
+    for UartePort<T, RX, TX>
+{
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        self.on_interrupt();
+        Ok(!self.rx.is_empty())
+    }
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> embedded_io::Write for UartePort<T, RX, TX> {
+    /// Waits for room in the TX buffer, then queues as much of `buf` as fits.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            self.on_interrupt();
+            let len = self.queue(buf);
+            if len > 0 {
+                return Ok(len);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        while !self.poll_flush() {}
+        Ok(())
+    }
+}
+
+impl<T: Instance, const RX: usize, const TX: usize> embedded_io::WriteReady
+    for UartePort<T, RX, TX>
+{
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        self.on_interrupt();
+        Ok(self.tx_free() > 0)
+    }
+}
+
+/// A UARTE instance that async tasks can wait on.
+pub trait AsyncInstance: Instance {
+    fn waker() -> &'static WakerCell;
+}
+
+impl AsyncInstance for UARTE0 {
+    fn waker() -> &'static WakerCell {
+        static WAKER: WakerCell = WakerCell::new();
+        &WAKER
+    }
+}
+
+impl AsyncInstance for UARTE1 {
+    fn waker() -> &'static WakerCell {
+        static WAKER: WakerCell = WakerCell::new();
+        &WAKER
+    }
+}
+
+/// Wake the task waiting on `T`, called from the UARTE interrupt.
+///
+/// The interrupt stays disabled until the task waits again, the port itself is
+/// serviced by the task.
+pub fn wake<T: AsyncInstance>() {
+    unlisten(unsafe { &*T::ptr() });
+    T::waker().wake();
+}
+
+fn unlisten(uarte: &uarte0::RegisterBlock) {
+    uarte.intenclr.write(|w| {
+        w.rxdrdy().clear();
+        w.rxstarted().clear();
+        w.endrx().clear();
+        w.endtx().clear();
+        w.error().clear()
+    });
+}
+
+impl<T: AsyncInstance, const RX: usize, const TX: usize> UartePort<T, RX, TX> {
+    /// Have the next UARTE event wake the task.
+    fn wait(&mut self, cx: &mut Context<'_>) {
+        T::waker().register(cx.waker());
+        self.listen();
+    }
+}
+
+impl<T: AsyncInstance, const RX: usize, const TX: usize> embedded_io_async::Read
+    for UartePort<T, RX, TX>
+{
+    /// Waits for at least one byte, then returns what the RX buffer holds.
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        poll_fn(|cx| {
+            self.on_interrupt();
+            match self.read_available(buf) {
+                0 => {
+                    self.wait(cx);
+                    Poll::Pending
+                }
+                len => Poll::Ready(Ok(len)),
+            }
+        })
+        .await
+    }
+}
+
+impl<T: AsyncInstance, const RX: usize, const TX: usize> embedded_io_async::Write
+    for UartePort<T, RX, TX>
+{
+    /// Waits for room in the TX buffer, then queues as much of `buf` as fits.
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        poll_fn(|cx| {
+            self.on_interrupt();
+            match self.queue(buf) {
+                0 => {
+                    self.wait(cx);
+                    Poll::Pending
+                }
+                len => Poll::Ready(Ok(len)),
+            }
+        })
+        .await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        poll_fn(|cx| {
+            if self.poll_flush() {
+                Poll::Ready(Ok(()))
+            } else {
+                self.wait(cx);
+                Poll::Pending
+            }
+        })
+        .await
     }
 }
@@ -0,0 +1,406 @@
+//! Command shell for text consoles.
+//!
+//! Applications describe their commands in a table and hand every input line to
+//! [`Shell::execute`], which finds the command, runs its handler with the rest of
+//! the line as [`Args`] and reports errors. Names may have several words, so
+//! `mag rate` and `mag show` can be separate entries. A `help` command listing
+//! the table is built in.
+//!
+//! ```ignore
+//! fn rate(sensor: &mut Sensor, args: &mut Args, out: &mut dyn Write) -> Result<(), Error> {
+//!     let odr = args.choice("hz", &[("10", Hz10), ("50", Hz50)])?;
+//!     args.finish()?;
+//!     sensor.set_mag_odr(odr).map_err(|_| Error::Failed("i2c error"))?;
+//!     Ok(write!(out, "rate set\r\n")?)
+//! }
+//!
+//! const COMMANDS: &[Command<Sensor>] = &[Command {
+//!     name: "mag rate",
+//!     usage: "<10|50>",
+//!     help: "set the magnetometer data rate",
+//!     run: rate,
+//! }];
+//!
+//! Shell::new(COMMANDS).execute(&mut sensor, line, &mut serial)?;
+//! ```
+
+use core::fmt::{self, Write};
+use core::str::{FromStr, SplitAsciiWhitespace};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    UnknownCommand,
+    /// The named argument wasn't given.
+    MissingArgument(&'static str),
+    /// The named argument couldn't be parsed.
+    InvalidArgument(&'static str),
+    TooManyArguments,
+    /// The command ran but didn't succeed.
+    Failed(&'static str),
+    /// Writing the output failed.
+    Output,
+}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Error {
+        Error::Output
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownCommand => f.write_str("unknown command, try 'help'"),
+            Error::MissingArgument(name) => write!(f, "missing <{}>", name),
+            Error::InvalidArgument(name) => write!(f, "invalid <{}>", name),
+            Error::TooManyArguments => f.write_str("too many arguments"),
+            Error::Failed(reason) => f.write_str(reason),
+            Error::Output => f.write_str("output error"),
+        }
+    }
+}
+
+/// Runs a command on the application state `C`.
+pub type Handler<C> = fn(&mut C, &mut Args<'_>, &mut dyn Write) -> Result<(), Error>;
+
+pub struct Command<C> {
+    /// One or more words.
+    pub name: &'static str,
+    /// Arguments as shown by `help`, e.g. `<hz>`.
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub run: Handler<C>,
+}
+
+/// The words following a command name.
+#[derive(Clone)]
+pub struct Args<'a> {
+    words: SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> Args<'a> {
+    pub fn new(line: &'a str) -> Self {
+        Args {
+            words: line.split_ascii_whitespace(),
+        }
+    }
+
+    /// The next word, `name` is used in the error if there is none.
+    pub fn word(&mut self, name: &'static str) -> Result<&'a str, Error> {
+        self.words.next().ok_or(Error::MissingArgument(name))
+    }
+
+    /// The next word, if any.
+    pub fn optional(&mut self) -> Option<&'a str> {
+        self.words.next()
+    }
+
+    /// Parse the next word, e.g. as an integer or a float.
+    pub fn parse<T: FromStr>(&mut self, name: &'static str) -> Result<T, Error> {
+        self.word(name)?
+            .parse()
+            .map_err(|_| Error::InvalidArgument(name))
+    }
+
+    /// Parse the next word or use `default` when the line ends here.
+    pub fn parse_or<T: FromStr>(&mut self, name: &'static str, default: T) -> Result<T, Error> {
+        match self.optional() {
+            Some(word) => word.parse().map_err(|_| Error::InvalidArgument(name)),
+            None => Ok(default),
+        }
+    }
+
+    /// Map the next word to one of `choices`, for enums.
+    pub fn choice<T: Copy>(
+        &mut self,
+        name: &'static str,
+        choices: &[(&str, T)],
+    ) -> Result<T, Error> {
+        let word = self.word(name)?;
+        choices
+            .iter()
+            .find(|(choice, _)| *choice == word)
+            .map(|(_, value)| *value)
+            .ok_or(Error::InvalidArgument(name))
+    }
+
+    /// Check that all the arguments were used.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        match self.words.next() {
+            Some(_) => Err(Error::TooManyArguments),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Dispatches lines to a table of commands.
+pub struct Shell<'a, C> {
+    commands: &'a [Command<C>],
+}
+
+impl<'a, C> Shell<'a, C> {
+    pub const fn new(commands: &'a [Command<C>]) -> Self {
+        Shell { commands }
+    }
+
+    pub fn commands(&self) -> &'a [Command<C>] {
+        self.commands
+    }
+
+    /// Run `line`, writing any error followed by the command usage to `out`.
+    pub fn execute(&self, context: &mut C, line: &str, out: &mut dyn Write) -> fmt::Result {
+        match self.run(context, line, out) {
+            Ok(()) => Ok(()),
+            Err(Error::Output) => Err(fmt::Error),
+            Err(error) => {
+                write!(out, "error: {}\r\n", error)?;
+                match (error, self.find(line)) {
+                    (
+                        Error::MissingArgument(_)
+                        | Error::InvalidArgument(_)
+                        | Error::TooManyArguments,
+                        Some((command, _)),
+                    ) => {
+                        out.write_str("usage: ")?;
+                        write_usage(out, command)?;
+                        out.write_str("\r\n")
+                    }
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Run `line`, a blank line does nothing.
+    pub fn run(&self, context: &mut C, line: &str, out: &mut dyn Write) -> Result<(), Error> {
+        if let Some((command, mut args)) = self.find(line) {
+            return (command.run)(context, &mut args, out);
+        }
+        let mut args = Args::new(line);
+        match args.optional() {
+            None => Ok(()),
+            Some("help") => self.help(args, out),
+            Some(_) => Err(Error::UnknownCommand),
+        }
+    }
+
+    /// List the commands starting with the words in `topic`, all of them if it's empty.
+    pub fn help(&self, topic: Args<'_>, out: &mut dyn Write) -> Result<(), Error> {
+        let matching = |command: &&Command<C>| starts_with(command.name, topic.clone());
+        let width = self
+            .commands
+            .iter()
+            .filter(matching)
+            .map(usage_len)
+            .max()
+            .ok_or(Error::UnknownCommand)?;
+        for command in self.commands.iter().filter(matching) {
+            out.write_str("  ")?;
+            let len = write_usage(out, command)?;
+            for _ in len..width {
+                out.write_char(' ')?;
+            }
+            write!(out, "  {}\r\n", command.help)?;
+        }
+        Ok(())
+    }
+
+    /// The command with the longest name matching the start of `line`.
+    fn find<'l>(&self, line: &'l str) -> Option<(&'a Command<C>, Args<'l>)> {
+        let mut found = None;
+        let mut found_words = 0;
+        for command in self.commands {
+            let mut args = Args::new(line);
+            let words = command.name.split_ascii_whitespace().count();
+            if words > found_words && consume_words(&mut args, command.name) {
+                found = Some((command, args));
+                found_words = words;
+            }
+        }
+        found
+    }
+}
+
+/// Write the name and arguments of `command`, returns the length written.
+fn write_usage<C>(out: &mut dyn Write, command: &Command<C>) -> Result<usize, fmt::Error> {
+    out.write_str(command.name)?;
+    if !command.usage.is_empty() {
+        out.write_char(' ')?;
+        out.write_str(command.usage)?;
+    }
+    Ok(usage_len(command))
+}
+
+fn usage_len<C>(command: &Command<C>) -> usize {
+    match command.usage.len() {
+        0 => command.name.len(),
+        len => command.name.len() + 1 + len,
+    }
+}
+
+/// Consume the words of `name` from `args`, false if they don't match.
+fn consume_words(args: &mut Args<'_>, name: &str) -> bool {
+    name.split_ascii_whitespace()
+        .all(|word| args.optional() == Some(word))
+}
+
+/// Whether the words of `topic` begin `name`.
+fn starts_with(name: &str, mut topic: Args<'_>) -> bool {
+    let mut name = name.split_ascii_whitespace();
+    topic.words.all(|word| name.next() == Some(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Context {
+        rate: u32,
+        shown: bool,
+    }
+
+    fn rate(context: &mut Context, args: &mut Args, _: &mut dyn Write) -> Result<(), Error> {
+        context.rate = args.choice("hz", &[("10", 10), ("50", 50)])?;
+        args.finish()
+    }
+
+    fn show(context: &mut Context, args: &mut Args, out: &mut dyn Write) -> Result<(), Error> {
+        let times: u32 = args.parse_or("times", 1)?;
+        args.finish()?;
+        context.shown = true;
+        for _ in 0..times {
+            out.write_str("shown\r\n")?;
+        }
+        Ok(())
+    }
+
+    fn mag(_: &mut Context, args: &mut Args, _: &mut dyn Write) -> Result<(), Error> {
+        let _: i32 = args.parse("offset")?;
+        args.finish()
+    }
+
+    fn fail(_: &mut Context, _: &mut Args, _: &mut dyn Write) -> Result<(), Error> {
+        Err(Error::Failed("sensor asleep"))
+    }
+
+    const COMMANDS: &[Command<Context>] = &[
+        Command {
+            name: "mag",
+            usage: "<offset>",
+            help: "set the offset",
+            run: mag,
+        },
+        Command {
+            name: "mag rate",
+            usage: "<10|50>",
+            help: "set the data rate",
+            run: rate,
+        },
+        Command {
+            name: "mag show",
+            usage: "[times]",
+            help: "show a reading",
+            run: show,
+        },
+        Command {
+            name: "wake",
+            usage: "",
+            help: "wake the sensor",
+            run: fail,
+        },
+    ];
+
+    fn execute(line: &str) -> (Context, String) {
+        let mut context = Context::default();
+        let mut out = String::new();
+        Shell::new(COMMANDS)
+            .execute(&mut context, line, &mut out)
+            .unwrap();
+        (context, out)
+    }
+
+    #[test]
+    fn dispatches_to_the_longest_name() {
+        let (context, out) = execute("  mag   rate 50 ");
+        assert_eq!(context.rate, 50);
+        assert_eq!(out, "");
+
+        let (context, out) = execute("mag show 2");
+        assert!(context.shown);
+        assert_eq!(out, "shown\r\nshown\r\n");
+
+        // "mag" itself, with "rate" not a whole word
+        let mut context = Context::default();
+        let shell = Shell::new(COMMANDS);
+        assert_eq!(
+            shell.run(&mut context, "mag rates", &mut String::new()),
+            Err(Error::InvalidArgument("offset"))
+        );
+        assert_eq!(
+            shell.run(&mut context, "mag -3", &mut String::new()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn blank_and_unknown_lines() {
+        assert_eq!(execute("   ").1, "");
+        assert_eq!(execute("accel").1, "error: unknown command, try 'help'\r\n");
+    }
+
+    #[test]
+    fn argument_errors_show_the_usage() {
+        assert_eq!(
+            execute("mag rate").1,
+            "error: missing <hz>\r\nusage: mag rate <10|50>\r\n"
+        );
+        assert_eq!(
+            execute("mag rate 20").1,
+            "error: invalid <hz>\r\nusage: mag rate <10|50>\r\n"
+        );
+        assert_eq!(
+            execute("mag rate 10 now").1,
+            "error: too many arguments\r\nusage: mag rate <10|50>\r\n"
+        );
+        assert_eq!(
+            execute("mag show x").1,
+            "error: invalid <times>\r\nusage: mag show [times]\r\n"
+        );
+    }
+
+    #[test]
+    fn failures_are_reported_without_usage() {
+        assert_eq!(execute("wake").1, "error: sensor asleep\r\n");
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(
+            execute("help").1,
+            "  mag <offset>      set the offset\r\n\
+             \x20 mag rate <10|50>  set the data rate\r\n\
+             \x20 mag show [times]  show a reading\r\n\
+             \x20 wake              wake the sensor\r\n"
+        );
+        assert_eq!(
+            execute("help mag show").1,
+            "  mag show [times]  show a reading\r\n"
+        );
+        assert_eq!(
+            execute("help accel").1,
+            "error: unknown command, try 'help'\r\n"
+        );
+    }
+
+    #[test]
+    fn args() {
+        let mut args = Args::new("7 2.5 x");
+        assert_eq!(args.parse::<u8>("a"), Ok(7));
+        assert_eq!(args.parse::<f32>("b"), Ok(2.5));
+        assert_eq!(args.parse::<u8>("c"), Err(Error::InvalidArgument("c")));
+        assert_eq!(args.word("d"), Err(Error::MissingArgument("d")));
+        assert_eq!(args.parse_or("e", 3), Ok(3));
+        assert_eq!(args.finish(), Ok(()));
+    }
+}
@@ -0,0 +1,191 @@
+//! Streaming of timestamped accelerometer and magnetometer readings.
+//!
+//! Records are written either as CSV lines, ready for a spreadsheet, or as
+//! frames from [`crate::framing`] carrying a little endian [`Record`].
+//! The stream is controlled with text commands terminated by CR or LF:
+//!
+//! - `start` / `stop`
+//! - `rate <hz>`, between 1 and [`MAX_RATE_HZ`]
+//! - `format csv` / `format bin`
+
+use core::fmt;
+
+use lsm303agr::Measurement;
+
+use crate::framing::{self, max_frame_len, FrameError};
+use crate::monotonic::{Duration, ExtU64};
+
+pub const MAX_RATE_HZ: u32 = 100;
+pub const CSV_HEADER: &str = "timestamp_us,accel_x,accel_y,accel_z,mag_x,mag_y,mag_z";
+/// Timestamp and six readings.
+pub const RECORD_SIZE: usize = 4 + 6 * 4;
+pub const FRAME_SIZE: usize = max_frame_len(RECORD_SIZE);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Format {
+    Csv,
+    Binary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Command {
+    Start,
+    Stop,
+    Rate(u32),
+    Format(Format),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Option<Command> {
+        let mut words = line.split_ascii_whitespace();
+        let command = match (words.next()?, words.next()) {
+            ("start", None) => Command::Start,
+            ("stop", None) => Command::Stop,
+            ("rate", Some(rate)) => Command::Rate(rate.parse().ok()?),
+            ("format", Some("csv")) => Command::Format(Format::Csv),
+            ("format", Some("bin")) => Command::Format(Format::Binary),
+            _ => return None,
+        };
+        if words.next().is_some() {
+            return None;
+        }
+        Some(command)
+    }
+}
+
+/// One sample of both sensors, accelerometer in mg and magnetometer in nT.
+#[derive(Debug, Clone, Copy)]
+pub struct Record {
+    pub timestamp_us: u32,
+    pub accel: Measurement,
+    pub mag: Measurement,
+}
+
+impl Record {
+    /// Write the record as one CSV line matching [`CSV_HEADER`].
+    pub fn write_csv<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        write!(
+            w,
+            "{},{},{},{},{},{},{}\r\n",
+            self.timestamp_us,
+            self.accel.x,
+            self.accel.y,
+            self.accel.z,
+            self.mag.x,
+            self.mag.y,
+            self.mag.z
+        )
+    }
+
+    /// Parse a line written by [`Record::write_csv`].
+    pub fn parse_csv(line: &str) -> Option<Record> {
+        let mut fields = line.trim_end().split(',');
+        let timestamp_us = fields.next()?.parse().ok()?;
+        let mut values = [0; 6];
+        for value in values.iter_mut() {
+            *value = fields.next()?.parse().ok()?;
+        }
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Record {
+            timestamp_us,
+            accel: Measurement {
+                x: values[0],
+                y: values[1],
+                z: values[2],
+            },
+            mag: Measurement {
+                x: values[3],
+                y: values[4],
+                z: values[5],
+            },
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        let values = [
+            self.accel.x,
+            self.accel.y,
+            self.accel.z,
+            self.mag.x,
+            self.mag.y,
+            self.mag.z,
+        ];
+        bytes[..4].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        for (chunk, value) in bytes[4..].chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> Record {
+        let word = |i: usize| [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]];
+        let value = |i: usize| i32::from_le_bytes(word(4 + 4 * i));
+        Record {
+            timestamp_us: u32::from_le_bytes(word(0)),
+            accel: Measurement {
+                x: value(0),
+                y: value(1),
+                z: value(2),
+            },
+            mag: Measurement {
+                x: value(3),
+                y: value(4),
+                z: value(5),
+            },
+        }
+    }
+
+    /// Write the record as a binary frame, returns the frame length.
+    pub fn encode_frame(&self, dst: &mut [u8; FRAME_SIZE]) -> Result<usize, FrameError> {
+        framing::encode_frame(&self.to_bytes(), dst)
+    }
+}
+
+/// Streaming state driven by [`Command`]s.
+pub struct Telemetry {
+    running: bool,
+    rate_hz: u32,
+    format: Format,
+}
+
+impl Default for Telemetry {
+    fn default() -> Telemetry {
+        Telemetry {
+            running: false,
+            rate_hz: 10,
+            format: Format::Csv,
+        }
+    }
+}
+
+impl Telemetry {
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn rate_hz(&self) -> u32 {
+        self.rate_hz
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Time between two records at the current rate.
+    pub fn period(&self) -> Duration {
+        (1_000_000 / self.rate_hz as u64).micros()
+    }
+
+    /// Apply a command, the rate is clamped to what the sensors can deliver.
+    pub fn handle(&mut self, command: Command) {
+        match command {
+            Command::Start => self.running = true,
+            Command::Stop => self.running = false,
+            Command::Rate(rate) => self.rate_hz = rate.clamp(1, MAX_RATE_HZ),
+            Command::Format(format) => self.format = format,
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! Software timers sharing one hardware deadline.
+//!
+//! [`Timers`] keeps up to `N` one-shot or periodic timers and only does the
+//! bookkeeping: the time is passed in and the caller sets up its hardware for
+//! [`Timers::next_deadline`], as [`TimerService`] does with one compare channel
+//! of a `MonoTimer`. A timer that fires calls its callback, if it has one, and
+//! raises a flag read with [`Timers::take_fired`].
+//!
+//! ```ignore
+//! const BLINK: TimerId = TimerId(0);
+//!
+//! timers.start_periodic(BLINK, now + 500.millis(), 500.millis(), Some(|| {
+//!     blink::spawn().ok();
+//! }));
+//! timers.fire_due(now);
+//! ```
+
+use crate::monotonic::{Delay, Duration, Instance32, Instant};
+
+/// Index of a timer, below the `N` of its [`Timers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct TimerId(pub usize);
+
+#[derive(Clone, Copy)]
+struct Timer {
+    deadline: Instant,
+    period: Option<Duration>,
+    callback: Option<fn()>,
+}
+
+/// Up to `N` software timers, addressed by [`TimerId`].
+///
+/// Methods taking a [`TimerId`] panic if it isn't below `N`.
+pub struct Timers<const N: usize> {
+    timers: [Option<Timer>; N],
+    fired: [bool; N],
+}
+
+impl<const N: usize> Timers<N> {
+    pub const fn new() -> Self {
+        Timers {
+            timers: [None; N],
+            fired: [false; N],
+        }
+    }
+
+    /// Fire `id` once at `deadline`, replacing what it was set to.
+    pub fn start_once(&mut self, id: TimerId, deadline: Instant, callback: Option<fn()>) {
+        self.timers[id.0] = Some(Timer {
+            deadline,
+            period: None,
+            callback,
+        });
+    }
+
+    /// Fire `id` at `first` and every `period` after, replacing what it was set
+    /// to. Periods missed because the timer wasn't serviced in time are skipped.
+    pub fn start_periodic(
+        &mut self,
+        id: TimerId,
+        first: Instant,
+        period: Duration,
+        callback: Option<fn()>,
+    ) {
+        self.timers[id.0] = Some(Timer {
+            deadline: first,
+            period: Some(period.max(Duration::from_ticks(1))),
+            callback,
+        });
+    }
+
+    /// Stop `id`, a flag it raised is kept.
+    pub fn cancel(&mut self, id: TimerId) {
+        self.timers[id.0] = None;
+    }
+
+    pub fn is_running(&self, id: TimerId) -> bool {
+        self.timers[id.0].is_some()
+    }
+
+    /// Whether `id` fired since the last call.
+    pub fn take_fired(&mut self, id: TimerId) -> bool {
+        core::mem::replace(&mut self.fired[id.0], false)
+    }
+
+    /// When the next timer is due.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers
+            .iter()
+            .flatten()
+            .map(|timer| timer.deadline)
+            .min()
+    }
+
+    /// Fire the timers due at `now`, returns how many fired.
+    pub fn fire_due(&mut self, now: Instant) -> usize {
+        let mut count = 0;
+        for (slot, fired) in self.timers.iter_mut().zip(&mut self.fired) {
+            let Some(timer) = slot else { continue };
+            if timer.deadline > now {
+                continue;
+            }
+            let callback = timer.callback;
+            match timer.period {
+                Some(period) => timer.deadline = next_period(timer.deadline, period, now),
+                None => *slot = None,
+            }
+            *fired = true;
+            count += 1;
+            if let Some(callback) = callback {
+                callback();
+            }
+        }
+        count
+    }
+}
+
+impl<const N: usize> Default for Timers<N> {
+    fn default() -> Self {
+        Timers::new()
+    }
+}
+
+/// [`Timers`] run from the compare channel of a [`Delay`], so many timers share
+/// one hardware TIMER.
+///
+/// The timer interrupt has to call [`TimerService::on_interrupt`], callbacks run
+/// from there.
+///
+/// ```ignore
+/// const POLL: TimerId = TimerId(0);
+///
+/// let (delay, _) = MonoTimer::new(board.TIMER2).into_delays();
+/// let mut timers = TimerService::<_, 4>::new(delay);
+/// timers.start_periodic(POLL, 100.millis(), Some(|| {
+///     poll::spawn().ok();
+/// }));
+///
+/// #[task(binds = TIMER2, shared = [timers])]
+/// fn timer2(mut cx: timer2::Context) {
+///     cx.shared.timers.lock(|timers| timers.on_interrupt());
+/// }
+/// ```
+pub struct TimerService<T: Instance32, const N: usize> {
+    delay: Delay<T>,
+    timers: Timers<N>,
+}
+
+impl<T: Instance32, const N: usize> TimerService<T, N> {
+    pub fn new(delay: Delay<T>) -> Self {
+        TimerService {
+            delay,
+            timers: Timers::new(),
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        self.delay.now()
+    }
+
+    /// Fire `id` once after `duration`, replacing what it was set to.
+    pub fn start_once(&mut self, id: TimerId, duration: Duration, callback: Option<fn()>) {
+        let deadline = self.now() + duration;
+        self.timers.start_once(id, deadline, callback);
+        self.reschedule();
+    }
+
+    /// Fire `id` every `period` from now on, replacing what it was set to.
+    pub fn start_periodic(&mut self, id: TimerId, period: Duration, callback: Option<fn()>) {
+        let first = self.now() + period;
+        self.timers.start_periodic(id, first, period, callback);
+        self.reschedule();
+    }
+
+    pub fn cancel(&mut self, id: TimerId) {
+        self.timers.cancel(id);
+        self.reschedule();
+    }
+
+    pub fn is_running(&self, id: TimerId) -> bool {
+        self.timers.is_running(id)
+    }
+
+    /// Whether `id` fired since the last call.
+    pub fn take_fired(&mut self, id: TimerId) -> bool {
+        self.timers.take_fired(id)
+    }
+
+    /// Fire the timers that are due and wait for the next one.
+    pub fn on_interrupt(&mut self) {
+        self.timers.fire_due(self.now());
+        self.reschedule();
+    }
+
+    fn reschedule(&mut self) {
+        // Timers falling due while the compare is set are fired right away
+        while let Some(deadline) = self.timers.next_deadline() {
+            if !self.delay.arm(deadline) {
+                return;
+            }
+            self.timers.fire_due(self.now());
+        }
+        self.delay.disarm();
+    }
+}
+
+/// The first deadline after `now` in steps of `period` from `deadline`.
+fn next_period(deadline: Instant, period: Duration, now: Instant) -> Instant {
+    let missed = (now - deadline).ticks() / period.ticks();
+    deadline + Duration::from_ticks(period.ticks() * (missed + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::monotonic::ExtU64;
+
+    const A: TimerId = TimerId(0);
+    const B: TimerId = TimerId(1);
+    const C: TimerId = TimerId(2);
+
+    fn at(ms: u64) -> Instant {
+        Instant::from_ticks(0) + ms.millis()
+    }
+
+    #[test]
+    fn once() {
+        let mut timers = Timers::<3>::new();
+        assert_eq!(timers.next_deadline(), None);
+        timers.start_once(A, at(100), None);
+        assert_eq!(timers.next_deadline(), Some(at(100)));
+
+        assert_eq!(timers.fire_due(at(99)), 0);
+        assert!(!timers.take_fired(A));
+        assert_eq!(timers.fire_due(at(100)), 1);
+        assert!(!timers.is_running(A));
+        assert_eq!(timers.next_deadline(), None);
+        assert!(timers.take_fired(A));
+        assert!(!timers.take_fired(A));
+        assert_eq!(timers.fire_due(at(1000)), 0);
+    }
+
+    #[test]
+    fn periodic_without_drift() {
+        let mut timers = Timers::<3>::new();
+        timers.start_periodic(A, at(100), 100.millis(), None);
+
+        // Serviced late, the next deadline stays on the period
+        assert_eq!(timers.fire_due(at(107)), 1);
+        assert_eq!(timers.next_deadline(), Some(at(200)));
+        assert_eq!(timers.fire_due(at(200)), 1);
+        assert_eq!(timers.next_deadline(), Some(at(300)));
+        // Missed periods are skipped
+        assert_eq!(timers.fire_due(at(530)), 1);
+        assert_eq!(timers.next_deadline(), Some(at(600)));
+        assert!(timers.is_running(A));
+        assert!(timers.take_fired(A));
+    }
+
+    #[test]
+    fn next_periods() {
+        let period = 100.millis();
+        assert_eq!(next_period(at(100), period, at(100)), at(200));
+        assert_eq!(next_period(at(100), period, at(199)), at(200));
+        assert_eq!(next_period(at(100), period, at(200)), at(300));
+        assert_eq!(next_period(at(100), period, at(1050)), at(1100));
+        assert_eq!(
+            next_period(at(0), Duration::from_ticks(1), Instant::from_ticks(5)),
+            Instant::from_ticks(6)
+        );
+    }
+
+    #[test]
+    fn cancel() {
+        let mut timers = Timers::<3>::new();
+        timers.start_periodic(A, at(100), 100.millis(), None);
+        timers.start_once(B, at(150), None);
+        timers.fire_due(at(100));
+        timers.cancel(A);
+        assert!(!timers.is_running(A));
+        assert_eq!(timers.next_deadline(), Some(at(150)));
+        assert_eq!(timers.fire_due(at(300)), 1);
+        // The flag raised before the cancel is kept
+        assert!(timers.take_fired(A));
+        assert!(timers.take_fired(B));
+        timers.cancel(B);
+        assert_eq!(timers.next_deadline(), None);
+    }
+
+    #[test]
+    fn full_table() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        fn count() {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut timers = Timers::<3>::new();
+        timers.start_once(A, at(300), Some(count));
+        timers.start_periodic(B, at(100), 50.millis(), Some(count));
+        timers.start_once(C, at(200), None);
+        assert_eq!(timers.next_deadline(), Some(at(100)));
+
+        // Starting a running timer replaces it
+        timers.start_once(C, at(250), None);
+        assert_eq!(timers.fire_due(at(200)), 1);
+        assert!(!timers.take_fired(C));
+        assert_eq!(timers.next_deadline(), Some(at(250)));
+
+        assert_eq!(timers.fire_due(at(300)), 3);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 3);
+        assert!([A, B, C].into_iter().all(|id| timers.take_fired(id)));
+        assert_eq!(
+            [A, B, C].map(|id| timers.is_running(id)),
+            [false, true, false]
+        );
+        assert_eq!(timers.next_deadline(), Some(at(350)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn id_out_of_range() {
+        Timers::<3>::new().start_once(TimerId(3), at(0), None);
+    }
+}
@@ -0,0 +1,403 @@
+//! Touch on the logo and the edge pins 0, 1 and 2.
+//!
+//! Each pad has a 10MΩ pull-up on the board. [`TouchSensor`] discharges a pad,
+//! lets it float and times how long the pull-up takes to charge it back to a
+//! high level. A finger adds capacitance, or on the edge pins a path to GND
+//! through the skin, and the pad charges slower. [`PadFilter`] tracks the
+//! untouched charge time of a pad as its baseline and turns readings well above
+//! it into presses and releases.
+//!
+//! ```ignore
+//! let mut touch = TouchSensor::new(
+//!     board.TIMER2,
+//!     [(Pad::Logo, board.pins.p1_04.degrade()), (Pad::P0, board.pins.p0_02.degrade())],
+//!     TouchConfig::default(),
+//! );
+//!
+//! // every 20ms
+//! for event in touch.scan() {
+//!     defmt::info!("{}", event);
+//! }
+//! ```
+
+use heapless::Vec;
+use microbit::{
+    hal::gpio::{Disconnected, Pin, Port},
+    pac::{self, p0},
+};
+
+use crate::monotonic::Instance32;
+
+/// Fraction bits of [`PadFilter`]'s baseline, so that it can drift slowly.
+const FRACTION: u32 = 4;
+/// About 10µs at 64MHz to empty a pad.
+const DISCHARGE_CYCLES: u32 = 640;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Pad {
+    Logo,
+    P0,
+    P1,
+    P2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum TouchEvent {
+    Pressed(Pad),
+    Released(Pad),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TouchConfig {
+    /// Percent above the baseline for a reading to count as a touch.
+    pub press: u16,
+    /// Percent above the baseline a touched pad has to fall below to be
+    /// released, below `press` so that a reading near the threshold doesn't
+    /// flicker.
+    pub release: u16,
+    /// Readings in a row across a threshold for a press or release.
+    pub confirm: u8,
+    /// How slowly the baseline follows untouched readings, each moves it by
+    /// 1/2^`drift` of the difference. Lower readings are taken at once.
+    pub drift: u8,
+    /// Readings a pad stays touched before the touch is taken for a new
+    /// baseline, 0 to keep touches forever.
+    pub recalibrate: u16,
+    /// Longest charge time in 16MHz ticks, a pad held to GND reads this.
+    pub timeout: u32,
+}
+
+impl Default for TouchConfig {
+    /// About 10s for a stuck touch with a reading every 20ms.
+    fn default() -> Self {
+        TouchConfig {
+            press: 30,
+            release: 15,
+            confirm: 2,
+            drift: 4,
+            recalibrate: 500,
+            timeout: 16_000,
+        }
+    }
+}
+
+/// `percent` above `baseline`.
+fn threshold(baseline: u32, percent: u16) -> u32 {
+    let above = u64::from(baseline) * u64::from(percent) / 100;
+    baseline.saturating_add(above.min(u64::from(u32::MAX)) as u32)
+}
+
+/// Baseline and touch state of one pad.
+#[derive(Debug, Clone, Copy)]
+pub struct PadFilter {
+    /// Untouched charge time with [`FRACTION`] bits, from the first reading on.
+    baseline: Option<u32>,
+    touched: bool,
+    /// Readings in a row across the threshold that changes `touched`.
+    count: u8,
+    /// Readings since the pad was touched.
+    held: u16,
+}
+
+impl PadFilter {
+    pub const fn new() -> PadFilter {
+        PadFilter {
+            baseline: None,
+            touched: false,
+            count: 0,
+            held: 0,
+        }
+    }
+
+    /// Untouched charge time, once there was a reading.
+    pub fn baseline(&self) -> Option<u32> {
+        self.baseline.map(|baseline| baseline >> FRACTION)
+    }
+
+    pub fn is_touched(&self) -> bool {
+        self.touched
+    }
+
+    /// Take a charge time, returns whether the pad is touched if that changed.
+    /// The first reading is taken as the baseline, the pad shouldn't be touched
+    /// then.
+    pub fn update(&mut self, reading: u32, config: &TouchConfig) -> Option<bool> {
+        let scaled = reading.saturating_mul(1 << FRACTION);
+        let Some(baseline) = self.baseline else {
+            self.baseline = Some(scaled);
+            return None;
+        };
+        let base = baseline >> FRACTION;
+
+        if self.touched {
+            self.held = self.held.saturating_add(1);
+            if config.recalibrate != 0 && self.held >= config.recalibrate {
+                // Something else than a finger, start over from here
+                self.baseline = Some(scaled);
+                return self.set(false);
+            }
+            return self.confirm(reading < threshold(base, config.release), config);
+        }
+
+        if reading > threshold(base, config.press) {
+            return self.confirm(true, config);
+        }
+        self.count = 0;
+        self.baseline = Some(if scaled < baseline {
+            scaled
+        } else {
+            baseline + ((scaled - baseline) >> config.drift)
+        });
+        None
+    }
+
+    /// Count a reading `across` the threshold, changes `touched` once there
+    /// were enough in a row.
+    fn confirm(&mut self, across: bool, config: &TouchConfig) -> Option<bool> {
+        if !across {
+            self.count = 0;
+            return None;
+        }
+        self.count = self.count.saturating_add(1);
+        if self.count < config.confirm {
+            return None;
+        }
+        self.set(!self.touched)
+    }
+
+    fn set(&mut self, touched: bool) -> Option<bool> {
+        self.touched = touched;
+        self.count = 0;
+        self.held = 0;
+        Some(touched)
+    }
+}
+
+impl Default for PadFilter {
+    fn default() -> Self {
+        PadFilter::new()
+    }
+}
+
+/// Measures the charge times of `P` pads with a timer of its own.
+pub struct TouchSensor<T: Instance32, const P: usize> {
+    timer: T,
+    config: TouchConfig,
+    pads: [(Pad, Pin<Disconnected>); P],
+    filters: [PadFilter; P],
+    readings: [u32; P],
+}
+
+impl<T: Instance32, const P: usize> TouchSensor<T, P> {
+    /// The pads' pins are the logo P1.04 and pins 0, 1 and 2 P0.02, P0.03 and
+    /// P0.04.
+    pub fn new(timer: T, pads: [(Pad, Pin<Disconnected>); P], config: TouchConfig) -> Self {
+        timer.tasks_stop.write(|w| unsafe { w.bits(1) });
+        timer.mode.write(|w| w.mode().timer());
+        timer.bitmode.write(|w| w.bitmode()._32bit());
+        // 16MHz
+        timer.prescaler.write(|w| unsafe { w.prescaler().bits(0) });
+        TouchSensor {
+            timer,
+            config,
+            pads,
+            filters: [PadFilter::new(); P],
+            readings: [0; P],
+        }
+    }
+
+    /// Measure every pad, returns the pads pressed or released since the last
+    /// scan.
+    pub fn scan(&mut self) -> Vec<TouchEvent, P> {
+        let mut events = Vec::new();
+        for i in 0..P {
+            let reading = self.measure(&self.pads[i].1);
+            self.readings[i] = reading;
+            let pad = self.pads[i].0;
+            match self.filters[i].update(reading, &self.config) {
+                Some(true) => events.push(TouchEvent::Pressed(pad)).unwrap(),
+                Some(false) => events.push(TouchEvent::Released(pad)).unwrap(),
+                None => {}
+            }
+        }
+        events
+    }
+
+    pub fn is_touched(&self, pad: Pad) -> bool {
+        self.index(pad)
+            .is_some_and(|i| self.filters[i].is_touched())
+    }
+
+    /// Last charge time of `pad` in 16MHz ticks, log it with
+    /// [`PadFilter::baseline`] to tune the thresholds.
+    pub fn reading(&self, pad: Pad) -> Option<u32> {
+        self.index(pad).map(|i| self.readings[i])
+    }
+
+    pub fn filter(&self, pad: Pad) -> Option<&PadFilter> {
+        self.index(pad).map(|i| &self.filters[i])
+    }
+
+    /// Stop the timer and hand back the peripherals.
+    pub fn release(self) -> (T, [(Pad, Pin<Disconnected>); P]) {
+        self.timer.tasks_stop.write(|w| unsafe { w.bits(1) });
+        (self.timer, self.pads)
+    }
+
+    fn index(&self, pad: Pad) -> Option<usize> {
+        self.pads.iter().position(|&(p, _)| p == pad)
+    }
+
+    /// Time for `pin` to charge from low to high, at most the timeout.
+    fn measure(&self, pin: &Pin<Disconnected>) -> u32 {
+        let port: &p0::RegisterBlock = match pin.port() {
+            Port::Port0 => unsafe { &*pac::P0::ptr() },
+            Port::Port1 => unsafe { &*pac::P1::ptr() },
+        };
+        let (n, bit) = (usize::from(pin.pin()), 1 << pin.pin());
+        let timer = &self.timer;
+
+        port.outclr.write(|w| unsafe { w.bits(bit) });
+        port.pin_cnf[n].write(|w| w.dir().output());
+        cortex_m::asm::delay(DISCHARGE_CYCLES);
+
+        timer.tasks_clear.write(|w| unsafe { w.bits(1) });
+        port.pin_cnf[n].write(|w| w.dir().input().input().connect().pull().disabled());
+        timer.tasks_start.write(|w| unsafe { w.bits(1) });
+        let ticks = loop {
+            timer.tasks_capture[0].write(|w| unsafe { w.bits(1) });
+            let ticks = timer.cc[0].read().bits();
+            if port.in_.read().bits() & bit != 0 || ticks >= self.config.timeout {
+                break ticks;
+            }
+        };
+        timer.tasks_stop.write(|w| unsafe { w.bits(1) });
+
+        port.pin_cnf[n].write(|w| w.dir().input().input().disconnect());
+        ticks.min(self.config.timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// A filter with a baseline of `baseline`.
+    fn filter(baseline: u32) -> PadFilter {
+        let mut filter = PadFilter::new();
+        assert_eq!(filter.update(baseline, &TouchConfig::default()), None);
+        filter
+    }
+
+    /// Changes while taking `readings`.
+    fn feed(filter: &mut PadFilter, readings: &[u32], config: &TouchConfig) -> Vec<Option<bool>> {
+        readings
+            .iter()
+            .map(|&reading| filter.update(reading, config))
+            .collect()
+    }
+
+    #[test]
+    fn first_reading_is_baseline() {
+        let mut pad = PadFilter::new();
+        assert_eq!(pad.baseline(), None);
+        // Even if it would be a touch
+        assert_eq!(pad.update(5000, &TouchConfig::default()), None);
+        assert_eq!(pad.baseline(), Some(5000));
+        assert!(!pad.is_touched());
+    }
+
+    #[test]
+    fn drift() {
+        let config = TouchConfig::default();
+        let mut pad = filter(1000);
+        // A 16th of the way up each reading
+        pad.update(1160, &config);
+        assert_eq!(pad.baseline(), Some(1010));
+        for _ in 0..200 {
+            pad.update(1160, &config);
+        }
+        assert!((1159..=1160).contains(&pad.baseline().unwrap()));
+        // All the way down at once
+        pad.update(900, &config);
+        assert_eq!(pad.baseline(), Some(900));
+        assert!(!pad.is_touched());
+    }
+
+    #[test]
+    fn press_and_release() {
+        let config = TouchConfig::default();
+        // 30% above the baseline is not a touch yet
+        let mut pad = filter(1000);
+        assert_eq!(feed(&mut pad, &[1300; 4], &config), [None; 4]);
+        assert!(!pad.is_touched());
+
+        let mut pad = filter(1000);
+        let changes = feed(&mut pad, &[1301, 1301, 1150, 1150, 1149, 1149], &config);
+        assert_eq!(changes, [None, Some(true), None, None, None, Some(false)]);
+        // The baseline stays put while touched
+        assert_eq!(pad.baseline(), Some(1000));
+    }
+
+    #[test]
+    fn confirm_counts_in_a_row() {
+        let config = TouchConfig {
+            confirm: 3,
+            ..TouchConfig::default()
+        };
+        let mut pad = filter(1000);
+        let changes = feed(&mut pad, &[1400, 1400, 1000, 1400, 1400, 1400], &config);
+        assert_eq!(changes, [None, None, None, None, None, Some(true)]);
+        let changes = feed(&mut pad, &[1000, 1000, 1400, 1000, 1000, 1000], &config);
+        assert_eq!(changes, [None, None, None, None, None, Some(false)]);
+
+        for confirm in [0, 1] {
+            let config = TouchConfig { confirm, ..config };
+            let mut pad = filter(1000);
+            assert_eq!(pad.update(1400, &config), Some(true));
+            assert_eq!(pad.update(1000, &config), Some(false));
+        }
+    }
+
+    #[test]
+    fn recalibrate_stuck_touch() {
+        let config = TouchConfig {
+            recalibrate: 4,
+            ..TouchConfig::default()
+        };
+        let mut pad = filter(1000);
+        let changes = feed(&mut pad, &[2000; 8], &config);
+        assert_eq!(
+            changes,
+            [None, Some(true), None, None, None, Some(false), None, None]
+        );
+        assert_eq!(pad.baseline(), Some(2000));
+
+        let config = TouchConfig {
+            recalibrate: 0,
+            ..config
+        };
+        let mut pad = filter(1000);
+        for _ in 0..1000 {
+            pad.update(2000, &config);
+        }
+        assert!(pad.is_touched());
+        assert_eq!(pad.baseline(), Some(1000));
+    }
+
+    #[test]
+    fn threshold_saturates() {
+        assert_eq!(threshold(1000, 30), 1300);
+        assert_eq!(threshold(0, 100), 0);
+        assert_eq!(threshold(u32::MAX, 0), u32::MAX);
+        assert_eq!(threshold(u32::MAX, 30), u32::MAX);
+        assert_eq!(threshold(u32::MAX - 10, 1), u32::MAX);
+        assert_eq!(threshold(u32::MAX / 2, u16::MAX), u32::MAX);
+
+        let config = TouchConfig::default();
+        let mut pad = filter(1000);
+        assert_eq!(feed(&mut pad, &[u32::MAX; 2], &config), [None, Some(true)]);
+    }
+}
@@ -0,0 +1,39 @@
+//! Waker storage shared by a future and the interrupt that completes it.
+
+use core::cell::Cell;
+use core::task::Waker;
+
+use cortex_m::interrupt::{self, Mutex};
+
+/// Holds the waker of the one task waiting on a peripheral.
+pub struct WakerCell(Mutex<Cell<Option<Waker>>>);
+
+impl WakerCell {
+    pub const fn new() -> WakerCell {
+        WakerCell(Mutex::new(Cell::new(None)))
+    }
+
+    /// Keep `waker` to be woken by [`WakerCell::wake`], replacing the last one.
+    pub fn register(&self, waker: &Waker) {
+        interrupt::free(|cs| {
+            let cell = self.0.borrow(cs);
+            match cell.take() {
+                Some(old) if old.will_wake(waker) => cell.set(Some(old)),
+                _ => cell.set(Some(waker.clone())),
+            }
+        });
+    }
+
+    /// Wake the registered task, if any.
+    pub fn wake(&self) {
+        if let Some(waker) = interrupt::free(|cs| self.0.borrow(cs).take()) {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for WakerCell {
+    fn default() -> WakerCell {
+        WakerCell::new()
+    }
+}